@@ -0,0 +1,307 @@
+//! Headless subcommands. These run without opening a window.
+use std::{
+    num::NonZeroUsize,
+    path::{Path, PathBuf},
+};
+
+use assets_manager::AssetCache;
+use clap::{Args, Subcommand, ValueEnum};
+use serde::de::DeserializeOwned;
+
+use crate::{
+    data::{Element, LootContext, LootTables, PlanetType, SectorType},
+    overworld::SectorInfo,
+    procgen::{LocationSeed, SectorProcgenLoader, SectorRecipes, MIN_MAP_SIZE},
+    resource::{AssetRoots, Resources},
+    sector::{self, Map, MapMetrics, MapReport, TilesetImage, TILESET_PATH},
+    util::WorldSize,
+};
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Roll a loot table many times and print its drop rates
+    LootReport {
+        /// Name of the table in data/loot_tables.yaml
+        table: String,
+
+        #[clap(long, default_value = "10000")]
+        rolls: NonZeroUsize,
+
+        #[clap(long, value_parser = parse_yaml::<Element>, default_value = "Water")]
+        element: Element,
+
+        #[clap(long, value_parser = parse_yaml::<PlanetType>, default_value = "Barren")]
+        planet_type: PlanetType,
+
+        #[clap(long, default_value_t = 1)]
+        depth: i32,
+    },
+    /// Generate a sector map and save it as a Tiled map (.tmx) or an image (.png)
+    ExportMap {
+        #[clap(flatten)]
+        sector: SectorArgs,
+
+        /// Where to save the map. The extension picks the format.
+        out: PathBuf,
+    },
+    /// Generate a batch of sector maps from consecutive seeds, to tune generators
+    Mapgen {
+        #[clap(flatten)]
+        sector: SectorArgs,
+
+        /// How many maps to generate
+        #[clap(long, default_value_t = 1)]
+        count: u64,
+
+        #[clap(long, value_enum, default_value_t = MapFormat::Ascii)]
+        format: MapFormat,
+
+        /// Also save every step of generation, one directory per map
+        #[clap(long)]
+        history: bool,
+
+        /// Directory to save the maps in
+        #[clap(long, default_value = "mapgen")]
+        out: PathBuf,
+    },
+    /// Generate maps from consecutive seeds and print how their metrics are distributed
+    MapReport {
+        #[clap(flatten)]
+        sector: SectorArgs,
+
+        #[clap(long, default_value_t = 50)]
+        count: u64,
+    },
+}
+
+/// How `mapgen` saves maps
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum MapFormat {
+    /// One line of tile glyphs per row
+    Ascii,
+    Json,
+    Png,
+}
+
+impl MapFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            MapFormat::Ascii => "txt",
+            MapFormat::Json => "json",
+            MapFormat::Png => "png",
+        }
+    }
+}
+
+/// Which sector to generate and what planet it's on
+#[derive(Args)]
+pub struct SectorArgs {
+    /// Name of a generator in data/sector_recipes.yaml
+    #[clap(long, default_value = "bsp")]
+    generator: String,
+
+    #[clap(long, value_parser = parse_size, default_value = "100x100")]
+    size: WorldSize,
+
+    #[clap(long, value_parser = parse_yaml::<SectorType>, default_value = "Barren")]
+    sector_type: SectorType,
+
+    #[clap(long, value_parser = parse_yaml::<Element>, default_value = "Water")]
+    element: Element,
+
+    #[clap(long, value_parser = parse_yaml::<PlanetType>, default_value = "Barren")]
+    planet_type: PlanetType,
+}
+
+impl SectorArgs {
+    fn sector_info(&self, cache: &AssetCache) -> anyhow::Result<SectorInfo> {
+        Ok(SectorInfo::standalone(
+            self.planet_type,
+            self.element,
+            self.sector_type,
+            self.size,
+            cache,
+        )?)
+    }
+
+    /// Generate and populate a map the same way the game does
+    fn generate(
+        &self,
+        resources: &mut Resources,
+        seed: LocationSeed,
+        history: &mut Vec<Map>,
+    ) -> anyhow::Result<Map> {
+        let sector_info = self.sector_info(&resources.assets_cache)?;
+        let mapgen = resources
+            .assets_cache
+            .load::<SectorRecipes>("data.sector_recipes")?
+            .read()
+            .build(&self.generator, self.size, &resources.assets_cache)?;
+
+        let mut world = hecs::World::new();
+        let mut loader = SectorProcgenLoader::new(mapgen, resources, history);
+        Ok(loader.load(&sector_info, seed, &mut world))
+    }
+}
+
+impl Command {
+    pub fn run(self, rng_seed: u64, roots: &AssetRoots) -> anyhow::Result<()> {
+        let mut resources = Resources::headless(rng_seed, roots.clone())?;
+
+        match self {
+            Command::LootReport {
+                table,
+                rolls,
+                element,
+                planet_type,
+                depth,
+            } => {
+                let tables = resources
+                    .assets_cache
+                    .load::<LootTables>("data.loot_tables")?
+                    .read();
+                if tables.get(&table).is_none() {
+                    let names: Vec<_> = tables.names().collect();
+                    anyhow::bail!(
+                        "Unknown loot table {:?} (expected one of {:?})",
+                        table,
+                        names
+                    );
+                }
+
+                let context = LootContext {
+                    element,
+                    planet_type,
+                    depth,
+                };
+                print!(
+                    "{}",
+                    tables.report(&table, &context, rolls.get(), resources.rng.get_rng())
+                );
+            }
+            Command::ExportMap { sector, out } => {
+                let seed = LocationSeed::galaxy(rng_seed);
+                let map = sector.generate(&mut resources, seed, &mut Vec::new())?;
+                export_map(&map, &out, &resources)?;
+            }
+            Command::Mapgen {
+                sector,
+                count,
+                format,
+                history,
+                out,
+            } => {
+                std::fs::create_dir_all(&out)?;
+                let tileset = match format {
+                    MapFormat::Png => Some(TilesetImage::load(&resources.assets_cache)?),
+                    _ => None,
+                };
+                let write = |map: &Map, path: &Path| -> anyhow::Result<()> {
+                    match format {
+                        MapFormat::Ascii => std::fs::write(path, sector::to_ascii(map))?,
+                        MapFormat::Json => std::fs::write(path, serde_json::to_string(map)?)?,
+                        MapFormat::Png => {
+                            sector::to_image(map, tileset.as_ref().unwrap()).save(path)?
+                        }
+                    }
+                    Ok(())
+                };
+
+                // Generator names can have characters that aren't allowed in file names
+                let prefix = sector
+                    .generator
+                    .replace(|c: char| !c.is_alphanumeric(), "_");
+                for seed in rng_seed..rng_seed.saturating_add(count) {
+                    let mut frames = Vec::new();
+                    let map =
+                        sector.generate(&mut resources, LocationSeed::galaxy(seed), &mut frames)?;
+                    let name = format!("{}_{}", prefix, seed);
+                    write(&map, &out.join(&name).with_extension(format.extension()))?;
+
+                    if history {
+                        let dir = out.join(&name);
+                        std::fs::create_dir_all(&dir)?;
+                        for (i, frame) in frames.iter().enumerate() {
+                            let path = dir.join(format!("{:04}.{}", i, format.extension()));
+                            write(frame, &path)?;
+                        }
+                    }
+                }
+                tracing::info!("Saved {} maps to {:?}", count, out);
+            }
+            Command::MapReport { sector, count } => {
+                let mut report = MapReport::new(&sector.generator);
+                for seed in rng_seed..rng_seed.saturating_add(count) {
+                    let fallbacks = resources.mapgen_fallbacks;
+                    let map = sector.generate(
+                        &mut resources,
+                        LocationSeed::galaxy(seed),
+                        &mut Vec::new(),
+                    )?;
+                    report.add(MapMetrics {
+                        fallback: resources.mapgen_fallbacks > fallbacks,
+                        ..MapMetrics::measure(&map, &mut resources.rng)
+                    });
+                }
+                print!("{}", report);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Save a map in the format picked by the file extension
+fn export_map(map: &Map, out: &Path, resources: &Resources) -> anyhow::Result<()> {
+    match out.extension().and_then(|ext| ext.to_str()) {
+        Some("tmx") => {
+            let base = resources
+                .asset_roots
+                .base()
+                .ok_or_else(|| anyhow::anyhow!("Expected a base assets path"))?;
+            let tileset = base.join(TILESET_PATH);
+            std::fs::write(out, sector::to_tmx(map, &tileset.to_string_lossy()))?;
+        }
+        Some("png") => {
+            let tileset = TilesetImage::load(&resources.assets_cache)?;
+            sector::to_image(map, &tileset).save(out)?;
+        }
+        _ => anyhow::bail!("Expected a .tmx or .png file, got {:?}", out),
+    }
+    tracing::info!("Saved {:?}", out);
+    Ok(())
+}
+
+/// Parse a size like `100x80`, no smaller than any generator can make
+fn parse_size(arg: &str) -> Result<WorldSize, String> {
+    let (width, height) = arg
+        .split_once('x')
+        .ok_or_else(|| format!("expected WIDTHxHEIGHT, got {:?}", arg))?;
+    let parse = |value: &str| value.trim().parse::<i32>().map_err(|err| err.to_string());
+    let size = WorldSize::new(parse(width)?, parse(height)?);
+    if size.width < MIN_MAP_SIZE || size.height < MIN_MAP_SIZE {
+        return Err(format!(
+            "expected at least {}x{}, got {:?}",
+            MIN_MAP_SIZE, MIN_MAP_SIZE, arg
+        ));
+    }
+    Ok(size)
+}
+
+/// Parse enum arguments using the same names as the YAML data files
+fn parse_yaml<T: DeserializeOwned>(arg: &str) -> Result<T, String> {
+    serde_yaml::from_str(arg).map_err(|err| err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sizes_fit_every_generator() {
+        assert_eq!(parse_size("100x80"), Ok(WorldSize::new(100, 80)));
+        assert!(parse_size("0x0").is_err());
+        assert!(parse_size("1x100").is_err());
+        assert!(parse_size("100").is_err());
+    }
+}
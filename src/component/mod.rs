@@ -30,5 +30,29 @@ pub struct Door {
     pub opened: bool,
 }
 
+/// Keeps a [`Door`] shut until it's opened with the [`Key`] of the same id
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Locked(pub u32);
+
+/// Opens the [`Locked`] doors with the same id
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Key(pub u32);
+
+/// Ids of the [`Key`]s an entity has picked up
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Keyring(pub Vec<u32>);
+
+/// An item lying in the sector, by name (see [`crate::data::LootTables`])
+#[derive(Clone, Debug, PartialEq)]
+pub struct Item {
+    pub name: String,
+}
+
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct BlocksTile {}
+
+/// Names the loot table rolled when this entity is killed or opened
+#[derive(Clone, Debug, PartialEq)]
+pub struct Loot {
+    pub table: String,
+}
@@ -1,19 +1,45 @@
-use std::borrow::Cow;
+use std::{borrow::Cow, collections::BTreeMap};
 
 use assets_manager::{asset::load_yaml, BoxedError, FileAsset};
 use bracket_random::prelude::{DiceType, RandomNumberGenerator};
 
 use serde::{Deserialize, Serialize};
 
-use crate::{overworld::PlanetInfo, util::OverworldSize};
+use crate::{
+    overworld::PlanetInfo,
+    util::{GalaxySize, OverworldSize},
+};
 
-use super::{ElementProbability, PlanetTypeProbability, SectorProbability};
+use super::{
+    ElementProbability, OrbitZone, PlanetGeneratorProbability, PlanetType, PlanetTypeProbability,
+    SectorProbabilityTable, StarType, StarTypeProbability, ZoneProbability,
+};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GalaxyProbability {
-    planet_count: DiceType,
+    galaxy_width: DiceType,
+    galaxy_height: DiceType,
+    system_count: DiceType,
+    star_type: StarTypeProbability,
+    planets_per_system: DiceType,
+    moons_per_planet: DiceType,
     planet_type: PlanetTypeProbability,
     planet_element: ElementProbability,
+    /// Per-OrbitZone overrides for [`Self::planet_type`] and [`Self::planet_element`]
+    #[serde(default)]
+    orbit_zone: BTreeMap<OrbitZone, ZoneProbability>,
+    planet_generator: PlanetGeneratorProbability,
+    /// Star systems are placed at least this far apart (when there's room)
+    min_system_distance: f32,
+    /// Chance of adding each short lane that isn't needed to connect the galaxy
+    extra_lanes: f32,
+    /// Dice for each planet's width and height (rolled separately)
+    default_planet_size: DiceType,
+    /// Per-PlanetType overrides for [`Self::default_planet_size`]
+    #[serde(default)]
+    planet_size: BTreeMap<PlanetType, DiceType>,
+    /// Dice for each moon's width and height, regardless of PlanetType
+    moon_size: DiceType,
 }
 
 impl FileAsset for GalaxyProbability {
@@ -25,23 +51,73 @@ impl FileAsset for GalaxyProbability {
 }
 
 impl GalaxyProbability {
-    pub fn roll_planet(&self, name: String, rng: &mut RandomNumberGenerator) -> PlanetInfo {
-        // TODO: Configure Planet Size rng
-        let size_dice = DiceType::new(1, 18, 9);
-        let width = rng.roll(size_dice);
-        let height = rng.roll(size_dice);
-        let inner = rng.get_rng();
+    pub fn roll_galaxy_size(&self, rng: &mut RandomNumberGenerator) -> GalaxySize {
+        GalaxySize::new(rng.roll(self.galaxy_width), rng.roll(self.galaxy_height))
+    }
 
-        let planet_type = self.planet_type.next_element(inner);
-        let planet_element = self.planet_element.next_element(inner);
+    pub fn roll_system_count(&self, rng: &mut RandomNumberGenerator) -> i32 {
+        rng.roll(self.system_count)
+    }
 
-        let sector_probability = SectorProbability::new();
+    pub fn roll_star_type(&self, rng: &mut RandomNumberGenerator) -> StarType {
+        self.star_type.next_element(rng.get_rng())
+    }
+
+    pub fn roll_planets_per_system(&self, rng: &mut RandomNumberGenerator) -> i32 {
+        rng.roll(self.planets_per_system).max(1)
+    }
+
+    pub fn roll_moons_per_planet(&self, rng: &mut RandomNumberGenerator) -> i32 {
+        rng.roll(self.moons_per_planet).max(0)
+    }
+
+    pub fn min_system_distance(&self) -> f32 {
+        self.min_system_distance
+    }
+
+    pub fn extra_lanes(&self) -> f32 {
+        self.extra_lanes
+    }
+
+    pub fn planet_size_dice(&self, planet_type: PlanetType) -> DiceType {
+        self.planet_size
+            .get(&planet_type)
+            .copied()
+            .unwrap_or(self.default_planet_size)
+    }
+
+    pub fn roll_planet(
+        &self,
+        name: String,
+        zone: OrbitZone,
+        moon: bool,
+        sector_probability: SectorProbabilityTable,
+        rng: &mut RandomNumberGenerator,
+    ) -> PlanetInfo {
+        let (planet_type, planet_element) = match self.orbit_zone.get(&zone) {
+            Some(zone) => (&zone.planet_type, &zone.planet_element),
+            None => (&self.planet_type, &self.planet_element),
+        };
+
+        let inner = rng.get_rng();
+        let planet_type = planet_type.next_element(inner);
+        let planet_element = planet_element.next_element(inner);
+        let generator = self.planet_generator.next_element(inner);
+
+        let size_dice = if moon {
+            self.moon_size
+        } else {
+            self.planet_size_dice(planet_type)
+        };
+        let width = rng.roll(size_dice);
+        let height = rng.roll(size_dice);
 
         PlanetInfo::new(
             name,
             OverworldSize::new(width, height),
             planet_type,
             planet_element,
+            generator,
             sector_probability,
         )
     }
@@ -49,29 +125,27 @@ impl GalaxyProbability {
 
 #[cfg(test)]
 mod tests {
-    use std::{env, path};
-
-    use assets_manager::AssetCache;
-
-    use crate::game::consts;
+    use crate::resource::test_asset_cache;
 
     use super::*;
 
-    fn get_asset_cache() -> AssetCache {
-        let manifest_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
-        let path = path::PathBuf::from(manifest_dir).join(consts::RESOURCE_PATH);
-        assets_manager::AssetCache::new(path).unwrap()
-    }
-
     #[test]
     fn test() {
-        let cache = get_asset_cache();
+        let cache = test_asset_cache();
 
         let galaxy_probability = cache
             .load::<GalaxyProbability>("data.galaxy_probability")
             .unwrap()
             .read();
 
-        assert_eq!(galaxy_probability.planet_count, DiceType::new(6, 2, 2));
+        assert_eq!(galaxy_probability.system_count, DiceType::new(6, 2, 2));
+        assert_eq!(
+            galaxy_probability.planet_size_dice(PlanetType::Barren),
+            galaxy_probability.default_planet_size
+        );
+        assert_ne!(
+            galaxy_probability.planet_size_dice(PlanetType::Aqueus),
+            galaxy_probability.default_planet_size
+        );
     }
 }
@@ -0,0 +1,297 @@
+use std::{borrow::Cow, collections::BTreeMap, fmt};
+
+use assets_manager::{asset::load_yaml, BoxedError, FileAsset};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use super::{Element, PlanetType, ProbabilityDistribution};
+
+/// Nested tables deeper than this are assumed to be a cycle
+const MAX_LOOT_DEPTH: usize = 16;
+
+/// A single weighted outcome of a loot table roll
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum LootEntry {
+    /// Drop an item by name
+    Item(String),
+    /// Roll another table once
+    Table(String),
+    /// Roll another table several times
+    Rolls { table: String, count: i32 },
+    /// Drop nothing at all
+    Nothing,
+}
+
+/// Restricts when a loot entry can be rolled. Empty lists match everything.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LootCondition {
+    #[serde(default)]
+    pub element: Vec<Element>,
+    #[serde(default)]
+    pub planet_type: Vec<PlanetType>,
+    pub min_depth: Option<i32>,
+    pub max_depth: Option<i32>,
+}
+
+impl LootCondition {
+    pub fn matches(&self, context: &LootContext) -> bool {
+        let element = self.element.is_empty() || self.element.contains(&context.element);
+        let planet_type =
+            self.planet_type.is_empty() || self.planet_type.contains(&context.planet_type);
+        let min_depth = self.min_depth.is_none_or(|min| context.depth >= min);
+        let max_depth = self.max_depth.is_none_or(|max| context.depth <= max);
+
+        element && planet_type && min_depth && max_depth
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WeightedLootEntry {
+    pub weight: f32,
+    pub entry: LootEntry,
+    #[serde(default)]
+    pub when: LootCondition,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LootTable {
+    /// How many times to pick from the entries each time this table is rolled
+    #[serde(default = "LootTable::default_rolls")]
+    pub rolls: i32,
+    pub entries: Vec<WeightedLootEntry>,
+}
+
+impl LootTable {
+    fn default_rolls() -> i32 {
+        1
+    }
+}
+
+/// Where the loot is being rolled. Used to filter conditional entries.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LootContext {
+    pub element: Element,
+    pub planet_type: PlanetType,
+    pub depth: i32,
+}
+
+/// All loot tables by name, loaded from `data/loot_tables.yaml`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LootTables(BTreeMap<String, LootTable>);
+
+impl FileAsset for LootTables {
+    const EXTENSION: &'static str = "yaml";
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Result<Self, BoxedError> {
+        load_yaml(&bytes)
+    }
+}
+
+impl LootTables {
+    pub fn get(&self, name: &str) -> Option<&LootTable> {
+        self.0.get(name)
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &String> {
+        self.0.keys()
+    }
+
+    /// Roll a table and return the names of every dropped item
+    pub fn roll<R: Rng>(&self, name: &str, context: &LootContext, rng: &mut R) -> Vec<String> {
+        let mut drops = Vec::new();
+        self.roll_into(name, context, rng, 0, &mut drops);
+        drops
+    }
+
+    fn roll_into<R: Rng>(
+        &self,
+        name: &str,
+        context: &LootContext,
+        rng: &mut R,
+        depth: usize,
+        drops: &mut Vec<String>,
+    ) {
+        if depth > MAX_LOOT_DEPTH {
+            tracing::warn!(
+                table = name,
+                "Loot tables nested too deeply; is there a cycle?"
+            );
+            return;
+        }
+
+        let Some(table) = self.get(name) else {
+            tracing::warn!(table = name, "Unknown loot table");
+            return;
+        };
+
+        // Only entries that match the context can be rolled. The
+        // distribution maps entry indices to weights.
+        let weights: BTreeMap<usize, f32> = table
+            .entries
+            .iter()
+            .enumerate()
+            .filter(|(_, entry)| entry.weight > 0. && entry.when.matches(context))
+            .map(|(idx, entry)| (idx, entry.weight))
+            .collect();
+
+        if weights.is_empty() {
+            return;
+        }
+        let distribution = ProbabilityDistribution::new(weights);
+
+        for _ in 0..table.rolls {
+            match &table.entries[distribution.next_element(rng)].entry {
+                LootEntry::Item(item) => drops.push(item.clone()),
+                LootEntry::Table(inner) => self.roll_into(inner, context, rng, depth + 1, drops),
+                LootEntry::Rolls { table, count } => {
+                    for _ in 0..*count {
+                        self.roll_into(table, context, rng, depth + 1, drops)
+                    }
+                }
+                LootEntry::Nothing => {}
+            }
+        }
+    }
+
+    /// Roll a table many times and tally the results
+    pub fn report<R: Rng>(
+        &self,
+        name: &str,
+        context: &LootContext,
+        rolls: usize,
+        rng: &mut R,
+    ) -> LootReport {
+        let mut report = LootReport {
+            table: name.to_owned(),
+            rolls,
+            empty: 0,
+            items: BTreeMap::new(),
+        };
+
+        for _ in 0..rolls {
+            let drops = self.roll(name, context, rng);
+            if drops.is_empty() {
+                report.empty += 1;
+            }
+            for item in drops {
+                *report.items.entry(item).or_default() += 1;
+            }
+        }
+
+        report
+    }
+}
+
+/// Drop rates for a loot table, for designers to sanity check
+#[derive(Debug, Clone)]
+pub struct LootReport {
+    pub table: String,
+    pub rolls: usize,
+    /// How many rolls dropped nothing at all
+    pub empty: usize,
+    /// How many of each item dropped across every roll
+    pub items: BTreeMap<String, usize>,
+}
+
+impl fmt::Display for LootReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} x{}", self.table, self.rolls)?;
+        // Rates out of no rolls at all are just 0
+        let rolls = self.rolls.max(1) as f32;
+        writeln!(
+            f,
+            "  {:<24} {:>8} {:>7.2}%",
+            "(nothing)",
+            self.empty,
+            100. * self.empty as f32 / rolls
+        )?;
+
+        let mut items: Vec<_> = self.items.iter().collect();
+        items.sort_by(|a, b| b.1.cmp(a.1));
+        for (item, count) in items {
+            writeln!(
+                f,
+                "  {:<24} {:>8} {:>7.2} per roll",
+                item,
+                count,
+                *count as f32 / rolls
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    fn tables() -> LootTables {
+        serde_yaml::from_str(
+            r###"
+---
+common:
+  entries:
+    - weight: 1.0
+      entry: !Item medkit
+    - weight: 1.0
+      entry: !Nothing
+    - weight: 1.0
+      entry: !Item ember
+      when:
+        element: [Fire]
+rare:
+  rolls: 2
+  entries:
+    - weight: 1.0
+      entry: !Rolls
+        table: common
+        count: 3
+    - weight: 1.0
+      entry: !Table rare
+      when:
+        min_depth: 5
+"###,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn conditions() {
+        let tables = tables();
+        let mut rng = StdRng::seed_from_u64(1);
+        let water = LootContext {
+            element: Element::Water,
+            planet_type: PlanetType::Lush,
+            depth: 1,
+        };
+
+        let report = tables.report("common", &water, 1000, &mut rng);
+        assert!(!report.items.contains_key("ember"));
+        assert!(report.items["medkit"] > 400);
+        assert!(report.empty > 400);
+
+        // No rolls, no rates
+        let report = tables.report("common", &water, 0, &mut rng).to_string();
+        assert!(!report.contains("NaN"));
+    }
+
+    #[test]
+    fn nested_rolls() {
+        let tables = tables();
+        let mut rng = StdRng::seed_from_u64(1);
+        let fire = LootContext {
+            element: Element::Fire,
+            planet_type: PlanetType::Barren,
+            depth: 10,
+        };
+
+        // The self-referencing "rare" table terminates thanks to the depth limit
+        for _ in 0..100 {
+            let drops = tables.roll("rare", &fire, &mut rng);
+            assert!(drops.iter().all(|item| item == "medkit" || item == "ember"));
+        }
+    }
+}
@@ -13,9 +13,21 @@ pub use probability_types::*;
 mod planet;
 pub use planet::*;
 
+mod star;
+pub use star::*;
+
 mod sector;
 pub use sector::*;
 
+mod terrain;
+pub use terrain::*;
+
+mod loot;
+pub use loot::*;
+
+mod prefab;
+pub use prefab::*;
+
 mod markov_seed;
 pub use markov_seed::*;
 
@@ -1,9 +1,10 @@
-use std::borrow::Cow;
+use std::{borrow::Cow, collections::BTreeMap};
 
 use assets_manager::{asset::load_yaml, BoxedError, FileAsset};
+use rand::Rng;
 use serde::{Deserialize, Serialize};
 
-use super::{ProbabilityDistribution, SectorTypeProbability};
+use super::{ProbabilityDistribution, SectorType, SectorTypeProbability};
 
 #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Eq, PartialOrd, Ord)]
 pub enum PlanetType {
@@ -19,10 +20,37 @@ pub enum PlanetType {
 
 pub type PlanetTypeProbability = ProbabilityDistribution<PlanetType>;
 
+/// Which OverworldGenerator creates a planet's surface
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, Eq, PartialOrd, Ord)]
+pub enum PlanetGenerator {
+    /// Independent SectorType rolls for every tile
+    Static,
+    /// Biomes classified from elevation, moisture and temperature noise
+    #[default]
+    Noise,
+}
+
+pub type PlanetGeneratorProbability = ProbabilityDistribution<PlanetGenerator>;
+
 /// Probability definitions for which sectors to create on an overworld
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PlanetProbability {
     sector_type: SectorTypeProbability,
+    /// Per-PlanetType overrides for [`Self::sector_type`]
+    #[serde(default)]
+    planet_type: BTreeMap<PlanetType, SectorTypeProbability>,
+}
+
+impl PlanetProbability {
+    pub fn sector_type_probability(&self, planet_type: PlanetType) -> &SectorTypeProbability {
+        self.planet_type
+            .get(&planet_type)
+            .unwrap_or(&self.sector_type)
+    }
+
+    pub fn roll_sector_type<R: Rng>(&self, planet_type: PlanetType, rng: &mut R) -> SectorType {
+        self.sector_type_probability(planet_type).next_element(rng)
+    }
 }
 
 impl FileAsset for PlanetProbability {
@@ -32,3 +60,41 @@ impl FileAsset for PlanetProbability {
         load_yaml(&bytes)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use crate::{data::SectorProbabilityTable, resource::test_asset_cache};
+
+    use super::*;
+
+    #[test]
+    fn planet_type_overrides() {
+        let cache = test_asset_cache();
+
+        let planet_probability = cache
+            .load::<PlanetProbability>("data.planet_probability")
+            .unwrap()
+            .read();
+
+        // Barren planets never roll liquids
+        let mut rng = StdRng::seed_from_u64(7);
+        for _ in 0..100 {
+            let sector_type = planet_probability.roll_sector_type(PlanetType::Barren, &mut rng);
+            assert_ne!(sector_type, SectorType::ImpassibleLiquid);
+            assert_ne!(sector_type, SectorType::PassibleLiquid);
+        }
+
+        let sector_probability = cache
+            .load::<SectorProbabilityTable>("data.sector_probability")
+            .unwrap()
+            .read();
+        assert!(
+            sector_probability
+                .get(SectorType::Civilization)
+                .monster_density
+                > sector_probability.get(SectorType::Barren).monster_density
+        );
+    }
+}
@@ -0,0 +1,278 @@
+use std::{borrow::Cow, collections::BTreeMap};
+
+use assets_manager::{asset::load_yaml, BoxedError, FileAsset};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    sector::Tile,
+    util::{WorldSize, WorldVector},
+};
+
+use super::{ProbabilityDistribution, SectorType};
+
+/// Cells in a prefab grid that leave the map as it is
+pub const TRANSPARENT: char = ' ';
+
+/// What a legend character spawns on its tile
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PrefabSpawn {
+    /// An item lying on the floor
+    Item(String),
+    /// Something that drops a loot table when opened (see [`super::LootTables`])
+    Container(String),
+    /// A closed door
+    Door,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LegendEntry {
+    /// The tile under the spawn (see [`Tile::from_char`])
+    #[serde(default = "LegendEntry::default_tile")]
+    pub tile: char,
+    pub spawn: PrefabSpawn,
+}
+
+impl LegendEntry {
+    fn default_tile() -> char {
+        '.'
+    }
+}
+
+/// What a prefab's footprint has to be stamped over
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Placement {
+    /// Open ground, eg. a wreck in a crater field
+    Floor,
+    /// Solid rock next to open ground, eg. a room dug into a cave wall
+    Wall,
+}
+
+/// A small hand-authored room, drawn with [`Tile::from_char`] characters.
+/// Spaces leave the map underneath alone.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Prefab {
+    pub grid: String,
+    #[serde(default)]
+    pub legend: BTreeMap<char, LegendEntry>,
+    pub placement: Placement,
+    /// How likely the prefab is in each SectorType. Missing types never get it.
+    pub weight: BTreeMap<SectorType, f32>,
+    #[serde(default = "Prefab::default_true")]
+    pub rotate: bool,
+    #[serde(default = "Prefab::default_true")]
+    pub mirror: bool,
+}
+
+impl Prefab {
+    fn default_true() -> bool {
+        true
+    }
+
+    /// The grid as authored. Short rows are padded with transparent cells.
+    pub fn cells(&self) -> PrefabGrid {
+        let rows: Vec<Vec<char>> = self
+            .grid
+            .lines()
+            .map(|line| line.trim_end().chars().collect())
+            .skip_while(|row: &Vec<char>| row.is_empty())
+            .collect();
+        let height = rows.len() - rows.iter().rev().take_while(|row| row.is_empty()).count();
+        let width = rows.iter().map(|row| row.len()).max().unwrap_or_default();
+
+        let cells = rows[..height]
+            .iter()
+            .flat_map(|row| (0..width).map(|x| row.get(x).copied().unwrap_or(TRANSPARENT)))
+            .collect();
+        PrefabGrid {
+            size: WorldSize::new(width as i32, height as i32),
+            cells,
+        }
+    }
+
+    /// Every distinct rotation and reflection allowed for this prefab
+    pub fn variants(&self) -> Vec<PrefabGrid> {
+        let mut variants = vec![self.cells()];
+        if self.mirror {
+            variants.push(variants[0].mirrored());
+        }
+        if self.rotate {
+            for i in 0..variants.len() {
+                let mut grid = variants[i].clone();
+                for _ in 0..3 {
+                    grid = grid.rotated();
+                    variants.push(grid.clone());
+                }
+            }
+        }
+
+        let mut distinct: Vec<PrefabGrid> = Vec::with_capacity(variants.len());
+        for grid in variants {
+            if !distinct.contains(&grid) {
+                distinct.push(grid);
+            }
+        }
+        distinct
+    }
+
+    /// The tile a grid character stamps, or None for transparent cells
+    pub fn tile(&self, c: char) -> Option<Tile> {
+        if c == TRANSPARENT {
+            return None;
+        }
+        match self.legend.get(&c) {
+            Some(entry) => Tile::from_char(entry.tile),
+            None => Tile::from_char(c),
+        }
+    }
+
+    pub fn spawn(&self, c: char) -> Option<&PrefabSpawn> {
+        self.legend.get(&c).map(|entry| &entry.spawn)
+    }
+}
+
+/// A prefab's characters laid out in rows
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrefabGrid {
+    pub size: WorldSize,
+    cells: Vec<char>,
+}
+
+impl PrefabGrid {
+    pub fn get(&self, x: i32, y: i32) -> char {
+        self.cells[(y * self.size.width + x) as usize]
+    }
+
+    /// Every non-transparent cell and its offset from the top left
+    pub fn iter(&self) -> impl Iterator<Item = (WorldVector, char)> + '_ {
+        (0..self.size.height)
+            .flat_map(move |y| (0..self.size.width).map(move |x| (x, y)))
+            .map(|(x, y)| (WorldVector::new(x, y), self.get(x, y)))
+            .filter(|(_, c)| *c != TRANSPARENT)
+    }
+
+    /// Quarter turn clockwise
+    pub fn rotated(&self) -> PrefabGrid {
+        let size = WorldSize::new(self.size.height, self.size.width);
+        let cells = (0..size.height)
+            .flat_map(|y| (0..size.width).map(move |x| (x, y)))
+            .map(|(x, y)| self.get(y, self.size.height - 1 - x))
+            .collect();
+        PrefabGrid { size, cells }
+    }
+
+    /// Flipped left to right
+    pub fn mirrored(&self) -> PrefabGrid {
+        let cells = (0..self.size.height)
+            .flat_map(|y| (0..self.size.width).map(move |x| (x, y)))
+            .map(|(x, y)| self.get(self.size.width - 1 - x, y))
+            .collect();
+        PrefabGrid {
+            size: self.size,
+            cells,
+        }
+    }
+}
+
+/// Every prefab by name, loaded from `data/prefabs.yaml`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Prefabs {
+    /// How many prefabs a sector tries to fit
+    #[serde(default)]
+    pub per_sector: usize,
+    pub prefabs: BTreeMap<String, Prefab>,
+}
+
+impl FileAsset for Prefabs {
+    const EXTENSION: &'static str = "yaml";
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Result<Self, BoxedError> {
+        load_yaml(&bytes)
+    }
+}
+
+impl Prefabs {
+    pub fn get(&self, name: &str) -> Option<&Prefab> {
+        self.prefabs.get(name)
+    }
+
+    /// Pick a prefab for a sector by weight. None if no prefab fits the type.
+    pub fn choose<R: Rng>(&self, sector_type: SectorType, rng: &mut R) -> Option<&str> {
+        let weights: BTreeMap<&str, f32> = self
+            .prefabs
+            .iter()
+            .filter_map(|(name, prefab)| {
+                let weight = *prefab.weight.get(&sector_type)?;
+                (weight > 0.).then_some((name.as_str(), weight))
+            })
+            .collect();
+        if weights.is_empty() {
+            return None;
+        }
+        Some(ProbabilityDistribution::new(weights).next_element(rng))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use crate::resource::test_asset_cache;
+
+    use super::*;
+
+    fn prefab(grid: &str) -> Prefab {
+        Prefab {
+            grid: grid.to_owned(),
+            legend: BTreeMap::new(),
+            placement: Placement::Floor,
+            weight: BTreeMap::from([(SectorType::Barren, 1.0)]),
+            rotate: true,
+            mirror: true,
+        }
+    }
+
+    #[test]
+    fn rotations() {
+        let grid = prefab("\n##.\n#  \n").cells();
+        assert_eq!(grid.size, WorldSize::new(3, 2));
+        assert_eq!(grid.iter().count(), 4);
+
+        let rotated = grid.rotated();
+        assert_eq!(rotated.size, WorldSize::new(2, 3));
+        assert_eq!(rotated.get(0, 0), '#');
+        assert_eq!(rotated.get(1, 2), '.');
+        assert_eq!(rotated.get(0, 2), TRANSPARENT);
+        assert_eq!(grid.rotated().rotated().rotated().rotated(), grid);
+        assert_eq!(grid.mirrored().get(0, 0), '.');
+
+        // An L has all eight orientations, a square only one
+        assert_eq!(prefab("##.\n#..").variants().len(), 8);
+        assert_eq!(prefab("##\n##").variants().len(), 1);
+    }
+
+    #[test]
+    fn prefabs_asset() {
+        let cache = test_asset_cache();
+        let prefabs = cache.load::<Prefabs>("data.prefabs").unwrap().read();
+
+        for (name, prefab) in prefabs.prefabs.iter() {
+            let grid = prefab.cells();
+            for x in 0..grid.size.width {
+                for y in 0..grid.size.height {
+                    let c = grid.get(x, y);
+                    assert!(
+                        c == TRANSPARENT || prefab.tile(c).is_some(),
+                        "{} has no tile for {:?}",
+                        name,
+                        c
+                    );
+                }
+            }
+        }
+
+        let mut rng = StdRng::seed_from_u64(1);
+        let name = prefabs.choose(SectorType::Civilization, &mut rng).unwrap();
+        assert!(prefabs.get(name).unwrap().weight[&SectorType::Civilization] > 0.);
+    }
+}
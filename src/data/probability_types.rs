@@ -17,6 +17,15 @@ where
         Self { items }
     }
 
+    /// The chance of rolling an item, from 0 to 1
+    pub fn probability(&self, item: &T) -> f32 {
+        let total: f32 = self.items.values().sum();
+        match self.items.get(item) {
+            Some(weight) if total > 0. => weight / total,
+            _ => 0.,
+        }
+    }
+
     pub fn next_element<R: Rng>(&self, rng: &mut R) -> T {
         let dist =
             WeightedAliasIndex::new(self.items.iter().map(|item| *(item.1)).collect()).unwrap();
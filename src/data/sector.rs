@@ -1,6 +1,7 @@
-use std::borrow::Cow;
+use std::{borrow::Cow, collections::BTreeMap};
 
 use assets_manager::{asset::load_yaml, BoxedError, FileAsset};
+use bracket_random::prelude::DiceType;
 use serde::{Deserialize, Serialize};
 
 use super::ProbabilityDistribution;
@@ -24,16 +25,32 @@ pub type SectorTypeProbability = ProbabilityDistribution<SectorType>;
 /// Probability definitions for what to create in each sector
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SectorProbability {
-    // TODO: not sure what is needed in sector probability (items? level distribution?)
+    /// Chance for each floor tile to spawn a monster at difficulty 1
+    pub monster_density: f32,
+    /// Chance for each floor tile to spawn an item
+    pub item_density: f32,
+    /// Difficulty level rolled when the sector is created. Multiplies
+    /// `monster_density` and is the depth of the sector's loot rolls.
+    pub difficulty: DiceType,
+    /// Weighted map generator names (eg. "bsp", "wfc:cave")
+    pub generator: ProbabilityDistribution<String>,
 }
 
-impl SectorProbability {
-    pub fn new() -> Self {
-        Self {}
+/// SectorProbability definitions for each SectorType
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SectorProbabilityTable {
+    default: SectorProbability,
+    #[serde(default)]
+    sector_type: BTreeMap<SectorType, SectorProbability>,
+}
+
+impl SectorProbabilityTable {
+    pub fn get(&self, sector_type: SectorType) -> &SectorProbability {
+        self.sector_type.get(&sector_type).unwrap_or(&self.default)
     }
 }
 
-impl FileAsset for SectorProbability {
+impl FileAsset for SectorProbabilityTable {
     const EXTENSION: &'static str = "yaml";
 
     fn from_bytes(bytes: Cow<[u8]>) -> Result<Self, BoxedError> {
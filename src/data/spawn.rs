@@ -1,3 +1,7 @@
+use std::borrow::Cow;
+
+use assets_manager::{asset::load_yaml, BoxedError, FileAsset};
+use bracket_random::prelude::RandomNumberGenerator;
 use hecs::{Entity, World};
 use rgb::RGBA8;
 use serde::Deserialize;
@@ -5,12 +9,13 @@ use serde::Deserialize;
 use crate::{
     camera::Glyph,
     component::{
-        Actor as ActorComponent, ActorKind, InitialBehavior, Player, Position, Renderable, Viewshed,
+        Actor as ActorComponent, ActorKind, InitialBehavior, Loot, Player, Position, Renderable,
+        Viewshed,
     },
     util::WorldPoint,
 };
 
-#[derive(Debug, PartialEq, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize)]
 pub struct Actor {
     name: String,
     glyph: char,
@@ -23,6 +28,10 @@ pub struct Actor {
     movement_cost: i32,
     behavior: InitialBehavior,
 
+    /// Name of the loot table dropped on death (see [`super::LootTables`])
+    #[serde(default)]
+    loot: Option<String>,
+
     #[serde(default)]
     is_player: bool,
 }
@@ -64,19 +73,66 @@ impl Spawnable for Actor {
                 self.energy_capacity,
                 self.movement_cost,
                 1,
-                ActorKind::Player(None),
+                ActorKind::Computer(None),
             );
 
             let behavior = self.behavior;
-            world.spawn((position, renderable, viewshed, actor, behavior))
+            let entity = world.spawn((position, renderable, viewshed, actor, behavior));
+            if let Some(table) = self.loot {
+                world.insert_one(entity, Loot { table }).unwrap();
+            }
+            entity
         }
     }
 }
 
+/// Every monster that can roam a sector, loaded from `data/monsters.yaml`
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(transparent)]
+pub struct Monsters(pub Vec<Actor>);
+
+impl FileAsset for Monsters {
+    const EXTENSION: &'static str = "yaml";
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Result<Self, BoxedError> {
+        load_yaml(&bytes)
+    }
+}
+
+impl Monsters {
+    /// Spawn a monster picked at random. Returns None if there are none.
+    pub fn spawn_random(
+        &self,
+        point: WorldPoint,
+        world: &mut World,
+        rng: &mut RandomNumberGenerator,
+    ) -> Option<Entity> {
+        let monster = rng.random_slice_entry(&self.0)?;
+        Some(monster.clone().into_spawn(point, world))
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use crate::resource::test_asset_cache;
+
     use super::*;
 
+    #[test]
+    fn monsters_spawn() {
+        let cache = test_asset_cache();
+        let monsters = cache.load::<Monsters>("data.monsters").unwrap().read();
+        assert!(!monsters.0.is_empty());
+
+        let mut world = World::new();
+        let mut rng = RandomNumberGenerator::seeded(1);
+        let monster = monsters
+            .spawn_random(WorldPoint::new(1, 1), &mut world, &mut rng)
+            .unwrap();
+        assert!(world.get::<&Loot>(monster).is_ok());
+        assert!(world.get::<&Player>(monster).is_err());
+    }
+
     #[test]
     fn spawn() {
         let yaml = r###"
@@ -99,11 +155,13 @@ mod tests {
   energy_capacity: 100
   movement_cost: 20
   behavior: !FollowNearest
+  loot: common_monster
 "###;
 
         let values: Vec<SpawnEntry> = serde_yaml::from_str(yaml).unwrap();
         let SpawnEntry::Actor(actor) = &values[0];
         assert_eq!(&actor.name, "Kobold");
         assert_eq!(actor.behavior, InitialBehavior::FollowNearest);
+        assert_eq!(actor.loot.as_deref(), Some("common_monster"));
     }
 }
@@ -0,0 +1,83 @@
+use serde::{Deserialize, Serialize};
+
+use super::{ElementProbability, PlanetTypeProbability, ProbabilityDistribution};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Eq, PartialOrd, Ord)]
+pub enum StarType {
+    /// Small, dim and common. Planets have to huddle close.
+    RedDwarf,
+    /// Sun-like
+    YellowDwarf,
+    /// A dead star's core. Barely any light.
+    WhiteDwarf,
+    /// Huge and bright. The habitable zone is far out.
+    BlueGiant,
+}
+
+pub type StarTypeProbability = ProbabilityDistribution<StarType>;
+
+impl StarType {
+    /// Luminosity relative to the sun
+    pub fn luminosity(&self) -> f32 {
+        match self {
+            StarType::RedDwarf => 0.04,
+            StarType::YellowDwarf => 1.,
+            StarType::WhiteDwarf => 0.01,
+            StarType::BlueGiant => 25.,
+        }
+    }
+
+    /// Orbit distance (in AU) that gets as much light as Earth does from the sun
+    pub fn habitable_orbit(&self) -> f32 {
+        self.luminosity().sqrt()
+    }
+}
+
+/// How much starlight a body gets, which decides what kind of planet it can be
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Eq, PartialOrd, Ord)]
+pub enum OrbitZone {
+    Hot,
+    Temperate,
+    Cold,
+}
+
+impl OrbitZone {
+    pub fn from_orbit(star: StarType, orbit: f32) -> Self {
+        let relative = orbit / star.habitable_orbit();
+        if relative < 0.75 {
+            OrbitZone::Hot
+        } else if relative <= 1.6 {
+            OrbitZone::Temperate
+        } else {
+            OrbitZone::Cold
+        }
+    }
+}
+
+/// PlanetType and Element probabilities for bodies in an OrbitZone
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ZoneProbability {
+    pub planet_type: PlanetTypeProbability,
+    pub planet_element: ElementProbability,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zones_scale_with_luminosity() {
+        assert_eq!(
+            OrbitZone::from_orbit(StarType::YellowDwarf, 1.),
+            OrbitZone::Temperate
+        );
+        assert_eq!(
+            OrbitZone::from_orbit(StarType::RedDwarf, 1.),
+            OrbitZone::Cold
+        );
+        assert_eq!(
+            OrbitZone::from_orbit(StarType::BlueGiant, 1.),
+            OrbitZone::Hot
+        );
+    }
+}
@@ -0,0 +1,103 @@
+use std::{borrow::Cow, collections::BTreeMap};
+
+use assets_manager::{asset::load_yaml, BoxedError, FileAsset};
+use serde::{Deserialize, Serialize};
+
+use super::{Element, PlanetType};
+
+/// How much of a planet is ocean and mountains
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TerrainShape {
+    /// Fraction of tiles below sea level
+    pub ocean: f32,
+    /// Fraction of land tiles that are mountains
+    pub mountains: f32,
+    /// Added to the moisture field
+    #[serde(default)]
+    pub moisture: f32,
+}
+
+impl Default for TerrainShape {
+    fn default() -> Self {
+        Self {
+            ocean: 0.3,
+            mountains: 0.1,
+            moisture: 0.,
+        }
+    }
+}
+
+/// Climate biases applied by a planet's Element
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct TerrainClimate {
+    #[serde(default)]
+    pub temperature: f32,
+    #[serde(default)]
+    pub moisture: f32,
+}
+
+/// Parameters for cities, roads and rivers drawn over the terrain
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct FeatureConfig {
+    /// Minimum distance between cities (in overworld tiles)
+    pub city_spacing: i32,
+    pub max_cities: usize,
+    /// Rivers per land tile
+    pub rivers: f32,
+    /// Rivers that don't reach water within this many tiles are dropped
+    pub max_river_length: usize,
+}
+
+impl Default for FeatureConfig {
+    fn default() -> Self {
+        Self {
+            city_spacing: 4,
+            max_cities: 8,
+            rivers: 0.01,
+            max_river_length: 40,
+        }
+    }
+}
+
+/// Parameters for noise-based overworld terrain, loaded from `data/terrain.yaml`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TerrainConfig {
+    /// Noise frequency in overworld tiles. Lower values make larger features.
+    pub frequency: f32,
+    pub octaves: i32,
+    /// Fraction of ocean tiles (closest to the coast) that are passable
+    pub shallows: f32,
+    /// Land wetter than this becomes jungle
+    pub jungle_moisture: f32,
+    /// Land colder than this is always barren
+    pub frozen_temperature: f32,
+    /// How much colder each unit of elevation above sea level is
+    pub lapse_rate: f32,
+    #[serde(default)]
+    pub features: FeatureConfig,
+    #[serde(default)]
+    pub planet_type: BTreeMap<PlanetType, TerrainShape>,
+    #[serde(default)]
+    pub element: BTreeMap<Element, TerrainClimate>,
+}
+
+impl TerrainConfig {
+    pub fn shape(&self, planet_type: PlanetType) -> TerrainShape {
+        self.planet_type
+            .get(&planet_type)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    pub fn climate(&self, element: Element) -> TerrainClimate {
+        self.element.get(&element).copied().unwrap_or_default()
+    }
+}
+
+impl FileAsset for TerrainConfig {
+    const EXTENSION: &'static str = "yaml";
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Result<Self, BoxedError> {
+        load_yaml(&bytes)
+    }
+}
@@ -1,7 +1,19 @@
+use std::{io::Read, path::Path};
+
 use anyhow::anyhow;
+use assets_manager::AssetCache;
 use macroquad::prelude::*;
 
-use crate::{game::consts, util::PixelPoint};
+use crate::{resource::read_asset_path, util::PixelPoint};
+
+/// Load an image through the asset cache as a texture with crispy pixels
+pub fn load_texture(cache: &AssetCache, path: &Path) -> anyhow::Result<Texture2D> {
+    let mut bytes = Vec::new();
+    read_asset_path(cache, path)?.read_to_end(&mut bytes)?;
+    let texture = Texture2D::from_file_with_format(&bytes, None);
+    texture.set_filter(FilterMode::Nearest);
+    Ok(texture)
+}
 
 pub struct Tileset {
     tileset: tiled::Tileset,
@@ -9,27 +21,17 @@ pub struct Tileset {
 }
 
 impl Tileset {
-    pub async fn try_from_file(path: &str) -> anyhow::Result<Self> {
-        // TODO: Android/WASM compat for tileset path loading. Use the util library?
-        let tileset_path = format!("{}/{}", consts::RESOURCE_PATH, path);
-        let tileset = tiled::Loader::new().load_tsx_tileset(tileset_path)?;
-        let tileset_image_path = tileset
+    /// Load a .tsx tileset and its image through the asset cache, so that
+    /// mods can replace either
+    pub fn load(cache: &AssetCache, path: &str) -> anyhow::Result<Self> {
+        let mut loader = tiled::Loader::with_reader(|path: &Path| read_asset_path(cache, path));
+        let tileset = loader.load_tsx_tileset(path)?;
+        let image_path = &tileset
             .image
-            .to_owned()
+            .as_ref()
             .ok_or_else(|| anyhow!("Expected an image for the tileset"))?
             .source;
-
-        let texture_path = tileset_image_path
-            .strip_prefix(consts::RESOURCE_PATH)
-            .unwrap_or(&tileset_image_path);
-        let path_str = texture_path
-            .to_str()
-            .ok_or_else(|| anyhow!("Expected path to be string-able"))?;
-
-        let texture = load_texture(path_str).await?;
-
-        // crispy pixels please
-        texture.set_filter(FilterMode::Nearest);
+        let texture = load_texture(cache, image_path)?;
 
         Ok(Tileset { tileset, texture })
     }
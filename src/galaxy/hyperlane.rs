@@ -0,0 +1,69 @@
+use pathfinding::prelude::dijkstra;
+
+use crate::util::GalaxyPoint;
+
+/// Travel lanes between star systems. Lanes are undirected.
+#[derive(Debug, Clone, Default)]
+pub struct Hyperlanes {
+    lanes: Vec<(GalaxyPoint, GalaxyPoint)>,
+}
+
+impl Hyperlanes {
+    pub fn new(lanes: Vec<(GalaxyPoint, GalaxyPoint)>) -> Self {
+        Self { lanes }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &(GalaxyPoint, GalaxyPoint)> {
+        self.lanes.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.lanes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lanes.is_empty()
+    }
+
+    pub fn contains(&self, a: &GalaxyPoint, b: &GalaxyPoint) -> bool {
+        self.lanes
+            .iter()
+            .any(|(x, y)| (x == a && y == b) || (x == b && y == a))
+    }
+
+    /// Every system with a lane to `point`
+    pub fn neighbors(&self, point: &GalaxyPoint) -> Vec<GalaxyPoint> {
+        self.lanes
+            .iter()
+            .filter_map(|(a, b)| {
+                if a == point {
+                    Some(*b)
+                } else if b == point {
+                    Some(*a)
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Shortest route (by lane length) between two systems, including both ends
+    pub fn route(&self, from: &GalaxyPoint, to: &GalaxyPoint) -> Option<Vec<GalaxyPoint>> {
+        dijkstra(
+            from,
+            |point| {
+                self.neighbors(point)
+                    .into_iter()
+                    .map(|next| (next, lane_cost(point, &next)))
+                    .collect::<Vec<_>>()
+            },
+            |point| point == to,
+        )
+        .map(|(route, _cost)| route)
+    }
+}
+
+/// Lane length in hundredths of a galaxy tile so that it can be summed as an integer
+pub fn lane_cost(a: &GalaxyPoint, b: &GalaxyPoint) -> i32 {
+    (a.to_f32().distance_to(b.to_f32()) * 100.).round() as i32
+}
@@ -1,3 +1,9 @@
+mod hyperlane;
+pub use hyperlane::*;
+
+mod star_system;
+pub use star_system::*;
+
 use std::{cell::RefCell, collections::HashMap, rc::Rc};
 
 use serde::{Deserialize, Serialize};
@@ -9,86 +15,116 @@ use crate::{
     util::{GalaxyPoint, GalaxySize},
 };
 
-pub type PlanetInfoVec = Vec<(GalaxyPoint, PlanetInfo)>;
-pub type PlanetMap = HashMap<GalaxyPoint, Rc<RefCell<Overworld>>>;
+pub type StarSystemVec = Vec<(GalaxyPoint, StarSystem)>;
+/// Generated Overworlds by star system and body index
+pub type PlanetMap = HashMap<(GalaxyPoint, usize), Rc<RefCell<Overworld>>>;
 
 /// A galaxy contains coordinates pointing to each star system
 // #[derive(Serialize, Deserialize)]
 pub struct Galaxy {
     info: GalaxyInfo,
-    planet_infos: PlanetInfoVec,
+    systems: StarSystemVec,
     planet_map: PlanetMap,
+    lanes: Hyperlanes,
 }
 
-/// Galaxy contains a list of different planets
+/// Galaxy contains a list of star systems, each with a few planets
 ///
-/// The planets aren't indexed, so searching for a planet is linear/not-constant
-/// time. We're talking probably 10 or so planets, so it's fine. Just use
-/// [`Self::iter_content`] to search.
+/// The systems aren't indexed, so searching for a system is linear/not-constant
+/// time. We're talking probably 10 or so systems, so it's fine. Just use
+/// [`Self::iter_systems`] to search.
 impl Galaxy {
     pub fn from_size(info: GalaxyInfo) -> Self {
-        let planets = Vec::new();
+        let systems = Vec::new();
         let planet_map = HashMap::new();
 
-        Self::new(info, planets, planet_map)
+        Self::new(info, systems, planet_map)
     }
 
-    pub fn new(info: GalaxyInfo, planets: PlanetInfoVec, planet_map: PlanetMap) -> Self {
+    pub fn new(info: GalaxyInfo, systems: StarSystemVec, planet_map: PlanetMap) -> Self {
         Self {
             info,
-            planet_infos: planets,
+            systems,
             planet_map,
+            lanes: Hyperlanes::default(),
         }
     }
 
-    pub fn with_planet_infos(mut self, planets: PlanetInfoVec) -> Self {
-        self.planet_infos.extend(planets);
+    pub fn with_systems(mut self, systems: StarSystemVec) -> Self {
+        self.systems.extend(systems);
+        self
+    }
+
+    pub fn with_lanes(mut self, lanes: Hyperlanes) -> Self {
+        self.lanes = lanes;
         self
     }
 
+    pub fn lanes(&self) -> &Hyperlanes {
+        &self.lanes
+    }
+
+    /// Systems reachable from `point` in a single jump
+    pub fn neighbors(&self, point: &GalaxyPoint) -> Vec<GalaxyPoint> {
+        self.lanes.neighbors(point)
+    }
+
+    /// Shortest route along the hyperlanes, including both ends
+    pub fn route(&self, from: &GalaxyPoint, to: &GalaxyPoint) -> Option<Vec<GalaxyPoint>> {
+        self.lanes.route(from, to)
+    }
+
     pub fn info(&self) -> &GalaxyInfo {
         &self.info
     }
 
     /// The entire galaxy is always populated with all possible points and their
-    /// corresponding PlanetInfos
-    pub fn iter_planet_infos(&self) -> impl Iterator<Item = &(GalaxyPoint, PlanetInfo)> {
-        // TODO: enforce no duplicate planets at same coordinates
-        self.planet_infos.iter()
+    /// corresponding StarSystems
+    pub fn iter_systems(&self) -> impl Iterator<Item = &(GalaxyPoint, StarSystem)> {
+        self.systems.iter()
+    }
+
+    pub fn system(&self, point: &GalaxyPoint) -> Option<&StarSystem> {
+        self.systems
+            .iter()
+            .find(|(p, _)| p == point)
+            .map(|(_, system)| system)
     }
 
-    pub fn set_planet(&mut self, point: GalaxyPoint, overworld: Overworld) {
+    pub fn set_planet(&mut self, point: GalaxyPoint, body: usize, overworld: Overworld) {
         // TODO: assert galaxy point is in Galaxy rect
         self.planet_map
-            .insert(point, Rc::new(RefCell::new(overworld)));
+            .insert((point, body), Rc::new(RefCell::new(overworld)));
     }
 
-    pub fn get_planet(&self, point: &GalaxyPoint) -> Option<Rc<RefCell<Overworld>>> {
-        self.planet_map.get(point).cloned()
+    pub fn get_planet(&self, point: &GalaxyPoint, body: usize) -> Option<Rc<RefCell<Overworld>>> {
+        self.planet_map.get(&(*point, body)).cloned()
     }
 
-    /// Use procgen to create a new Sector at a given overworld grid point
+    /// Use procgen to create the Overworld for a body in a star system
     pub fn create_planet<'a, T: OverworldGenerator>(
         &mut self,
         point: &GalaxyPoint,
+        body: usize,
         loader: &mut OverworldProcgenLoader<'a, T>,
     ) -> Rc<RefCell<Overworld>> {
-        let planet_info = self.find(point).expect("planet should have an info");
+        let planet_info = self.find(point, body).expect("planet should have an info");
         let overworld = loader.load(planet_info);
 
-        self.set_planet(*point, overworld);
-        self.get_planet(point).unwrap()
+        self.set_planet(*point, body, overworld);
+        self.get_planet(point, body).unwrap()
     }
 
-    pub fn iter_planets(&self) -> impl Iterator<Item = (&GalaxyPoint, &Rc<RefCell<Overworld>>)> {
+    pub fn iter_planets(
+        &self,
+    ) -> impl Iterator<Item = (&(GalaxyPoint, usize), &Rc<RefCell<Overworld>>)> {
         self.planet_map.iter()
     }
 
-    pub fn find(&self, point: &GalaxyPoint) -> Option<PlanetInfo> {
-        self.planet_infos
-            .iter()
-            .find(|(p, _)| p == point)
-            .map(|(_, overworld)| overworld.clone())
+    pub fn find(&self, point: &GalaxyPoint, body: usize) -> Option<PlanetInfo> {
+        self.system(point)
+            .and_then(|system| system.body(body))
+            .map(|body| body.info.clone())
     }
 }
 
@@ -115,4 +151,8 @@ impl GalaxyInfo {
     pub fn height(&self) -> i32 {
         self.size.height
     }
+
+    pub fn size(&self) -> GalaxySize {
+        self.size
+    }
 }
@@ -0,0 +1,91 @@
+use std::fmt;
+
+use macroquad::prelude::Color;
+
+use crate::{
+    color::{COMMON, FIRE, WATER},
+    data::{OrbitZone, StarType, Tileset},
+    overworld::PlanetInfo,
+    util::PixelPoint,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BodyKind {
+    Planet,
+    /// A moon orbiting the body at the given index
+    Moon {
+        planet: usize,
+    },
+}
+
+/// A planet or moon that can be landed on
+#[derive(Debug, Clone)]
+pub struct OrbitalBody {
+    pub info: PlanetInfo,
+    /// Distance from the star in AU
+    pub orbit: f32,
+    pub zone: OrbitZone,
+    pub kind: BodyKind,
+}
+
+impl OrbitalBody {
+    pub fn is_moon(&self) -> bool {
+        matches!(self.kind, BodyKind::Moon { .. })
+    }
+}
+
+/// A star and everything orbiting it. Bodies are sorted by orbit, with moons
+/// directly after their planet.
+#[derive(Debug, Clone)]
+pub struct StarSystem {
+    pub name: String,
+    pub star: StarType,
+    bodies: Vec<OrbitalBody>,
+}
+
+impl StarSystem {
+    pub fn new(name: String, star: StarType, bodies: Vec<OrbitalBody>) -> Self {
+        Self { name, star, bodies }
+    }
+
+    pub fn bodies(&self) -> &[OrbitalBody] {
+        &self.bodies
+    }
+
+    pub fn body(&self, idx: usize) -> Option<&OrbitalBody> {
+        self.bodies.get(idx)
+    }
+
+    /// Moons orbiting the body at `planet`
+    pub fn moons(&self, planet: usize) -> impl Iterator<Item = &OrbitalBody> {
+        self.bodies
+            .iter()
+            .filter(move |body| body.kind == BodyKind::Moon { planet })
+    }
+
+    pub fn color(&self) -> Color {
+        match self.star {
+            StarType::RedDwarf => FIRE.four,
+            StarType::YellowDwarf => FIRE.five,
+            StarType::WhiteDwarf => COMMON.five,
+            StarType::BlueGiant => WATER.five,
+        }
+    }
+
+    pub fn draw(&self, point: PixelPoint, tileset: &Tileset, scale_mod: Option<f32>) {
+        let size = (16. * scale_mod.unwrap_or(1.)).round() as u8;
+        tileset.draw(265, point, Some(self.color()), Some(size), false);
+    }
+}
+
+impl fmt::Display for StarSystem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} ({:?}, {} bodies)",
+            self.name,
+            self.star,
+            self.bodies.len()
+        )
+    }
+}
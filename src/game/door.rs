@@ -0,0 +1,113 @@
+use hecs::{Entity, World};
+
+use crate::{
+    component::{BlocksTile, Door, Key, Keyring, Locked, Position},
+    util::WorldPoint,
+};
+
+/// Open the closed [`Door`]s at `point`, unless they're [`Locked`] and
+/// `opener` doesn't hold the key. Returns true if a door was in the way,
+/// opened or not, so bumping into a door takes the move.
+pub fn open_doors(world: &mut World, opener: Entity, point: WorldPoint) -> bool {
+    let held = world
+        .get::<&Keyring>(opener)
+        .map(|keyring| keyring.0.clone())
+        .unwrap_or_default();
+
+    let doors: Vec<(Entity, Option<u32>)> = world
+        .query::<(&Position, &Door, Option<&Locked>)>()
+        .iter()
+        .filter(|(_, (position, door, _))| position.grid_point() == point && !door.opened)
+        .map(|(entity, (_, _, locked))| (entity, locked.map(|locked| locked.0)))
+        .collect();
+
+    for (entity, locked) in doors.iter() {
+        if locked.is_some_and(|key| !held.contains(&key)) {
+            tracing::debug!(?point, ?locked, "Door is locked");
+            continue;
+        }
+        world.get::<&mut Door>(*entity).unwrap().opened = true;
+        let _ = world.remove::<(Locked, BlocksTile)>(*entity);
+    }
+
+    !doors.is_empty()
+}
+
+/// Put every [`Key`] lying at `point` on `holder`'s [`Keyring`]
+pub fn pick_up_keys(world: &mut World, holder: Entity, point: WorldPoint) {
+    let keys: Vec<(Entity, u32)> = world
+        .query::<(&Position, &Key)>()
+        .iter()
+        .filter(|(_, (position, _))| position.grid_point() == point)
+        .map(|(entity, (_, key))| (entity, key.0))
+        .collect();
+    if keys.is_empty() {
+        return;
+    }
+
+    let mut keyring = world
+        .get::<&Keyring>(holder)
+        .map(|keyring| (*keyring).clone())
+        .unwrap_or_default();
+    for (entity, key) in keys {
+        keyring.0.push(key);
+        world.despawn(entity).unwrap();
+    }
+    world.insert_one(holder, keyring).unwrap();
+}
+
+/// Whether anything that blocks movement stands at `point`
+pub fn is_blocked_by_entity(world: &World, point: WorldPoint) -> bool {
+    world
+        .query::<(&Position, &BlocksTile)>()
+        .iter()
+        .any(|(_, (position, _))| position.grid_point() == point)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::component::{Item, Player};
+
+    use super::*;
+
+    #[test]
+    fn keys_open_their_doors() {
+        let mut world = World::new();
+        let player = world.spawn((Position::new(WorldPoint::new(0, 0)), Player {}));
+        let door = WorldPoint::new(2, 0);
+        world.spawn((
+            Position::new(door),
+            Door { opened: false },
+            Locked(1),
+            BlocksTile {},
+        ));
+        let wrong_key = WorldPoint::new(0, 1);
+        world.spawn((
+            Position::new(wrong_key),
+            Item {
+                name: "keycard".into(),
+            },
+            Key(0),
+        ));
+
+        // Locked without the key
+        assert!(open_doors(&mut world, player, door));
+        assert!(is_blocked_by_entity(&world, door));
+        pick_up_keys(&mut world, player, wrong_key);
+        assert!(open_doors(&mut world, player, door));
+        assert!(is_blocked_by_entity(&world, door));
+
+        // Opens with it
+        let key = WorldPoint::new(1, 1);
+        world.spawn((Position::new(key), Key(1)));
+        pick_up_keys(&mut world, player, key);
+        assert_eq!(*world.get::<&Keyring>(player).unwrap(), Keyring(vec![0, 1]));
+        assert!(world.query::<&Key>().iter().next().is_none());
+
+        assert!(open_doors(&mut world, player, door));
+        assert!(!is_blocked_by_entity(&world, door));
+
+        // and stays open
+        assert!(!open_doors(&mut world, player, door));
+    }
+}
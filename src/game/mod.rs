@@ -9,5 +9,8 @@ pub use turns::*;
 mod gui;
 pub use gui::*;
 
+mod door;
+pub use door::*;
+
 // mod actor;
 // pub use actor::*;
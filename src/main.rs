@@ -1,11 +1,11 @@
-use game::consts::RESOURCE_PATH;
 use game::consts::SCREEN_ASPECT_RATIO;
-use std::{env, path};
+use std::{cell::RefCell, env, path::PathBuf, rc::Rc};
 use tracing::info;
 use tracing::Level;
 
 pub mod animation;
 pub mod camera;
+pub mod cli;
 pub mod color;
 pub mod component;
 pub mod data;
@@ -21,10 +21,11 @@ pub mod system;
 pub mod util;
 
 use clap::Parser;
+use cli::Command;
 use macroquad::prelude::*;
 
 use game::consts::{self, SCREEN_HEIGHT_PIXELS, SCREEN_WIDTH_PIXELS, TITLE_HEADER};
-use resource::Resources;
+use resource::{AssetRoots, Resources};
 use scene::MainState;
 
 #[derive(Parser)]
@@ -37,8 +38,47 @@ struct Cli {
     #[clap(short, long, action, default_value_t = false)]
     debug: bool,
 
-    #[clap(long)]
+    /// Seed for everything random. Subcommands that make several maps count up from it.
+    #[clap(long, global = true)]
     seed: Option<u64>,
+
+    /// Base game assets directory (defaults to the one next to the binary)
+    #[clap(long)]
+    assets: Option<PathBuf>,
+
+    /// Mod directories layered on top of the base assets (each needs a mod.yaml)
+    #[clap(long = "mod")]
+    mods: Vec<PathBuf>,
+
+    /// User assets directory, layered on top of everything else
+    #[clap(long)]
+    user_dir: Option<PathBuf>,
+
+    #[clap(subcommand)]
+    command: Option<Command>,
+}
+
+/// Find the base assets directory: the CLI flag, then next to the binary, then the source tree
+fn base_assets_dir(cli: &Cli) -> anyhow::Result<PathBuf> {
+    let exe_assets = env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(|dir| dir.join(consts::RESOURCE_PATH)));
+
+    let dir = cli
+        .assets
+        .clone()
+        .or_else(|| exe_assets.filter(|dir| dir.is_dir()))
+        .unwrap_or_else(|| PathBuf::from(env!("CARGO_MANIFEST_DIR")).join(consts::RESOURCE_PATH));
+
+    Ok(dir.canonicalize()?)
+}
+
+/// Default user assets directory (eg. ~/.local/share/blastoff/assets)
+fn user_assets_dir() -> Option<PathBuf> {
+    env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")))
+        .map(|dir| dir.join(env!("CARGO_PKG_NAME")).join(consts::RESOURCE_PATH))
 }
 
 const WINDOW_SCALE: i32 = 4;
@@ -59,8 +99,7 @@ fn window_conf() -> Conf {
     }
 }
 
-#[macroquad::main(window_conf)]
-async fn main() -> anyhow::Result<()> {
+fn main() -> anyhow::Result<()> {
     let cli = Cli::parse();
 
     let level = match cli.verbose {
@@ -79,23 +118,44 @@ async fn main() -> anyhow::Result<()> {
     // TODO: std::env isn't supported on WASM.
     //#[cfg(not(target_arch = "wasm32"))]
     info!("linking resources");
-    let manifest_dir = env::var("CARGO_MANIFEST_DIR")?;
+    let base_dir = base_assets_dir(&cli)?;
 
-    let path = path::PathBuf::from(manifest_dir).join(consts::RESOURCE_PATH);
+    let mut roots = AssetRoots::new(base_dir);
+    for mod_dir in cli.mods.iter() {
+        roots = roots.with_mod(mod_dir.clone())?;
+    }
+    if let Some(user_dir) = cli.user_dir.clone().or_else(user_assets_dir) {
+        roots = roots.with_user_dir(user_dir);
+    }
 
-    set_pc_assets_folder(consts::RESOURCE_PATH);
+    // Subcommands run headless, everything else opens the game window
+    if let Some(command) = cli.command {
+        return command.run(rng_seed, &roots);
+    }
+
+    // The window swallows the game's result, so carry any error out of it
+    let debug = cli.debug;
+    let result: Rc<RefCell<anyhow::Result<()>>> = Rc::new(RefCell::new(Ok(())));
+    let game_result = result.clone();
+    macroquad::Window::from_config(window_conf(), async move {
+        if let Err(err) = run_game(rng_seed, roots, debug).await {
+            tracing::error!("Error: {:?}", err);
+            *game_result.borrow_mut() = Err(err);
+        }
+    });
 
-    tracing::info!("Adding 'resources' path {:?}", path);
-    let cache = assets_manager::AssetCache::new(path)?;
+    result.replace(Ok(()))
+}
 
+async fn run_game(rng_seed: u64, roots: AssetRoots, debug: bool) -> anyhow::Result<()> {
     // Global Resources struct used for resources shared across scenes
     tracing::info!("Creating global Resources instance");
-    let resources = Resources::try_new(rng_seed, cache).await?;
+    let resources = Resources::try_new(rng_seed, roots)?;
 
     let mut game = MainState::new(resources);
 
     // Push an initial scene to the SceneStack and prepare it for playing
-    if cli.debug {
+    if debug {
         game.init_debug()
     } else {
         game.init();
@@ -3,6 +3,7 @@ use macroquad::prelude::Color;
 pub use sector_info::*;
 mod tile;
 
+use assets_manager::AssetCache;
 use rgb::RGBA8;
 pub use tile::*;
 
@@ -12,9 +13,10 @@ use serde::{Deserialize, Serialize};
 
 use crate::{
     color::{FIRE, PLANT, WATER},
-    data::{Element, PlanetType, SectorProbability, Tileset},
+    component::Cardinal,
+    data::{Element, PlanetGenerator, PlanetType, SectorProbabilityTable, Tileset},
     game::consts::MAX_PLANET_SPRITE_SIZE,
-    procgen::{MapGenerator, SectorProcgenLoader, Spawner},
+    procgen::{LocationSeed, MapGenerator, SectorProcgenLoader, Spawner},
     sector,
     util::{OverworldPoint, OverworldRect, OverworldSize, PixelPoint},
 };
@@ -26,6 +28,8 @@ pub type OverworldMap = HashMap<OverworldPoint, SectorInfo>;
 pub struct SectorData {
     pub map: sector::Map,
     pub world: hecs::World,
+    /// Rooms and corridors, for sectors whose generator laid them out
+    pub layout: Option<sector::MapLayout>,
 }
 
 // #[derive(Serialize, Deserialize)]
@@ -94,6 +98,14 @@ impl Overworld {
         }
     }
 
+    pub fn get_sector_info_mut(&mut self, point: &OverworldPoint) -> Option<&mut SectorInfo> {
+        if self.info.rect.contains(*point) {
+            self.map.get_mut(point)
+        } else {
+            None
+        }
+    }
+
     /// sets *JUST* the tile
     pub fn set_sector_info(&mut self, point: OverworldPoint, sector_info: SectorInfo) {
         self.map.insert(point, sector_info);
@@ -111,6 +123,12 @@ impl Overworld {
     }
 
     /// Use procgen to create a new Sector at a given overworld grid point
+    ///
+    /// The map depends on the sector's seed and on the edges of the
+    /// neighbours generated before it, so that paths carry on across sector
+    /// boundaries. A sector without generated neighbours is a pure function of
+    /// its seed, but regenerating a planet only gives the same sectors if they
+    /// are visited in the same order.
     pub fn create_sector<'a, T: MapGenerator + Spawner>(
         &mut self,
         point: &OverworldPoint,
@@ -118,22 +136,39 @@ impl Overworld {
     ) -> Rc<RefCell<SectorData>> {
         // TODO: it's confusing how Overworld and procgen/overworld/ interact. Consolidate?
 
-        let sector_info = self
+        let mut sector_info = self
             .get_sector_info(point)
-            .expect("SectorInfo should exist for point");
+            .expect("SectorInfo should exist for point")
+            .clone();
+        sector_info.borders = self.neighbour_borders(point);
 
         // Create a new Sector and spawn to a fresh ECS world
         let mut world = hecs::World::new();
 
-        let map = loader.load(sector_info, &mut world);
+        let seed = self.info.seed.sector(*point);
+        let map = loader.load(&sector_info, seed, &mut world);
+        let layout = loader.layout();
 
         // Set the sector to the given point
-        self.set_sector(point, SectorData { map, world });
+        self.set_sector(point, SectorData { map, world, layout });
 
         // aaaand let's also return a reference to it in the map
         self.get_sector(point).unwrap()
     }
 
+    /// Collect the edges of every generated sector next to `point`
+    fn neighbour_borders(&self, point: &OverworldPoint) -> SectorBorders {
+        let mut borders = SectorBorders::default();
+        for cardinal in [Cardinal::N, Cardinal::S, Cardinal::E, Cardinal::W] {
+            let vector = cardinal.to_vector();
+            let neighbour = OverworldPoint::new(point.x + vector.x, point.y + vector.y);
+            if let Some(sector) = self.get_sector(&neighbour) {
+                borders.insert(cardinal, &sector.borrow().map);
+            }
+        }
+        borders
+    }
+
     pub fn color(&self) -> Color {
         self.info.color()
     }
@@ -169,7 +204,10 @@ pub struct PlanetInfo {
     pub rect: OverworldRect,
     pub planet_type: PlanetType,
     pub element: Element,
-    pub sector_probability: SectorProbability,
+    pub generator: PlanetGenerator,
+    pub sector_probability: SectorProbabilityTable,
+    /// Seed for this planet's location, see [`Self::with_seed`]
+    pub seed: LocationSeed,
 }
 
 impl PlanetInfo {
@@ -178,7 +216,8 @@ impl PlanetInfo {
         size: OverworldSize,
         planet_type: PlanetType,
         element: Element,
-        sector_probability: SectorProbability,
+        generator: PlanetGenerator,
+        sector_probability: SectorProbabilityTable,
     ) -> Self {
         let rect = OverworldRect::new(OverworldPoint::new(0, 0), size);
         Self {
@@ -187,10 +226,41 @@ impl PlanetInfo {
             rect,
             planet_type,
             element,
+            generator,
             sector_probability,
+            seed: LocationSeed::default(),
         }
     }
 
+    /// A planet outside of any galaxy, with the sector probabilities from
+    /// `data/sector_probability.yaml`
+    pub fn standalone(
+        size: OverworldSize,
+        planet_type: PlanetType,
+        element: Element,
+        generator: PlanetGenerator,
+        cache: &AssetCache,
+    ) -> Result<Self, assets_manager::Error> {
+        let sector_probability = cache
+            .load::<SectorProbabilityTable>("data.sector_probability")?
+            .read()
+            .clone();
+        Ok(Self::new(
+            "Standalone".to_owned(),
+            size,
+            planet_type,
+            element,
+            generator,
+            sector_probability,
+        ))
+    }
+
+    /// Set where this planet is so that its Overworld and sectors can be regenerated
+    pub fn with_seed(mut self, seed: LocationSeed) -> Self {
+        self.seed = seed;
+        self
+    }
+
     pub fn center(&self) -> OverworldPoint {
         self.rect.center()
     }
@@ -247,3 +317,76 @@ impl fmt::Display for PlanetInfo {
         f.write_str(&format!("{} ({:?})", self.name, self.size))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        data::SectorType,
+        procgen::sector_generator,
+        resource::{test_asset_roots, Resources},
+        util::{WorldPoint, WorldSize},
+    };
+
+    use super::*;
+
+    fn planet(resources: &Resources) -> Overworld {
+        let info = PlanetInfo::standalone(
+            OverworldSize::new(2, 1),
+            PlanetType::Barren,
+            Element::Water,
+            PlanetGenerator::Static,
+            &resources.assets_cache,
+        )
+        .unwrap()
+        .with_seed(LocationSeed::galaxy(11));
+        let mut overworld = Overworld::from_info(info);
+        for x in 0..2 {
+            let sector_info = SectorInfo::standalone(
+                PlanetType::Barren,
+                Element::Water,
+                SectorType::Barren,
+                WorldSize::new(32, 32),
+                &resources.assets_cache,
+            )
+            .unwrap();
+            overworld.set_sector_info(OverworldPoint::new(x, 0), sector_info);
+        }
+        overworld
+    }
+
+    fn create(overworld: &mut Overworld, point: OverworldPoint, resources: &mut Resources) {
+        let seed = overworld.info().seed.sector(point);
+        let mapgen = sector_generator(overworld.get_sector_info(&point).unwrap(), seed, resources);
+        let mut history = Vec::new();
+        let mut loader = SectorProcgenLoader::new(mapgen, resources, &mut history);
+        overworld.create_sector(&point, &mut loader);
+    }
+
+    fn map(overworld: &Overworld, point: OverworldPoint) -> sector::Map {
+        overworld.get_sector(&point).unwrap().borrow().map.clone()
+    }
+
+    #[test]
+    fn sectors_depend_on_generation_order() {
+        let mut resources = Resources::headless(0, test_asset_roots()).unwrap();
+        let west = OverworldPoint::new(0, 0);
+        let east = OverworldPoint::new(1, 0);
+
+        // Without generated neighbours a sector only depends on its seed
+        let mut first = planet(&resources);
+        create(&mut first, west, &mut resources);
+        let mut second = planet(&resources);
+        create(&mut second, west, &mut resources);
+        assert_eq!(map(&first, west), map(&second, west));
+
+        // A later neighbour lines its edge up with the sector already there
+        create(&mut first, east, &mut resources);
+        let (west_map, east_map) = (map(&first, west), map(&first, east));
+        for y in 0..32 {
+            assert_eq!(
+                east_map[&WorldPoint::new(0, y)].is_passable(),
+                west_map[&WorldPoint::new(31, y)].is_passable()
+            );
+        }
+    }
+}
@@ -1,6 +1,11 @@
+use assets_manager::AssetCache;
+
 use crate::{
+    component::Cardinal,
+    data::{Element, PlanetGenerator, PlanetType, SectorProbability, SectorType},
     resource::Resources,
-    util::{PixelPoint, WorldSize},
+    sector::{Map, Tile},
+    util::{OverworldSize, PixelPoint, WorldPoint, WorldSize},
 };
 
 use super::{OverworldTile, PlanetInfo};
@@ -9,19 +14,207 @@ use super::{OverworldTile, PlanetInfo};
 pub struct SectorInfo {
     pub planet_info: PlanetInfo,
     pub tile: OverworldTile,
+    pub sector_type: SectorType,
+    pub probability: SectorProbability,
     pub size: WorldSize,
+    /// Edges a road leaves this sector through
+    pub roads: SectorEdges,
+    /// Edges a river leaves this sector through
+    pub rivers: SectorEdges,
+    /// Tiles the edges should line up with. Only filled in while generating.
+    pub borders: SectorBorders,
+    /// Generator recipe this sector always uses instead of the rules in
+    /// `data/sector_recipes.yaml`, eg. for a hand-crafted map
+    pub generator: Option<String>,
 }
 
 impl SectorInfo {
-    pub fn new(planet_info: PlanetInfo, tile: OverworldTile, size: WorldSize) -> Self {
+    pub fn new(
+        planet_info: PlanetInfo,
+        tile: OverworldTile,
+        sector_type: SectorType,
+        size: WorldSize,
+    ) -> Self {
+        let probability = planet_info.sector_probability.get(sector_type).clone();
         Self {
             planet_info,
             tile,
+            sector_type,
+            probability,
             size,
+            roads: SectorEdges::default(),
+            rivers: SectorEdges::default(),
+            borders: SectorBorders::default(),
+            generator: None,
         }
     }
 
+    /// The only sector of a standalone 1x1 planet, for generating a map
+    /// outside of the game
+    pub fn standalone(
+        planet_type: PlanetType,
+        element: Element,
+        sector_type: SectorType,
+        size: WorldSize,
+        cache: &AssetCache,
+    ) -> Result<Self, assets_manager::Error> {
+        let planet_info = PlanetInfo::standalone(
+            OverworldSize::new(1, 1),
+            planet_type,
+            element,
+            PlanetGenerator::default(),
+            cache,
+        )?;
+        let tile = OverworldTile::from_sector_type(sector_type, &planet_info);
+        Ok(Self::new(planet_info, tile, sector_type, size))
+    }
+
+    /// Change the SectorType (and the probabilities that come with it)
+    pub fn set_sector_type(&mut self, sector_type: SectorType, tile: OverworldTile) {
+        self.probability = self.planet_info.sector_probability.get(sector_type).clone();
+        self.sector_type = sector_type;
+        self.tile = tile;
+    }
+
     pub fn render(&self, resources: &mut Resources, point: PixelPoint) {
         self.tile.render(resources, point)
     }
 }
+
+/// The sides of a sector that a road or river crosses
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SectorEdges {
+    pub north: bool,
+    pub south: bool,
+    pub east: bool,
+    pub west: bool,
+}
+
+impl SectorEdges {
+    /// Mark an edge. Diagonals mark both of their edges.
+    pub fn insert(&mut self, cardinal: Cardinal) {
+        match cardinal {
+            Cardinal::N => self.north = true,
+            Cardinal::S => self.south = true,
+            Cardinal::E => self.east = true,
+            Cardinal::W => self.west = true,
+            Cardinal::NE => {
+                self.north = true;
+                self.east = true
+            }
+            Cardinal::NW => {
+                self.north = true;
+                self.west = true
+            }
+            Cardinal::SE => {
+                self.south = true;
+                self.east = true
+            }
+            Cardinal::SW => {
+                self.south = true;
+                self.west = true
+            }
+        }
+    }
+
+    pub fn contains(&self, cardinal: Cardinal) -> bool {
+        match cardinal {
+            Cardinal::N => self.north,
+            Cardinal::S => self.south,
+            Cardinal::E => self.east,
+            Cardinal::W => self.west,
+            Cardinal::NE => self.north && self.east,
+            Cardinal::NW => self.north && self.west,
+            Cardinal::SE => self.south && self.east,
+            Cardinal::SW => self.south && self.west,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        !(self.north || self.south || self.east || self.west)
+    }
+}
+
+/// Edge tiles of already-generated neighbouring sectors, so that a new sector
+/// can line its own edges up with them
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SectorBorders {
+    /// The bottom row of the sector to the north
+    pub north: Option<Vec<Tile>>,
+    /// The top row of the sector to the south
+    pub south: Option<Vec<Tile>>,
+    /// The left column of the sector to the east
+    pub east: Option<Vec<Tile>>,
+    /// The right column of the sector to the west
+    pub west: Option<Vec<Tile>>,
+}
+
+impl SectorBorders {
+    /// Copy the edge facing us off of the neighbour at `cardinal`. Diagonal
+    /// neighbours don't share an edge and are ignored.
+    pub fn insert(&mut self, cardinal: Cardinal, neighbour: &Map) {
+        let width = neighbour.get_width();
+        let height = neighbour.get_height();
+        let row = |y| {
+            (0..width)
+                .map(|x| neighbour[&WorldPoint::new(x, y)])
+                .collect()
+        };
+        let column = |x| {
+            (0..height)
+                .map(|y| neighbour[&WorldPoint::new(x, y)])
+                .collect()
+        };
+
+        match cardinal {
+            Cardinal::N => self.north = Some(row(height - 1)),
+            Cardinal::S => self.south = Some(row(0)),
+            Cardinal::E => self.east = Some(column(0)),
+            Cardinal::W => self.west = Some(column(width - 1)),
+            _ => {}
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.north.is_none() && self.south.is_none() && self.east.is_none() && self.west.is_none()
+    }
+
+    /// The sides that don't have a generated neighbour yet
+    pub fn open_sides(&self) -> SectorEdges {
+        SectorEdges {
+            north: self.north.is_none(),
+            south: self.south.is_none(),
+            east: self.east.is_none(),
+            west: self.west.is_none(),
+        }
+    }
+
+    /// Every cell of a map of `size` that touches a neighbour, with the tile
+    /// it should have and the direction pointing into the map
+    pub fn cells(&self, size: WorldSize) -> Vec<(WorldPoint, Tile, Cardinal)> {
+        let sides = [
+            (&self.north, Cardinal::S),
+            (&self.south, Cardinal::N),
+            (&self.east, Cardinal::W),
+            (&self.west, Cardinal::E),
+        ];
+
+        sides
+            .into_iter()
+            .filter_map(|(tiles, inward)| tiles.as_ref().map(|tiles| (tiles, inward)))
+            .flat_map(|(tiles, inward)| {
+                tiles.iter().enumerate().filter_map(move |(i, tile)| {
+                    let i = i as i32;
+                    let point = match inward {
+                        Cardinal::S => WorldPoint::new(i, 0),
+                        Cardinal::N => WorldPoint::new(i, size.height - 1),
+                        Cardinal::W => WorldPoint::new(size.width - 1, i),
+                        _ => WorldPoint::new(0, i),
+                    };
+                    let in_bounds = point.x < size.width && point.y < size.height;
+                    in_bounds.then_some((point, *tile, inward))
+                })
+            })
+            .collect()
+    }
+}
@@ -1,12 +1,16 @@
 use macroquad::prelude::Color;
+use serde::{Deserialize, Serialize};
 
 use crate::{
     color::{COMMON, FIRE, PLANT, WATER},
+    data::{Element, PlanetType, SectorType},
     resource::Resources,
     util::PixelPoint,
 };
 
-#[derive(Debug, Clone, Copy)]
+use super::PlanetInfo;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum OverworldTile {
     City,
     Barren,
@@ -14,9 +18,29 @@ pub enum OverworldTile {
     Lava,
     Jungle,
     Mountains,
+    Road,
+    River,
 }
 
 impl OverworldTile {
+    /// Pick the tile that best represents a SectorType on a given planet
+    pub fn from_sector_type(sector_type: SectorType, planet_info: &PlanetInfo) -> Self {
+        match sector_type {
+            SectorType::Barren => match planet_info.planet_type {
+                PlanetType::Barren | PlanetType::Mountains => OverworldTile::Barren,
+                PlanetType::Lush | PlanetType::Aqueus => OverworldTile::Jungle,
+            },
+            SectorType::Civilization => OverworldTile::City,
+            SectorType::ImpassibleTerrain => OverworldTile::Mountains,
+            SectorType::ImpassibleLiquid | SectorType::PassibleLiquid => {
+                match planet_info.element {
+                    Element::Fire => OverworldTile::Lava,
+                    Element::Water | Element::Plant => OverworldTile::Water,
+                }
+            }
+        }
+    }
+
     pub fn glyph(&self) -> char {
         match self {
             OverworldTile::City => '⌂',
@@ -25,6 +49,8 @@ impl OverworldTile {
             OverworldTile::Lava => '~',
             OverworldTile::Jungle => '♣',
             OverworldTile::Mountains => '▲',
+            OverworldTile::Road => '#',
+            OverworldTile::River => '≈',
         }
     }
 
@@ -36,6 +62,8 @@ impl OverworldTile {
             OverworldTile::Lava => 253,
             OverworldTile::Jungle => 101,
             OverworldTile::Mountains => 264,
+            OverworldTile::Road => 2,
+            OverworldTile::River => 253,
         }
     }
 
@@ -47,18 +75,20 @@ impl OverworldTile {
             OverworldTile::Lava => FIRE.three,
             OverworldTile::Jungle => PLANT.three,
             OverworldTile::Mountains => COMMON.four,
+            OverworldTile::Road => COMMON.three,
+            OverworldTile::River => WATER.four,
         }
     }
 
     fn render_sprite(&self, resources: &mut Resources, point: PixelPoint) {
         resources
-            .assets
+            .assets()
             .tileset
             .draw(self.sprite(), point, Some(self.fg()), None, false)
     }
 
     fn render_ascii(&self, resources: &mut Resources, point: PixelPoint) {
-        resources.assets.monospace_font.draw(
+        resources.assets().monospace_font.draw(
             &self.glyph().to_string(),
             point,
             Some(self.fg()),
@@ -0,0 +1,159 @@
+//! Star system placement and the hyperlanes between them
+use std::collections::HashSet;
+
+use rand::{seq::SliceRandom, Rng};
+
+use crate::{
+    galaxy::{lane_cost, Hyperlanes},
+    util::{GalaxyPoint, GalaxySize},
+};
+
+/// How many random points to try per system before giving up on spacing
+const PLACEMENT_ATTEMPTS: usize = 30;
+
+/// How many of each system's nearest neighbors are considered for extra lanes
+const EXTRA_LANE_NEIGHBORS: usize = 3;
+
+/// Pick `count` unique points inside the galaxy, spaced at least `min_distance`
+/// apart when possible. If the spacing can't be met, the remaining systems go
+/// in random free cells. Returns fewer points only if the galaxy is full.
+pub fn place_systems<R: Rng>(
+    size: GalaxySize,
+    count: usize,
+    min_distance: f32,
+    rng: &mut R,
+) -> Vec<GalaxyPoint> {
+    let mut points: Vec<GalaxyPoint> = Vec::with_capacity(count);
+
+    // Rejection sampling for the spaced points
+    for _ in 0..count * PLACEMENT_ATTEMPTS {
+        if points.len() == count {
+            return points;
+        }
+
+        let candidate =
+            GalaxyPoint::new(rng.gen_range(0..size.width), rng.gen_range(0..size.height));
+        let spaced = points
+            .iter()
+            .all(|point| point.to_f32().distance_to(candidate.to_f32()) >= min_distance);
+        if spaced {
+            points.push(candidate);
+        }
+    }
+
+    // Fall back to any unused cell
+    let taken: HashSet<GalaxyPoint> = points.iter().copied().collect();
+    let mut free: Vec<GalaxyPoint> = (0..size.height)
+        .flat_map(|y| (0..size.width).map(move |x| GalaxyPoint::new(x, y)))
+        .filter(|point| !taken.contains(point))
+        .collect();
+    free.shuffle(rng);
+
+    let missing = count - points.len();
+    if missing > free.len() {
+        tracing::warn!(count, "Galaxy is too small for every star system");
+    }
+    points.extend(free.into_iter().take(missing));
+    points
+}
+
+/// Connect every system with a minimum spanning tree, then add some of the
+/// remaining short lanes (with probability `extra_lanes`) to create loops
+pub fn generate_hyperlanes<R: Rng>(
+    points: &[GalaxyPoint],
+    extra_lanes: f32,
+    rng: &mut R,
+) -> Hyperlanes {
+    let mut lanes = Vec::new();
+    if points.is_empty() {
+        return Hyperlanes::new(lanes);
+    }
+
+    // Prim's algorithm. Galaxies are small, so O(n^2) is fine.
+    let mut connected = vec![false; points.len()];
+    let mut best: Vec<Option<(i32, usize)>> = vec![None; points.len()];
+    let mut current = 0;
+    connected[0] = true;
+
+    for _ in 1..points.len() {
+        for (idx, point) in points.iter().enumerate() {
+            if connected[idx] {
+                continue;
+            }
+            let cost = lane_cost(&points[current], point);
+            if best[idx].is_none_or(|(best_cost, _)| cost < best_cost) {
+                best[idx] = Some((cost, current));
+            }
+        }
+
+        let (next, (_, from)) = best
+            .iter()
+            .enumerate()
+            .filter(|(idx, _)| !connected[*idx])
+            .filter_map(|(idx, best)| best.map(|best| (idx, best)))
+            .min_by_key(|(_, (cost, _))| *cost)
+            .unwrap();
+
+        connected[next] = true;
+        lanes.push((points[from], points[next]));
+        current = next;
+    }
+
+    // Extra lanes between near neighbors
+    let has_lane = |lanes: &[(GalaxyPoint, GalaxyPoint)], a: &GalaxyPoint, b: &GalaxyPoint| {
+        lanes
+            .iter()
+            .any(|(x, y)| (x == a && y == b) || (x == b && y == a))
+    };
+    for point in points.iter() {
+        let mut nearest: Vec<_> = points.iter().filter(|other| *other != point).collect();
+        nearest.sort_by_key(|other| lane_cost(point, other));
+
+        for other in nearest.into_iter().take(EXTRA_LANE_NEIGHBORS) {
+            if !has_lane(&lanes, point, other) && rng.gen::<f32>() < extra_lanes {
+                lanes.push((*point, *other));
+            }
+        }
+    }
+
+    Hyperlanes::new(lanes)
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    #[test]
+    fn unique_points() {
+        let mut rng = StdRng::seed_from_u64(3);
+
+        // Even when there isn't room for the requested spacing
+        let points = place_systems(GalaxySize::new(4, 4), 14, 2.0, &mut rng);
+        assert_eq!(points.len(), 14);
+        assert_eq!(points.iter().collect::<HashSet<_>>().len(), 14);
+
+        // And when there isn't room at all
+        let points = place_systems(GalaxySize::new(3, 3), 14, 2.0, &mut rng);
+        assert_eq!(points.len(), 9);
+        assert_eq!(points.iter().collect::<HashSet<_>>().len(), 9);
+    }
+
+    #[test]
+    fn lanes_connect_everything() {
+        let mut rng = StdRng::seed_from_u64(9);
+        let points = place_systems(GalaxySize::new(12, 12), 12, 2.0, &mut rng);
+        let lanes = generate_hyperlanes(&points, 0.3, &mut rng);
+
+        assert!(lanes.len() >= points.len() - 1);
+        for point in points.iter() {
+            let route = lanes.route(&points[0], point).expect("a route");
+            assert_eq!(route.first(), Some(&points[0]));
+            assert_eq!(route.last(), Some(point));
+            for step in route.windows(2) {
+                assert!(lanes.contains(&step[0], &step[1]));
+            }
+        }
+    }
+}
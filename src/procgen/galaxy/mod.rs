@@ -1,13 +1,16 @@
-use bracket_random::prelude::DiceType;
+mod layout;
+pub use layout::*;
+
+mod system;
+pub use system::*;
 
 use crate::{
-    data::GalaxyProbability,
+    data::{GalaxyProbability, SectorProbabilityTable},
     galaxy::{Galaxy, GalaxyInfo},
     resource::Resources,
-    util::{GalaxyPoint, GalaxySize},
 };
 
-use super::generate_planet_name;
+use super::{generate_planet_name, Layer, LocationSeed};
 
 pub trait GalaxyGenerator {
     fn generate(&mut self, resources: &mut Resources) -> Galaxy;
@@ -16,49 +19,63 @@ pub trait GalaxyGenerator {
 pub struct StaticGalaxy {}
 impl GalaxyGenerator for StaticGalaxy {
     fn generate(&mut self, resources: &mut Resources) -> Galaxy {
-        let info = generate_galaxy_info(resources);
-        // 5-10 planets
-        let num_planets = resources.rng.roll(DiceType::new(1, 6, 4));
+        let seed = LocationSeed::galaxy(resources.seed);
+        let sector_probability = resources
+            .load_asset::<SectorProbabilityTable>("data.sector_probability")
+            .read()
+            .clone();
 
-        let mut planets: Vec<_> = (0..num_planets)
-            // First, create all the OverworldInfos
-            .map(|_| {
-                let name = generate_planet_name(resources);
-                tracing::warn!("Generated planet name: {}", &name);
-                let rng = &mut resources.rng;
-                info.probability.roll_planet(name, rng)
-            })
-            .collect::<Vec<_>>()
-            .into_iter()
-            // Then figure out which Galaxy coordinates to use for this planet
-            .map(|planet_info| {
-                let x = resources.rng.roll_dice(1, info.width() - 1);
-                let y = resources.rng.roll_dice(1, info.height() - 1);
+        // First, lay out the galaxy: its size, where the systems are, and the lanes between them
+        let (info, points, lanes) = resources.with_rng(seed.rng(Layer::Galaxy), |resources| {
+            let info = generate_galaxy_info(resources);
+            let num_systems = info.probability.roll_system_count(&mut resources.rng);
+
+            let rng = resources.rng.get_rng();
+            let points = place_systems(
+                info.size(),
+                num_systems as usize,
+                info.probability.min_system_distance(),
+                rng,
+            );
+            let lanes = generate_hyperlanes(&points, info.probability.extra_lanes(), rng);
+            (info, points, lanes)
+        });
 
-                // TODO: make it so that galaxy generation doesn't accidentally overwrite collisions
-                (GalaxyPoint::new(x, y), planet_info)
+        // Then create each StarSystem from its own location's seed
+        let systems = points
+            .into_iter()
+            .map(|point| {
+                let system_seed = seed.system(point);
+                let system = resources.with_rng(system_seed.rng(Layer::System), |resources| {
+                    let name = generate_planet_name(resources);
+                    tracing::info!("Generated system name: {}", &name);
+                    generate_star_system(
+                        name,
+                        system_seed,
+                        &info.probability,
+                        &sector_probability,
+                        &mut resources.rng,
+                    )
+                });
+                (point, system)
             })
             .collect();
 
-        // TODO: planet dedup doesn't seem to work(?)
-        planets.dedup_by_key(|(point, _)| *point);
-
-        Galaxy::from_size(info).with_planet_infos(planets)
+        Galaxy::from_size(info)
+            .with_systems(systems)
+            .with_lanes(lanes)
     }
 }
 
 /// Galaxy Info
 fn generate_galaxy_info(resources: &mut Resources) -> GalaxyInfo {
-    // TODO: galaxy size constant
-    let width = resources.rng.roll_dice(3, 6);
-    let height = resources.rng.roll_dice(3, 6);
-
     // Load GalaxyProbability from assets data files
-    let galaxy_probability = resources.load_asset::<GalaxyProbability>("data.galaxy_probability");
+    let galaxy_probability = resources
+        .load_asset::<GalaxyProbability>("data.galaxy_probability")
+        .read()
+        .clone();
+
+    let size = galaxy_probability.roll_galaxy_size(&mut resources.rng);
 
-    GalaxyInfo::new(
-        "Procgen Galaxy Name".to_owned(),
-        GalaxySize::new(width, height),
-        galaxy_probability.read().clone(),
-    )
+    GalaxyInfo::new("Procgen Galaxy Name".to_owned(), size, galaxy_probability)
 }
@@ -0,0 +1,120 @@
+//! Star systems: a star, planets in increasing orbits, and their moons
+use bracket_random::prelude::RandomNumberGenerator;
+use rand::Rng;
+
+use crate::{
+    data::{GalaxyProbability, OrbitZone, SectorProbabilityTable},
+    galaxy::{BodyKind, OrbitalBody, StarSystem},
+    procgen::LocationSeed,
+};
+
+const ROMAN_NUMERALS: [&str; 10] = ["I", "II", "III", "IV", "V", "VI", "VII", "VIII", "IX", "X"];
+
+/// Roll a star and its bodies. Planets are named after the system ("Name II")
+/// and moons after their planet ("Name II b"). Each body gets a seed derived
+/// from the system's `seed` and its index.
+pub fn generate_star_system(
+    name: String,
+    seed: LocationSeed,
+    probability: &GalaxyProbability,
+    sector_probability: &SectorProbabilityTable,
+    rng: &mut RandomNumberGenerator,
+) -> StarSystem {
+    let star = probability.roll_star_type(rng);
+    let num_planets = probability.roll_planets_per_system(rng) as usize;
+
+    // Roughly geometric spacing starting inside the habitable zone
+    let mut orbit = star.habitable_orbit() * rng.get_rng().gen_range(0.3..0.6);
+    let mut bodies = Vec::new();
+
+    for idx in 0..num_planets {
+        let zone = OrbitZone::from_orbit(star, orbit);
+        let numeral = ROMAN_NUMERALS
+            .get(idx)
+            .map(|numeral| numeral.to_string())
+            .unwrap_or_else(|| (idx + 1).to_string());
+        let planet_name = format!("{} {}", name, numeral);
+
+        let parent = bodies.len();
+        bodies.push(OrbitalBody {
+            info: probability
+                .roll_planet(
+                    planet_name.clone(),
+                    zone,
+                    false,
+                    sector_probability.clone(),
+                    rng,
+                )
+                .with_seed(seed.body(parent)),
+            orbit,
+            zone,
+            kind: BodyKind::Planet,
+        });
+
+        let num_moons = probability.roll_moons_per_planet(rng);
+        for (moon, letter) in (0..num_moons).zip('b'..='z') {
+            let moon_name = format!("{} {}", planet_name, letter);
+            tracing::trace!(moon, "Generated moon {}", &moon_name);
+            let info = probability
+                .roll_planet(moon_name, zone, true, sector_probability.clone(), rng)
+                .with_seed(seed.body(bodies.len()));
+            bodies.push(OrbitalBody {
+                info,
+                orbit,
+                zone,
+                kind: BodyKind::Moon { planet: parent },
+            });
+        }
+
+        orbit *= rng.get_rng().gen_range(1.4..2.0);
+    }
+
+    StarSystem::new(name, star, bodies)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{data::Element, resource::test_asset_cache};
+
+    use super::*;
+
+    #[test]
+    fn zones_follow_orbits() {
+        let cache = test_asset_cache();
+        let probability = cache
+            .load::<GalaxyProbability>("data.galaxy_probability")
+            .unwrap()
+            .read();
+        let sector_probability = cache
+            .load::<SectorProbabilityTable>("data.sector_probability")
+            .unwrap()
+            .read();
+        let mut rng = RandomNumberGenerator::seeded(4);
+
+        for _ in 0..20 {
+            let system = generate_star_system(
+                "Test".to_owned(),
+                LocationSeed::galaxy(4),
+                &probability,
+                &sector_probability,
+                &mut rng,
+            );
+            assert!(!system.bodies().is_empty());
+
+            let planets: Vec<_> = system.bodies().iter().filter(|b| !b.is_moon()).collect();
+            for pair in planets.windows(2) {
+                assert!(pair[0].orbit < pair[1].orbit);
+                assert!(pair[0].zone <= pair[1].zone);
+            }
+
+            for body in system.bodies() {
+                if body.zone == OrbitZone::Hot {
+                    assert_eq!(body.info.element, Element::Fire, "{}", body.info);
+                }
+                if let BodyKind::Moon { planet } = body.kind {
+                    assert_eq!(system.bodies()[planet].zone, body.zone);
+                }
+            }
+        }
+    }
+}
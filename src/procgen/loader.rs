@@ -6,10 +6,20 @@ use crate::overworld::SectorInfo;
 use crate::resource::Resources;
 use crate::sector;
 
+use crate::data::SectorType;
+
+use super::connect_regions;
+use super::place_locks;
+use super::place_prefabs;
+use super::player_point;
+use super::populate;
 use super::GalaxyGenerator;
+use super::Layer;
+use super::LocationSeed;
 use super::MapGenerator;
 use super::OverworldGenerator;
 use super::Spawner;
+use super::MIN_REGION_SIZE;
 
 pub struct SectorProcgenLoader<'a, T>
 where
@@ -18,6 +28,7 @@ where
     inner: T,
     resources: &'a mut Resources,
     mapgen_history: &'a mut Vec<sector::Map>,
+    layout: Option<sector::MapLayout>,
 }
 
 impl<'a, T> SectorProcgenLoader<'a, T>
@@ -33,19 +44,74 @@ where
             inner,
             resources,
             mapgen_history,
+            layout: None,
         }
     }
 
-    /// Generates the map and returns the Spawner
-    pub fn load(&mut self, sector_info: &SectorInfo, world: &mut World) -> sector::Map {
-        let map = self
-            .inner
-            .generate(sector_info, self.resources, self.mapgen_history);
+    /// Generates the map and spawns its entities. Both only depend on `seed`
+    /// and `sector_info`, including the neighbours' borders it carries.
+    pub fn load(
+        &mut self,
+        sector_info: &SectorInfo,
+        seed: LocationSeed,
+        world: &mut World,
+    ) -> sector::Map {
+        let inner = &mut self.inner;
+        let mapgen_history = &mut *self.mapgen_history;
+        let mut map = self.resources.with_rng(seed.rng(Layer::Map), |resources| {
+            inner.generate(sector_info, resources, mapgen_history)
+        });
+
+        if inner.postprocess() {
+            // Every generator gets the same guarantee that the whole map is reachable
+            connect_regions(&mut map, MIN_REGION_SIZE);
+            mapgen_history.push(map.clone());
+
+            self.resources
+                .with_rng(seed.rng(Layer::Prefabs), |resources| {
+                    place_prefabs(&mut map, sector_info, world, resources)
+                });
+            mapgen_history.push(map.clone());
+        }
+
+        self.resources
+            .with_rng(seed.rng(Layer::Spawn), |resources| {
+                inner.spawn(&map, world, resources)
+            });
 
-        self.inner.spawn(&map, world, self.resources);
+        // The entrance is the room the player starts in
+        self.layout = inner.layout().map(|mut layout| {
+            if let Some(room) = player_point(world).and_then(|start| layout.room_at(start)) {
+                layout.set_entrance(room);
+            }
+            layout
+        });
+
+        // Civilization sectors with rooms play like facilities
+        if sector_info.sector_type == SectorType::Civilization {
+            if let Some(layout) = &self.layout {
+                self.resources
+                    .with_rng(seed.rng(Layer::Locks), |resources| {
+                        place_locks(&map, layout, sector_info, world, resources)
+                    });
+            }
+        }
+
+        // Last, so that nothing lands on the doors and keys
+        if inner.postprocess() {
+            self.resources
+                .with_rng(seed.rng(Layer::Population), |resources| {
+                    populate(&map, sector_info, world, resources)
+                });
+        }
 
         map
     }
+
+    /// The room layout of the last loaded map, if its generator made one
+    pub fn layout(&self) -> Option<sector::MapLayout> {
+        self.layout.clone()
+    }
 }
 
 pub struct OverworldProcgenLoader<'a, T>
@@ -63,8 +129,12 @@ where
     pub fn new(inner: T, resources: &'a mut Resources) -> Self {
         Self { inner, resources }
     }
+    /// Generates the Overworld from the planet's own seed
     pub fn load(&mut self, info: PlanetInfo) -> Overworld {
-        self.inner.generate(info, self.resources)
+        let inner = &mut self.inner;
+        let rng = info.seed.rng(Layer::Overworld);
+        self.resources
+            .with_rng(rng, |resources| inner.generate(info, resources))
     }
 }
 
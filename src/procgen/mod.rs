@@ -12,3 +12,9 @@ pub use overworld::*;
 
 mod galaxy;
 pub use galaxy::*;
+
+mod name;
+pub use name::*;
+
+mod location;
+pub use location::*;
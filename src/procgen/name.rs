@@ -0,0 +1,351 @@
+//! Order-N character Markov chains for generating names from a text corpus
+//!
+//! Each corpus is a [`MarkovSeed`] (one name per line, anything after a comma is
+//! ignored). The chain is trained on lowercase names and the output is title-cased.
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap},
+};
+
+use assets_manager::{asset::load_yaml, Asset, AssetCache, BoxedError, FileAsset, SharedString};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{data::MarkovSeed, resource::Resources};
+
+/// Marks the start and end of a name in the chain
+const START: char = '\u{2}';
+const END: char = '\u{3}';
+
+/// Name for anything whose corpus is empty
+const UNNAMED: &str = "Unnamed";
+
+/// Which corpus to generate a name from
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Eq, PartialOrd, Ord)]
+pub enum NameKind {
+    Planet,
+    City,
+    Creature,
+    Faction,
+}
+
+impl NameKind {
+    const ALL: [NameKind; 4] = [
+        NameKind::Planet,
+        NameKind::City,
+        NameKind::Creature,
+        NameKind::Faction,
+    ];
+
+    pub fn from_asset_id(id: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.asset_id() == id)
+    }
+
+    pub fn asset_id(&self) -> &'static str {
+        match self {
+            NameKind::Planet => "markov.planet",
+            NameKind::City => "markov.city",
+            NameKind::Creature => "markov.creature",
+            NameKind::Faction => "markov.faction",
+        }
+    }
+}
+
+/// Tuning parameters for a single Markov name generator
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct MarkovConfig {
+    /// How many previous characters to consider when picking the next one
+    pub order: usize,
+    pub min_length: usize,
+    pub max_length: usize,
+    /// How many names to try before giving up on a novel name
+    pub max_attempts: usize,
+}
+
+impl Default for MarkovConfig {
+    fn default() -> Self {
+        Self {
+            order: 3,
+            min_length: 4,
+            max_length: 16,
+            max_attempts: 100,
+        }
+    }
+}
+
+/// MarkovConfig for each NameKind, loaded from `markov/names.yaml`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NameConfigs(BTreeMap<NameKind, MarkovConfig>);
+
+impl NameConfigs {
+    pub fn get(&self, kind: NameKind) -> MarkovConfig {
+        self.0.get(&kind).copied().unwrap_or_default()
+    }
+}
+
+impl FileAsset for NameConfigs {
+    const EXTENSION: &'static str = "yaml";
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Result<Self, BoxedError> {
+        load_yaml(&bytes)
+    }
+}
+
+/// A trained character-level Markov chain
+pub struct MarkovNameGenerator {
+    config: MarkovConfig,
+    /// Maps the last `order` characters to weighted next characters. The
+    /// weights are stored in corpus order so that output is deterministic.
+    transitions: HashMap<Vec<char>, Vec<(char, u32)>>,
+    /// Sorted, for looking names up and picking them deterministically
+    corpus: Vec<String>,
+}
+
+impl MarkovNameGenerator {
+    pub fn new(seed: &MarkovSeed, mut config: MarkovConfig) -> Self {
+        if config.order == 0 {
+            tracing::warn!("Markov order must be at least 1; using 1");
+            config.order = 1;
+        }
+
+        let mut corpus: Vec<String> = seed
+            .as_str()
+            .lines()
+            .filter_map(|line| line.split(',').next())
+            .map(|name| name.trim().to_lowercase())
+            .filter(|name| !name.is_empty())
+            .collect();
+
+        let mut transitions: HashMap<Vec<char>, Vec<(char, u32)>> = HashMap::new();
+        for name in corpus.iter() {
+            let chars: Vec<char> = std::iter::repeat_n(START, config.order)
+                .chain(name.chars())
+                .chain(std::iter::once(END))
+                .collect();
+
+            for window in chars.windows(config.order + 1) {
+                let (state, next) = window.split_at(config.order);
+                let choices = transitions.entry(state.to_vec()).or_default();
+                match choices.iter_mut().find(|(c, _)| *c == next[0]) {
+                    Some((_, weight)) => *weight += 1,
+                    None => choices.push((next[0], 1)),
+                }
+            }
+        }
+
+        corpus.sort();
+        corpus.dedup();
+        Self {
+            config,
+            transitions,
+            corpus,
+        }
+    }
+
+    /// Walk the chain once. Returns None if the result is out of the length
+    /// bounds or copies a name from the corpus.
+    fn try_generate<R: Rng>(&self, rng: &mut R) -> Option<String> {
+        let mut state = vec![START; self.config.order];
+        let mut name = String::new();
+
+        loop {
+            let choices = self.transitions.get(&state)?;
+            let total: u32 = choices.iter().map(|(_, weight)| weight).sum();
+            let mut roll = rng.gen_range(0..total);
+
+            let next = choices
+                .iter()
+                .find(|(_, weight)| {
+                    if roll < *weight {
+                        true
+                    } else {
+                        roll -= weight;
+                        false
+                    }
+                })
+                .map(|(c, _)| *c)?;
+
+            if next == END {
+                break;
+            }
+
+            name.push(next);
+            if name.chars().count() > self.config.max_length {
+                return None;
+            }
+
+            state.remove(0);
+            state.push(next);
+        }
+
+        let name = name.trim().to_owned();
+        if name.chars().count() < self.config.min_length || self.corpus.binary_search(&name).is_ok()
+        {
+            return None;
+        }
+
+        Some(title_case(&name))
+    }
+
+    /// Generate a name that doesn't appear in the corpus
+    pub fn generate<R: Rng>(&self, rng: &mut R) -> Option<String> {
+        (0..self.config.max_attempts).find_map(|_| self.try_generate(rng))
+    }
+
+    /// A name straight from the corpus, for when [`Self::generate`] gives up
+    pub fn corpus_name<R: Rng>(&self, rng: &mut R) -> Option<String> {
+        if self.corpus.is_empty() {
+            return None;
+        }
+        Some(title_case(
+            &self.corpus[rng.gen_range(0..self.corpus.len())],
+        ))
+    }
+}
+
+/// Trained once per [`NameKind`] from the seed with the same asset id, and
+/// retrained whenever the seed or `markov/names.yaml` changes
+impl Asset for MarkovNameGenerator {
+    fn load(cache: &AssetCache, id: &SharedString) -> Result<Self, BoxedError> {
+        let kind = NameKind::from_asset_id(id)
+            .ok_or_else(|| format!("{:?} isn't a name corpus", id.as_str()))?;
+        let config = cache.load::<NameConfigs>("markov.names")?.read().get(kind);
+        let seed = cache.load::<MarkovSeed>(id)?.read();
+        Ok(Self::new(&seed, config))
+    }
+}
+
+/// Capitalize each word, keeping roman numerals (eg. "IV") fully uppercase
+fn title_case(name: &str) -> String {
+    name.split(' ')
+        .map(|word| {
+            if !word.is_empty() && word.chars().all(|c| matches!(c, 'i' | 'v' | 'x')) {
+                return word.to_uppercase();
+            }
+
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// Generate a name from the corpus for a given NameKind using the Resources rng
+pub fn generate_name(kind: NameKind, resources: &mut Resources) -> String {
+    let id = kind.asset_id();
+    let generator = resources
+        .assets_cache
+        .load::<MarkovNameGenerator>(id)
+        .unwrap_or_else(|err| panic!("asset load from {:?}: {}", id, err))
+        .read();
+    let rng = resources.rng.get_rng();
+
+    generator
+        .generate(rng)
+        .or_else(|| {
+            tracing::warn!(?kind, "Markov name generation failed; using a corpus name");
+            generator.corpus_name(rng)
+        })
+        .unwrap_or_else(|| {
+            tracing::warn!(?kind, "Empty name corpus; using {:?}", UNNAMED);
+            UNNAMED.to_owned()
+        })
+}
+
+pub fn generate_planet_name(resources: &mut Resources) -> String {
+    generate_name(NameKind::Planet, resources)
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use crate::resource::test_asset_cache;
+
+    use super::*;
+
+    fn corpus() -> MarkovSeed {
+        "Andor\nAndoria\nAntos IV, Antos\nArgelius II\nBajor\nBarzan II\nBenzar\nBetazed\nBolarus IX\nCardassia\nCeti Alpha V\nDenobula\nFerenginar\nKronos\nRisa\nRomulus\nTalos IV\nVulcan"
+            .to_owned()
+            .into()
+    }
+
+    #[test]
+    fn novel_names_within_bounds() {
+        let config = MarkovConfig {
+            order: 2,
+            min_length: 4,
+            max_length: 10,
+            max_attempts: 1000,
+        };
+        let generator = MarkovNameGenerator::new(&corpus(), config);
+        let mut rng = StdRng::seed_from_u64(7);
+
+        for _ in 0..20 {
+            let name = generator.generate(&mut rng).expect("a novel name");
+            let length = name.chars().count();
+            assert!((4..=10).contains(&length), "{:?}", name);
+            assert!(
+                !generator.corpus.contains(&name.to_lowercase()),
+                "{:?}",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn deterministic() {
+        let generator = MarkovNameGenerator::new(&corpus(), MarkovConfig::default());
+        let names = |seed| {
+            let mut rng = StdRng::seed_from_u64(seed);
+            (0..5)
+                .map(|_| generator.generate(&mut rng))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(names(42), names(42));
+    }
+
+    #[test]
+    fn trained_once_per_kind() {
+        let cache = test_asset_cache();
+
+        for kind in NameKind::ALL {
+            let generator = cache.load::<MarkovNameGenerator>(kind.asset_id()).unwrap();
+            // The same handle every time
+            assert!(std::ptr::eq(
+                generator,
+                cache.load::<MarkovNameGenerator>(kind.asset_id()).unwrap()
+            ));
+        }
+        assert!(cache.load::<MarkovNameGenerator>("markov.names").is_err());
+    }
+
+    #[test]
+    fn order_zero_means_one() {
+        let config = MarkovConfig {
+            order: 0,
+            ..Default::default()
+        };
+        let generator = MarkovNameGenerator::new(&corpus(), config);
+        assert_eq!(generator.config.order, 1);
+        assert!(generator.generate(&mut StdRng::seed_from_u64(3)).is_some());
+    }
+
+    #[test]
+    fn corpus_names_drop_aliases() {
+        let generator = MarkovNameGenerator::new(&corpus(), MarkovConfig::default());
+        let mut rng = StdRng::seed_from_u64(3);
+        for _ in 0..50 {
+            let name = generator.corpus_name(&mut rng).unwrap();
+            assert!(!name.contains(','), "{}", name);
+        }
+
+        let empty = MarkovNameGenerator::new(&String::new().into(), MarkovConfig::default());
+        assert_eq!(empty.generate(&mut rng), None);
+        assert_eq!(empty.corpus_name(&mut rng), None);
+    }
+}
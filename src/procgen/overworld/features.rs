@@ -0,0 +1,346 @@
+//! Cities, roads and rivers drawn on top of classified overworld terrain
+use std::collections::HashSet;
+
+use pathfinding::prelude::astar;
+use rand::{seq::SliceRandom, Rng};
+
+use crate::{
+    component::Cardinal,
+    data::{FeatureConfig, SectorType},
+    overworld::{Overworld, OverworldTile},
+    util::OverworldPoint,
+};
+
+use super::TerrainFields;
+
+const ORTHOGONAL: [Cardinal; 4] = [Cardinal::N, Cardinal::E, Cardinal::S, Cardinal::W];
+
+/// Run rivers downhill, place cities, then connect the cities with roads.
+/// `civilization` is the chance that a land tile would be a city.
+pub fn place_features<R: Rng>(
+    planet: &mut Overworld,
+    fields: &TerrainFields,
+    config: &FeatureConfig,
+    civilization: f32,
+    rng: &mut R,
+) {
+    place_rivers(planet, fields, config, rng);
+    let cities = place_cities(planet, config, civilization, rng);
+    place_roads(planet, &cities);
+}
+
+fn neighbor(point: OverworldPoint, cardinal: Cardinal) -> OverworldPoint {
+    let vector = cardinal.to_vector();
+    OverworldPoint::new(point.x + vector.x, point.y + vector.y)
+}
+
+fn step_cardinal(from: OverworldPoint, to: OverworldPoint) -> Option<Cardinal> {
+    ORTHOGONAL
+        .into_iter()
+        .find(|cardinal| neighbor(from, *cardinal) == to)
+}
+
+fn is_liquid(sector_type: SectorType) -> bool {
+    matches!(
+        sector_type,
+        SectorType::PassibleLiquid | SectorType::ImpassibleLiquid
+    )
+}
+
+fn sector_type(planet: &Overworld, point: OverworldPoint) -> Option<SectorType> {
+    planet.get_sector_info(&point).map(|info| info.sector_type)
+}
+
+fn place_rivers<R: Rng>(
+    planet: &mut Overworld,
+    fields: &TerrainFields,
+    config: &FeatureConfig,
+    rng: &mut R,
+) {
+    let mut land: Vec<OverworldPoint> = planet
+        .iter_points()
+        .filter(|point| sector_type(planet, *point).is_some_and(|t| !is_liquid(t)))
+        .collect();
+
+    // Rivers start in the upper half of the land
+    land.sort_by(|a, b| fields.elevation_at(*b).total_cmp(&fields.elevation_at(*a)));
+    let count = (land.len() as f32 * config.rivers).round() as usize;
+    let mut sources: Vec<_> = land[..land.len() / 2].to_vec();
+    sources.shuffle(rng);
+
+    for source in sources.into_iter().take(count) {
+        if let Some(course) = river_course(planet, fields, config, source) {
+            for (idx, point) in course.iter().enumerate() {
+                let info = planet.get_sector_info_mut(point).unwrap();
+                if idx + 1 < course.len() {
+                    info.set_sector_type(SectorType::PassibleLiquid, OverworldTile::River);
+                }
+                if let Some(next) = course.get(idx + 1) {
+                    info.rivers.insert(step_cardinal(*point, *next).unwrap());
+                }
+                if let Some(prev) = idx.checked_sub(1).map(|prev| course[prev]) {
+                    info.rivers.insert(step_cardinal(*point, prev).unwrap());
+                }
+            }
+        }
+    }
+}
+
+/// Follow the steepest descent from a source until reaching water (or another
+/// river). The last point of the course is the water it flows into.
+fn river_course(
+    planet: &Overworld,
+    fields: &TerrainFields,
+    config: &FeatureConfig,
+    source: OverworldPoint,
+) -> Option<Vec<OverworldPoint>> {
+    let mut course = vec![source];
+    let mut visited = HashSet::from([source]);
+
+    while course.len() <= config.max_river_length {
+        let current = *course.last().unwrap();
+
+        // Carve through pits by always taking the lowest unvisited neighbor
+        let next = ORTHOGONAL
+            .into_iter()
+            .map(|cardinal| neighbor(current, cardinal))
+            .filter(|point| planet.get_sector_info(point).is_some() && !visited.contains(point))
+            .min_by(|a, b| fields.elevation_at(*a).total_cmp(&fields.elevation_at(*b)))?;
+
+        course.push(next);
+        visited.insert(next);
+
+        let reached_water = sector_type(planet, next).is_some_and(is_liquid);
+        if reached_water && course.len() > 2 {
+            return Some(course);
+        } else if reached_water {
+            // Too short to bother with
+            return None;
+        }
+    }
+
+    None
+}
+
+fn place_cities<R: Rng>(
+    planet: &mut Overworld,
+    config: &FeatureConfig,
+    civilization: f32,
+    rng: &mut R,
+) -> Vec<OverworldPoint> {
+    let near_water = |point: OverworldPoint| {
+        ORTHOGONAL
+            .into_iter()
+            .any(|cardinal| sector_type(planet, neighbor(point, cardinal)).is_some_and(is_liquid))
+    };
+
+    // Cities prefer open land next to water
+    let mut candidates: Vec<(f32, OverworldPoint)> = planet
+        .iter_points()
+        .filter(|point| sector_type(planet, *point) == Some(SectorType::Barren))
+        .map(|point| {
+            let score = if near_water(point) { 2. } else { 0. };
+            (score + rng.gen::<f32>(), point)
+        })
+        .collect();
+    candidates.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+    let target = ((candidates.len() as f32 * civilization).round() as usize).min(config.max_cities);
+
+    let mut cities: Vec<OverworldPoint> = Vec::new();
+    for (_, point) in candidates {
+        if cities.len() >= target {
+            break;
+        }
+
+        let spaced = cities.iter().all(|city| {
+            (city.x - point.x).abs().max((city.y - point.y).abs()) >= config.city_spacing
+        });
+        if spaced {
+            cities.push(point);
+        }
+    }
+
+    for city in cities.iter() {
+        planet
+            .get_sector_info_mut(city)
+            .unwrap()
+            .set_sector_type(SectorType::Civilization, OverworldTile::City);
+    }
+
+    cities
+}
+
+/// Cost of building a road into a tile, or None if it can't be built on
+fn road_cost(planet: &Overworld, point: OverworldPoint) -> Option<i32> {
+    let info = planet.get_sector_info(&point)?;
+    match (info.tile, info.sector_type) {
+        (_, SectorType::ImpassibleLiquid) => None,
+        (OverworldTile::City | OverworldTile::Road, _) => Some(1),
+        (OverworldTile::Barren, _) => Some(2),
+        (OverworldTile::Jungle, _) => Some(3),
+        (OverworldTile::River, _) => Some(4),
+        (_, SectorType::PassibleLiquid) => Some(8),
+        (OverworldTile::Mountains, _) => Some(10),
+        _ => Some(3),
+    }
+}
+
+/// Connect every city to its closest connected city, spanning-tree style
+fn place_roads(planet: &mut Overworld, cities: &[OverworldPoint]) {
+    let Some((first, rest)) = cities.split_first() else {
+        return;
+    };
+
+    let mut connected = vec![*first];
+    let mut remaining = rest.to_vec();
+    let distance = |a: &OverworldPoint, b: &OverworldPoint| (a.x - b.x).abs() + (a.y - b.y).abs();
+
+    while !remaining.is_empty() {
+        // The closest pair between the connected and unconnected cities
+        let (idx, target) = remaining
+            .iter()
+            .enumerate()
+            .flat_map(|(idx, city)| connected.iter().map(move |other| (idx, *city, *other)))
+            .min_by_key(|(_, city, other)| distance(city, other))
+            .map(|(idx, _, other)| (idx, other))
+            .unwrap();
+        let start = remaining.remove(idx);
+        connected.push(start);
+
+        let path = astar(
+            &start,
+            |point| {
+                ORTHOGONAL
+                    .into_iter()
+                    .map(|cardinal| neighbor(*point, cardinal))
+                    .filter_map(|next| road_cost(planet, next).map(|cost| (next, cost)))
+                    .collect::<Vec<_>>()
+            },
+            |point| distance(point, &target),
+            |point| *point == target,
+        );
+
+        // Cities separated by deep water stay disconnected
+        let Some((path, _)) = path else {
+            continue;
+        };
+
+        for (idx, point) in path.iter().enumerate() {
+            let info = planet.get_sector_info_mut(point).unwrap();
+            if matches!(
+                info.tile,
+                OverworldTile::Barren | OverworldTile::Jungle | OverworldTile::Mountains
+            ) {
+                info.set_sector_type(SectorType::Barren, OverworldTile::Road);
+            }
+            if let Some(next) = path.get(idx + 1) {
+                info.roads.insert(step_cardinal(*point, *next).unwrap());
+            }
+            if let Some(prev) = idx.checked_sub(1).map(|prev| path[prev]) {
+                info.roads.insert(step_cardinal(*point, prev).unwrap());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use crate::{
+        data::{Element, PlanetGenerator, PlanetType, TerrainConfig},
+        game::consts::SECTOR_SIZE,
+        overworld::{PlanetInfo, SectorInfo},
+        resource::test_asset_cache,
+        util::OverworldSize,
+    };
+
+    use super::*;
+
+    fn planet(seed: u64, civilization: f32) -> Overworld {
+        let cache = test_asset_cache();
+        let config = cache.load::<TerrainConfig>("data.terrain").unwrap().read();
+        let info = PlanetInfo::standalone(
+            OverworldSize::new(30, 30),
+            PlanetType::Lush,
+            Element::Water,
+            PlanetGenerator::Noise,
+            &cache,
+        )
+        .unwrap();
+
+        let fields = TerrainFields::generate(&info, &config, seed);
+        let mut planet = Overworld::from_info(info);
+        for (point, tile, sector_type) in fields.classify(planet.info(), &config) {
+            let sector_info =
+                SectorInfo::new(planet.info().clone(), tile, sector_type, SECTOR_SIZE);
+            planet.set_sector_info(point, sector_info);
+        }
+
+        let features = FeatureConfig {
+            rivers: 0.02,
+            ..config.features
+        };
+        place_features(
+            &mut planet,
+            &fields,
+            &features,
+            civilization,
+            &mut StdRng::seed_from_u64(seed),
+        );
+        planet
+    }
+
+    fn city_count(planet: &Overworld) -> usize {
+        planet
+            .iter_sector_infos()
+            .filter(|(_, info)| info.sector_type == SectorType::Civilization)
+            .count()
+    }
+
+    #[test]
+    fn uncivilized_planets_have_no_cities() {
+        assert_eq!(city_count(&planet(5, 0.)), 0);
+    }
+
+    #[test]
+    fn edges_are_continuous() {
+        let planet = planet(5, 0.05);
+        assert!(city_count(&planet) > 1);
+
+        // Every road or river edge is matched by the neighbor it points to
+        for (point, info) in planet.iter_sector_infos() {
+            for cardinal in ORTHOGONAL {
+                let other = planet.get_sector_info(&neighbor(*point, cardinal));
+                if info.roads.contains(cardinal) {
+                    assert!(other.unwrap().roads.contains(cardinal.inv()));
+                }
+                if info.rivers.contains(cardinal) {
+                    assert!(other.unwrap().rivers.contains(cardinal.inv()));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn rivers_reach_water() {
+        let planet = planet(8, 0.05);
+
+        for (point, info) in planet.iter_sector_infos() {
+            if info.tile != OverworldTile::River {
+                continue;
+            }
+
+            // Rivers are always connected to more river or open water
+            let flows = ORTHOGONAL
+                .into_iter()
+                .filter(|cardinal| info.rivers.contains(*cardinal))
+                .any(|cardinal| {
+                    let next = planet.get_sector_info(&neighbor(*point, cardinal)).unwrap();
+                    is_liquid(next.sector_type)
+                });
+            assert!(flows, "river at {:?} doesn't flow anywhere", point);
+        }
+    }
+}
@@ -1,9 +1,11 @@
-mod name;
-use bracket_random::prelude::RandomNumberGenerator;
-pub use name::*;
+mod terrain;
+pub use terrain::*;
+
+mod features;
+pub use features::*;
 
 use crate::{
-    data::{Element, PlanetType},
+    data::PlanetProbability,
     game::consts::SECTOR_SIZE,
     overworld::{Overworld, OverworldTile, PlanetInfo, SectorInfo},
     resource::Resources,
@@ -13,74 +15,29 @@ pub trait OverworldGenerator {
     fn generate(&mut self, planet_info: PlanetInfo, resources: &mut Resources) -> Overworld;
 }
 
+/// Rolls an independent SectorType for every overworld tile using the
+/// planet's PlanetProbability
 pub struct StaticPlanet {}
 impl OverworldGenerator for StaticPlanet {
     fn generate(&mut self, info: PlanetInfo, resources: &mut Resources) -> Overworld {
-        let planet = Overworld::from_info(info);
-
-        let planet: Overworld = match planet.info().planet_type {
-            PlanetType::Barren => create_barren_planet(planet, &mut resources.rng),
-            PlanetType::Lush => create_lush_planet(planet, &mut resources.rng),
-            PlanetType::Aqueus => create_aqueus_planet(planet, &mut resources.rng),
-            PlanetType::Mountains => create_mountain_planet(planet, &mut resources.rng),
-        };
+        let mut planet = Overworld::from_info(info);
+
+        let planet_probability = resources
+            .load_asset::<PlanetProbability>("data.planet_probability")
+            .read()
+            .clone();
+
+        let planet_type = planet.info().planet_type;
+        let points: Vec<_> = planet.iter_points().collect();
+        for point in points {
+            let sector_type =
+                planet_probability.roll_sector_type(planet_type, resources.rng.get_rng());
+            let tile = OverworldTile::from_sector_type(sector_type, planet.info());
+            let sector_info =
+                SectorInfo::new(planet.info().clone(), tile, sector_type, SECTOR_SIZE);
+            planet.set_sector_info(point, sector_info);
+        }
 
         planet
     }
 }
-
-fn create_mountain_planet(mut planet: Overworld, rng: &mut RandomNumberGenerator) -> Overworld {
-    for point in planet.iter_points().into_iter() {
-        let tile = if rng.roll_dice(1, 5) % 5 == 0 {
-            OverworldTile::Mountains
-        } else {
-            OverworldTile::Barren
-        };
-        let sector_info = SectorInfo::new(planet.info().clone(), tile, SECTOR_SIZE);
-        planet.set_sector_info(point, sector_info);
-    }
-
-    planet
-}
-
-fn create_aqueus_planet(mut planet: Overworld, rng: &mut RandomNumberGenerator) -> Overworld {
-    for point in planet.iter_points().into_iter() {
-        let tile = if rng.roll_dice(1, 3) % 3 == 0 {
-            OverworldTile::Water
-        } else {
-            OverworldTile::Jungle
-        };
-        let sector_info = SectorInfo::new(planet.info().clone(), tile, SECTOR_SIZE);
-        planet.set_sector_info(point, sector_info);
-    }
-
-    planet
-}
-
-fn create_lush_planet(mut planet: Overworld, rng: &mut RandomNumberGenerator) -> Overworld {
-    for point in planet.iter_points().into_iter() {
-        let tile = if rng.roll_dice(1, 5) % 5 == 0 {
-            OverworldTile::Jungle
-        } else {
-            OverworldTile::Water
-        };
-        let sector_info = SectorInfo::new(planet.info().clone(), tile, SECTOR_SIZE);
-        planet.set_sector_info(point, sector_info);
-    }
-
-    planet
-}
-
-fn create_barren_planet(mut planet: Overworld, rng: &mut RandomNumberGenerator) -> Overworld {
-    for point in planet.iter_points().into_iter() {
-        let tile = if rng.roll_dice(1, 25) % 25 == 0 {
-            OverworldTile::Mountains
-        } else {
-            OverworldTile::Barren
-        };
-        let sector_info = SectorInfo::new(planet.info().clone(), tile, SECTOR_SIZE);
-        planet.set_sector_info(point, sector_info);
-    }
-
-    planet
-}
@@ -0,0 +1,278 @@
+//! Noise-based overworld terrain
+//!
+//! Elevation, moisture and temperature are sampled from fractal noise, then
+//! biased by the planet's PlanetType and Element. Sea level and mountain height
+//! are picked by quantile so each PlanetType gets a predictable amount of ocean.
+use bracket_noise::prelude::{FastNoise, FractalType, NoiseType};
+
+use crate::{
+    data::{Element, PlanetProbability, SectorType, TerrainConfig},
+    game::consts::SECTOR_SIZE,
+    overworld::{Overworld, OverworldTile, PlanetInfo, SectorInfo},
+    resource::Resources,
+    util::OverworldPoint,
+};
+
+use super::{place_features, OverworldGenerator};
+
+/// Classifies noise fields into OverworldTile biomes
+pub struct NoisePlanet {}
+impl OverworldGenerator for NoisePlanet {
+    fn generate(&mut self, info: PlanetInfo, resources: &mut Resources) -> Overworld {
+        let config = resources
+            .load_asset::<TerrainConfig>("data.terrain")
+            .read()
+            .clone();
+        let civilization = resources
+            .load_asset::<PlanetProbability>("data.planet_probability")
+            .read()
+            .sector_type_probability(info.planet_type)
+            .probability(&SectorType::Civilization);
+        let seed = resources.rng.next_u64();
+
+        let fields = TerrainFields::generate(&info, &config, seed);
+        let mut planet = Overworld::from_info(info);
+
+        for (point, tile, sector_type) in fields.classify(planet.info(), &config) {
+            let sector_info =
+                SectorInfo::new(planet.info().clone(), tile, sector_type, SECTOR_SIZE);
+            planet.set_sector_info(point, sector_info);
+        }
+
+        place_features(
+            &mut planet,
+            &fields,
+            &config.features,
+            civilization,
+            resources.rng.get_rng(),
+        );
+
+        planet
+    }
+}
+
+/// Per-tile climate values for a planet, stored row-major
+pub struct TerrainFields {
+    width: i32,
+    height: i32,
+    /// Roughly -1..1; only meaningful relative to the other tiles
+    pub elevation: Vec<f32>,
+    /// 0..1 before biases
+    pub moisture: Vec<f32>,
+    /// 0 (poles) to 1 (equator) before biases
+    pub temperature: Vec<f32>,
+}
+
+impl TerrainFields {
+    pub fn generate(info: &PlanetInfo, config: &TerrainConfig, seed: u64) -> Self {
+        let shape = config.shape(info.planet_type);
+        let climate = config.climate(info.element);
+
+        let elevation_noise = fractal_noise(seed, config);
+        let moisture_noise = fractal_noise(seed.wrapping_add(1), config);
+        let temperature_noise = fractal_noise(seed.wrapping_add(2), config);
+
+        let (width, height) = (info.size.width, info.size.height);
+        let area = (width * height) as usize;
+        let mut fields = Self {
+            width,
+            height,
+            elevation: Vec::with_capacity(area),
+            moisture: Vec::with_capacity(area),
+            temperature: Vec::with_capacity(area),
+        };
+
+        for y in 0..height {
+            // 0 at the poles, 1 at the equator
+            let latitude = 1. - ((y as f32 + 0.5) / height as f32 * 2. - 1.).abs();
+
+            for x in 0..width {
+                let (fx, fy) = (x as f32, y as f32);
+                let elevation = elevation_noise.get_noise(fx, fy);
+                let moisture = (moisture_noise.get_noise(fx, fy) + 1.) / 2.;
+                let temperature = 0.6 * latitude + 0.2 * (temperature_noise.get_noise(fx, fy) + 1.);
+
+                fields.elevation.push(elevation);
+                fields
+                    .moisture
+                    .push(moisture + shape.moisture + climate.moisture);
+                fields.temperature.push(temperature + climate.temperature);
+            }
+        }
+
+        fields
+    }
+
+    fn index(&self, point: OverworldPoint) -> usize {
+        (point.y * self.width + point.x) as usize
+    }
+
+    pub fn elevation_at(&self, point: OverworldPoint) -> f32 {
+        self.elevation[self.index(point)]
+    }
+
+    /// Pick a tile and SectorType for every point on the planet
+    pub fn classify(
+        &self,
+        info: &PlanetInfo,
+        config: &TerrainConfig,
+    ) -> Vec<(OverworldPoint, OverworldTile, SectorType)> {
+        let shape = config.shape(info.planet_type);
+
+        let sea_level = quantile(&self.elevation, shape.ocean);
+        let shallows_level = quantile(&self.elevation, shape.ocean * (1. - config.shallows));
+        let land: Vec<f32> = self
+            .elevation
+            .iter()
+            .copied()
+            .filter(|elevation| *elevation >= sea_level)
+            .collect();
+        let mountain_level = quantile(&land, 1. - shape.mountains);
+
+        let liquid = match info.element {
+            Element::Fire => OverworldTile::Lava,
+            Element::Water | Element::Plant => OverworldTile::Water,
+        };
+
+        let mut tiles = Vec::with_capacity(self.elevation.len());
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let point = OverworldPoint::new(x, y);
+                let idx = self.index(point);
+                let elevation = self.elevation[idx];
+
+                let (tile, sector_type) = if elevation < shallows_level {
+                    (liquid, SectorType::ImpassibleLiquid)
+                } else if elevation < sea_level {
+                    (liquid, SectorType::PassibleLiquid)
+                } else if elevation >= mountain_level && shape.mountains > 0. {
+                    (OverworldTile::Mountains, SectorType::ImpassibleTerrain)
+                } else {
+                    // Higher land is colder
+                    let temperature =
+                        self.temperature[idx] - config.lapse_rate * (elevation - sea_level);
+                    let tile = if self.moisture[idx] > config.jungle_moisture
+                        && temperature > config.frozen_temperature
+                    {
+                        OverworldTile::Jungle
+                    } else {
+                        OverworldTile::Barren
+                    };
+                    (tile, SectorType::Barren)
+                };
+
+                tiles.push((point, tile, sector_type));
+            }
+        }
+
+        tiles
+    }
+}
+
+fn fractal_noise(seed: u64, config: &TerrainConfig) -> FastNoise {
+    let mut noise = FastNoise::seeded(seed);
+    noise.set_noise_type(NoiseType::SimplexFractal);
+    noise.set_fractal_type(FractalType::FBM);
+    noise.set_fractal_octaves(config.octaves);
+    noise.set_fractal_gain(0.5);
+    noise.set_fractal_lacunarity(2.0);
+    noise.set_frequency(config.frequency);
+    noise
+}
+
+/// The value below which `fraction` of the values fall
+fn quantile(values: &[f32], fraction: f32) -> f32 {
+    if values.is_empty() || fraction <= 0. {
+        return f32::NEG_INFINITY;
+    }
+    if fraction >= 1. {
+        return f32::INFINITY;
+    }
+
+    let mut sorted = values.to_vec();
+    sorted.sort_by(f32::total_cmp);
+    sorted[(fraction * sorted.len() as f32) as usize]
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use crate::{
+        data::{PlanetGenerator, PlanetType},
+        resource::test_asset_cache,
+        util::OverworldSize,
+    };
+
+    use super::*;
+
+    fn classify(
+        planet_type: PlanetType,
+        seed: u64,
+    ) -> Vec<(OverworldPoint, OverworldTile, SectorType)> {
+        let cache = test_asset_cache();
+        let config = cache.load::<TerrainConfig>("data.terrain").unwrap().read();
+        let info = PlanetInfo::standalone(
+            OverworldSize::new(30, 30),
+            planet_type,
+            Element::Water,
+            PlanetGenerator::Noise,
+            &cache,
+        )
+        .unwrap();
+
+        TerrainFields::generate(&info, &config, seed).classify(&info, &config)
+    }
+
+    fn is_liquid(sector_type: SectorType) -> bool {
+        matches!(
+            sector_type,
+            SectorType::PassibleLiquid | SectorType::ImpassibleLiquid
+        )
+    }
+
+    #[test]
+    fn ocean_follows_planet_type() {
+        let ocean = |planet_type| {
+            classify(planet_type, 3)
+                .iter()
+                .filter(|(_, _, sector_type)| is_liquid(*sector_type))
+                .count()
+        };
+
+        assert!(ocean(PlanetType::Aqueus) > 500);
+        assert!(ocean(PlanetType::Barren) < 100);
+    }
+
+    #[test]
+    fn oceans_are_contiguous() {
+        let water: HashSet<OverworldPoint> = classify(PlanetType::Aqueus, 11)
+            .into_iter()
+            .filter(|(_, _, sector_type)| is_liquid(*sector_type))
+            .map(|(point, _, _)| point)
+            .collect();
+
+        // Flood fill from any ocean tile; most of the ocean should be one body
+        let mut largest = 0;
+        let mut seen = HashSet::new();
+        for start in water.iter() {
+            if !seen.insert(*start) {
+                continue;
+            }
+            let mut stack = vec![*start];
+            let mut size = 0;
+            while let Some(point) = stack.pop() {
+                size += 1;
+                for (dx, dy) in [(1, 0), (-1, 0), (0, 1), (0, -1)] {
+                    let next = OverworldPoint::new(point.x + dx, point.y + dy);
+                    if water.contains(&next) && seen.insert(next) {
+                        stack.push(next);
+                    }
+                }
+            }
+            largest = largest.max(size);
+        }
+
+        assert!(largest * 2 > water.len(), "{} of {}", largest, water.len());
+    }
+}
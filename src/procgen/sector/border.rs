@@ -0,0 +1,118 @@
+//! Lining up a new sector's edges with its already-generated neighbours
+use crate::{
+    component::Cardinal,
+    overworld::SectorBorders,
+    sector::Map,
+    util::{WorldPoint, WorldVector},
+};
+
+/// Copy the neighbours' edge tiles onto a map's edges, then dig a corridor
+/// from every opening in an edge to the map's nearest floor so that paths
+/// carry on across sector boundaries.
+pub fn blend_borders(map: &mut Map, borders: &SectorBorders) {
+    let size = map.get_rect().size;
+    let cells = borders.cells(size);
+
+    // Floors the generator made, before any edges are copied over
+    let is_edge = |point: &WorldPoint| {
+        point.x == 0 || point.y == 0 || point.x == size.width - 1 || point.y == size.height - 1
+    };
+    let floors: Vec<WorldPoint> = map
+        .iter_tiles()
+        .filter(|(point, tile)| tile.is_passable() && !is_edge(point))
+        .map(|(point, _)| point)
+        .collect();
+
+    let mut changed = Vec::new();
+    let mut previous: Option<(WorldPoint, Cardinal)> = None;
+
+    for (point, tile, inward) in cells {
+        map[&point] = tile;
+        changed.push(point);
+
+        // Only dig once for each run of open tiles along an edge
+        let continues_run = previous.is_some_and(|(previous, previous_inward)| {
+            previous_inward == inward && (previous - point).square_length() == 1
+        });
+        previous = tile.is_passable().then_some((point, inward));
+        if !tile.is_passable() || continues_run {
+            continue;
+        }
+
+        let start = point + *inward.to_vector();
+        let target = floors
+            .iter()
+            .min_by_key(|floor| (**floor - start).square_length())
+            .copied();
+
+        let Some(target) = target else {
+            continue;
+        };
+
+        let mut cursor = point;
+        while cursor != target {
+            let delta = target - cursor;
+            // Head inwards first so the corridor doesn't run along the edge
+            let step = if cursor == point {
+                *inward.to_vector()
+            } else if delta.x != 0 {
+                WorldVector::new(delta.x.signum(), 0)
+            } else {
+                WorldVector::new(0, delta.y.signum())
+            };
+            cursor += step;
+
+            if !map[&cursor].is_passable() {
+                map[&cursor] = tile;
+                changed.push(cursor);
+            }
+        }
+    }
+
+    // Walls next to anything that changed may need a different shape
+    map.reshape_walls(&changed);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        sector::{FloorKind, Tile, WallKind},
+        util::{WorldRect, WorldSize},
+    };
+
+    use super::*;
+
+    #[test]
+    fn edges_match_and_connect() {
+        let size = WorldSize::new(20, 20);
+        let mut map = Map::init("test".into(), size, Tile::Wall(WallKind::default()));
+        let room = WorldRect::new(WorldPoint::new(8, 8), WorldSize::new(4, 4));
+        for x in room.x_range() {
+            for y in room.y_range() {
+                map[&WorldPoint::new(x, y)] = Tile::Floor(FloorKind::FloorDefault);
+            }
+        }
+
+        // The sector to the north has a corridor ending at x = 3
+        let mut neighbour = Map::init("north".into(), size, Tile::Wall(WallKind::default()));
+        neighbour[&WorldPoint::new(3, 19)] = Tile::Floor(FloorKind::FloorInterior);
+        let mut borders = SectorBorders::default();
+        borders.insert(Cardinal::N, &neighbour);
+
+        blend_borders(&mut map, &borders);
+
+        for x in 0..size.width {
+            assert_eq!(
+                map[&WorldPoint::new(x, 0)].is_passable(),
+                neighbour[&WorldPoint::new(x, 19)].is_passable()
+            );
+        }
+        assert_eq!(
+            map[&WorldPoint::new(3, 0)],
+            Tile::Floor(FloorKind::FloorInterior)
+        );
+        assert!(map
+            .astar_path(&WorldPoint::new(3, 0), &room.center())
+            .is_some());
+    }
+}
@@ -5,15 +5,16 @@ use crate::color::{Palette, COMMON, FIRE, PLANT, WATER};
 use crate::overworld::SectorInfo;
 use crate::procgen::Spawner;
 use crate::resource::Resources;
-use crate::sector::{FloorKind, Map, Tile, WallKind};
+use crate::sector::{FloorKind, Map, MapLayout, Tile, WallKind};
 use crate::util::{WorldPoint, WorldRect, WorldSize, WorldVector};
 use crate::{component::*, sector};
 
-use super::MapGenerator;
+use super::{blend_borders, spawn_player, spawn_point, MapGenerator};
 
 pub struct Bsp {
     size: WorldSize,
     rooms: Vec<WorldRect>,
+    layout: Option<MapLayout>,
 }
 
 impl Bsp {
@@ -21,6 +22,7 @@ impl Bsp {
         Self {
             size,
             rooms: Vec::new(),
+            layout: None,
         }
     }
 }
@@ -129,8 +131,10 @@ fn apply_room_to_map(map: &mut Map, room: &WorldRect) {
     }
 }
 
-fn draw_corridor(map: &mut Map, start: &WorldPoint, end: &WorldPoint) {
+/// Dig an L-shaped corridor and return every tile along it, start included
+fn draw_corridor(map: &mut Map, start: &WorldPoint, end: &WorldPoint) -> Vec<WorldPoint> {
     let mut cursor = *start;
+    let mut path = vec![cursor];
 
     while cursor != *end {
         if cursor.x < end.x {
@@ -144,23 +148,17 @@ fn draw_corridor(map: &mut Map, start: &WorldPoint, end: &WorldPoint) {
         }
 
         map[&cursor] = Tile::Floor(FloorKind::default());
+        path.push(cursor);
     }
-}
 
-impl MapGenerator for Bsp {
-    fn generate(
-        &mut self,
-        sector_info: &SectorInfo,
-        resources: &mut Resources,
-        mapgen_history: &mut Vec<sector::Map>,
-    ) -> Map {
-        let mut map = Map::init(
-            "bsp".into(),
-            self.size,
-            Tile::Floor(FloorKind::FloorDefault),
-        );
+    path
+}
 
-        let rng = &mut resources.rng;
+impl Bsp {
+    /// Carve out the rooms and the corridors between them
+    pub fn carve(&mut self, rng: &mut RandomNumberGenerator, mapgen_history: &mut Vec<Map>) -> Map {
+        let mut map = Map::init("bsp".into(), self.size, Tile::Wall(WallKind::default()));
+        self.rooms.clear();
 
         // Initialize with a single Rect
         let mut rects = vec![WorldRect::new(WorldPoint::new(0, 0), self.size)];
@@ -189,8 +187,9 @@ impl MapGenerator for Bsp {
 
         // Sort rooms by left coordinate
         self.rooms.sort_by(|a, b| a.origin.x.cmp(&b.origin.x));
+        let mut layout = MapLayout::new(self.rooms.clone());
 
-        for i in 0..self.rooms.len() - 1 {
+        for i in 0..self.rooms.len().saturating_sub(1) {
             let room = self.rooms[i];
             let next_room = self.rooms[i + 1];
             let start_x = room.origin.x + (rng.roll_dice(1, room.width()) - 1);
@@ -198,63 +197,74 @@ impl MapGenerator for Bsp {
             let end_x = next_room.origin.x + (rng.roll_dice(1, next_room.width()) - 1);
             let end_y = next_room.origin.y + (rng.roll_dice(1, next_room.height()) - 1);
 
-            draw_corridor(
+            let path = draw_corridor(
                 &mut map,
                 &WorldPoint::new(start_x, start_y),
                 &WorldPoint::new(end_x, end_y),
             );
+            layout.add_path(&path);
 
             // Snapshot to mapgen history
             mapgen_history.push(map.clone());
         }
 
-        let points: Vec<_> = map.iter_points().collect();
-
-        // first pass to find all the "all sides" walls and replace them with floor
-        for point in &points {
-            if let Some(Tile::Wall(_)) = map.get(*point) {
-                if let Tile::Wall(WallKind::WallAllSides) =
-                    Tile::Wall(WallKind::from_map_position(&map, *point))
-                {
-                    map[point] = Tile::Floor(FloorKind::default());
-                }
+        // Flip the walls to match their neighbours
+        for point in map.iter_points().collect::<Vec<_>>() {
+            if let Some(Tile::Wall(_)) = map.get(point) {
+                map[&point] = Tile::Wall(WallKind::from_map_position(&map, point));
             }
         }
 
-        // second pass to flip all the walls
-        for point in &points {
-            if let Some(Tile::Wall(_)) = map.get(*point) {
-                map[point] = Tile::Wall(WallKind::from_map_position(&map, *point));
-            }
+        self.layout = Some(layout);
+        map
+    }
+}
+
+impl MapGenerator for Bsp {
+    fn generate(
+        &mut self,
+        sector_info: &SectorInfo,
+        resources: &mut Resources,
+        mapgen_history: &mut Vec<sector::Map>,
+    ) -> Map {
+        let mut map = self.carve(&mut resources.rng, mapgen_history);
+
+        // Carry on the corridors of neighbouring sectors
+        if !sector_info.borders.is_empty() {
+            blend_borders(&mut map, &sector_info.borders);
+            mapgen_history.push(map.clone());
         }
 
         map
     }
+
+    fn layout(&self) -> Option<MapLayout> {
+        self.layout.clone()
+    }
+
+    fn resize(&mut self, size: WorldSize) {
+        self.size = size;
+    }
 }
 impl Spawner for Bsp {
-    fn spawn(&self, _map: &Map, world: &mut hecs::World, resources: &mut Resources) {
+    fn spawn(&self, map: &Map, world: &mut hecs::World, resources: &mut Resources) {
         let rng = &mut resources.rng;
-        let center = self.rooms[0].center();
 
+        // Start in the first room, unless it got cut off from the rest of the map
+        let regions = map.regions();
+        let center = self
+            .rooms
+            .first()
+            .map(|room| room.center())
+            .filter(|center| regions.label(*center) == regions.largest())
+            .or_else(|| spawn_point(map))
+            .unwrap_or_else(|| map.get_rect().center());
+
+        // Rooms are lit, but only a little
+        let player = spawn_player(world, center, center);
         let mut viewshed = Viewshed::default().with_range(10);
         viewshed.set_dirty();
-
-        // Add the player
-        world.spawn((
-            Position::new(center),
-            // Renderable::new(
-            //     Glyph::new('@', COMMON.four, Palette::empty()),
-            //     PLAYER,
-            //     5,
-            //     None,
-            // ),
-            viewshed,
-            Player {},
-            Actor::new(0, 100, 100, 20, 0, ActorKind::Player(None)),
-        ));
-
-        // Add the camera
-        world.spawn((Position::new(center), Camera {}));
+        world.insert_one(player, viewshed).unwrap();
 
         for room_idx in 1..self.rooms.len() {
             let is_populated = rng.roll_dice(1, 5) == 1;
@@ -392,3 +402,32 @@ enum RoomKind {
     Water,
     Plant,
 }
+
+#[cfg(test)]
+mod tests {
+    use bracket_random::prelude::RandomNumberGenerator;
+
+    use crate::procgen::{LockPlan, MAX_LOCKS};
+
+    use super::*;
+
+    #[test]
+    fn rooms_can_be_locked() {
+        let mut locked = 0;
+        for seed in 0..10 {
+            let mut bsp = Bsp::new(WorldSize::new(80, 50));
+            let map = bsp.carve(&mut RandomNumberGenerator::seeded(seed), &mut Vec::new());
+
+            assert!(!bsp.rooms.is_empty(), "seed {}", seed);
+            let layout = bsp.layout().unwrap();
+            assert_eq!(layout.rooms.len(), bsp.rooms.len());
+
+            let start = bsp.rooms[0].center();
+            let mut rng = RandomNumberGenerator::seeded(seed);
+            if LockPlan::new(&map, &layout, start, MAX_LOCKS, &mut rng).is_some() {
+                locked += 1;
+            }
+        }
+        assert!(locked > 0);
+    }
+}
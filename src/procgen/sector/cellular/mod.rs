@@ -0,0 +1,229 @@
+//! Caves grown with a cellular automaton.
+//!
+//! For more info: https://www.roguebasin.com/index.php/Cellular_Automata_Method_for_Generating_Random_Cave-Like_Levels
+use bracket_random::prelude::RandomNumberGenerator;
+use serde::{Deserialize, Serialize};
+
+use crate::data::{Element, PlanetType};
+use crate::overworld::SectorInfo;
+use crate::procgen::Spawner;
+use crate::resource::Resources;
+use crate::sector::{FloorKind, Map, Tile, WallKind};
+use crate::util::{WorldPoint, WorldSize};
+
+use super::{blend_borders, is_edge, spawn_player, spawn_point, MapGenerator};
+
+/// Rules for growing a cave, where walls are the live cells. Neighbours are
+/// the 8 surrounding tiles, and anything off the map counts as a wall.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CaveParams {
+    /// Percent chance that a tile starts out as a wall
+    pub fill_percent: i32,
+    /// A wall is born on a floor tile with at least this many wall
+    /// neighbours. Floors with fewer stay open.
+    pub birth: usize,
+    /// A wall survives with at least this many wall neighbours. Walls with
+    /// fewer crumble into floor.
+    pub survival: usize,
+    /// How many smoothing passes to run after the random fill
+    pub iterations: usize,
+}
+
+impl Default for CaveParams {
+    fn default() -> Self {
+        Self {
+            fill_percent: 45,
+            birth: 5,
+            survival: 4,
+            iterations: 5,
+        }
+    }
+}
+
+impl CaveParams {
+    /// Shape the cave to the planet: fire burns out wide caverns, water
+    /// wears the rock smooth and mountains leave only narrow passages
+    pub fn for_planet(mut self, element: Element, planet_type: PlanetType) -> Self {
+        match element {
+            Element::Fire => {
+                self.fill_percent -= 4;
+                self.iterations = self.iterations.saturating_sub(1);
+            }
+            Element::Water => self.iterations += 2,
+            Element::Plant => {}
+        }
+
+        match planet_type {
+            PlanetType::Mountains => self.fill_percent += 6,
+            PlanetType::Barren => self.fill_percent -= 3,
+            PlanetType::Lush | PlanetType::Aqueus => {}
+        }
+
+        self
+    }
+
+    pub fn for_sector(self, sector_info: &SectorInfo) -> Self {
+        let planet_info = &sector_info.planet_info;
+        self.for_planet(planet_info.element, planet_info.planet_type)
+    }
+}
+
+pub struct CellularCave {
+    size: WorldSize,
+    /// Shaped by each sector's planet (see [`CaveParams::for_sector`])
+    params: CaveParams,
+}
+
+impl CellularCave {
+    pub fn new(size: WorldSize, params: CaveParams) -> Self {
+        Self { size, params }
+    }
+
+    /// Fill and smooth a cave, snapshotting each step. Its regions are
+    /// connected afterwards like every other generator's.
+    pub fn carve(
+        &self,
+        params: &CaveParams,
+        rng: &mut RandomNumberGenerator,
+        mapgen_history: &mut Vec<Map>,
+    ) -> Map {
+        let wall = Tile::Wall(WallKind::default());
+        let floor = Tile::Floor(FloorKind::FloorDefault);
+
+        let mut map = Map::init("cellular cave".into(), self.size, wall);
+        let points: Vec<WorldPoint> = map.iter_points().collect();
+        // The edges stay solid so that the cave is closed off
+        let interior: Vec<WorldPoint> = points
+            .iter()
+            .filter(|point| !is_edge(&map, point))
+            .copied()
+            .collect();
+
+        for point in interior.iter() {
+            if rng.roll_dice(1, 100) > params.fill_percent {
+                map[point] = floor;
+            }
+        }
+        mapgen_history.push(map.clone());
+
+        for _ in 0..params.iterations {
+            let mut next = map.clone();
+            for point in interior.iter() {
+                let walls = wall_neighbours(&map, point);
+                let is_wall = if map[point].is_passable() {
+                    walls >= params.birth
+                } else {
+                    walls >= params.survival
+                };
+                next[point] = if is_wall { wall } else { floor };
+            }
+            map = next;
+            mapgen_history.push(map.clone());
+        }
+
+        map.reshape_walls(&points);
+        mapgen_history.push(map.clone());
+
+        map
+    }
+}
+
+fn wall_neighbours(map: &Map, point: &WorldPoint) -> usize {
+    (-1..=1)
+        .flat_map(|dy| (-1..=1).map(move |dx| (dx, dy)))
+        .filter(|(dx, dy)| (*dx, *dy) != (0, 0))
+        .filter(|(dx, dy)| {
+            map.get(WorldPoint::new(point.x + dx, point.y + dy))
+                .is_none_or(|tile| !tile.is_passable())
+        })
+        .count()
+}
+
+impl MapGenerator for CellularCave {
+    fn generate(
+        &mut self,
+        sector_info: &SectorInfo,
+        resources: &mut Resources,
+        mapgen_history: &mut Vec<Map>,
+    ) -> Map {
+        let params = self.params.for_sector(sector_info);
+        tracing::info!(?params, "Growing cellular cave");
+
+        let mut map = self.carve(&params, &mut resources.rng, mapgen_history);
+
+        // Open the cave up to neighbouring sectors
+        if !sector_info.borders.is_empty() {
+            blend_borders(&mut map, &sector_info.borders);
+            mapgen_history.push(map.clone());
+        }
+
+        map
+    }
+
+    fn resize(&mut self, size: WorldSize) {
+        self.size = size;
+    }
+}
+
+impl Spawner for CellularCave {
+    fn spawn(&self, map: &Map, world: &mut hecs::World, _resources: &mut Resources) {
+        if let Some(point) = spawn_point(map) {
+            spawn_player(world, point, map.get_rect().center());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::procgen::sector::{connect_regions, MIN_REGION_SIZE};
+
+    use super::*;
+
+    fn wall_count(map: &Map) -> usize {
+        map.iter_tiles()
+            .filter(|(_, tile)| !tile.is_passable())
+            .count()
+    }
+
+    #[test]
+    fn grows_a_connected_cave() {
+        let params = CaveParams::default();
+        let cave = CellularCave::new(WorldSize::new(40, 30), params);
+        let mut history = Vec::new();
+        let mut map = cave.carve(&params, &mut RandomNumberGenerator::seeded(7), &mut history);
+
+        // Fill, every smoothing pass, then reshaping the walls
+        assert_eq!(history.len(), params.iterations + 2);
+        connect_regions(&mut map, MIN_REGION_SIZE);
+        assert_eq!(map.regions().len(), 1);
+        assert!(map
+            .iter_points()
+            .filter(|point| is_edge(&map, point))
+            .all(|point| !map[&point].is_passable()));
+
+        // Same seed, same cave
+        let again = cave.carve(
+            &params,
+            &mut RandomNumberGenerator::seeded(7),
+            &mut Vec::new(),
+        );
+        assert!(map.iter_tiles().eq(again.iter_tiles()));
+    }
+
+    #[test]
+    fn mountains_are_denser() {
+        let cave = CellularCave::new(WorldSize::new(60, 60), CaveParams::default());
+        let walls = |planet_type| {
+            let params = CaveParams::default().for_planet(Element::Plant, planet_type);
+            let map = cave.carve(
+                &params,
+                &mut RandomNumberGenerator::seeded(3),
+                &mut Vec::new(),
+            );
+            wall_count(&map)
+        };
+
+        assert!(walls(PlanetType::Mountains) > walls(PlanetType::Lush));
+    }
+}
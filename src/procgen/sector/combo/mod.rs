@@ -1,23 +1,119 @@
+use bracket_noise::prelude::{FastNoise, NoiseType};
+use bracket_random::prelude::RandomNumberGenerator;
 use euclid::Transform2D;
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    camera::Glyph,
-    color::{Palette, COMMON},
-    component::{Actor, ActorKind, Camera, Player, Position, Renderable, Viewshed},
-    overworld::SectorInfo,
+    overworld::{SectorBorders, SectorInfo},
     procgen::Spawner,
     resource::Resources,
-    sector::{self, Map, Tile},
-    util::{TransformExt, WorldPoint, WorldSize, WorldSpace},
+    sector::{self, Map, MapLayout, Tile},
+    util::{TransformExt, WorldPoint, WorldRect, WorldSize, WorldSpace},
 };
 
-use super::MapGenerator;
+use super::{blend_borders, spawn_player, spawn_point, MapGenerator, MIN_MAP_SIZE};
+
+/// Most times a submap is shrunk looking for somewhere it fits
+const MAX_SHRINKS: usize = 6;
+
+/// Most times a submap is generated again after its generator gives up
+const MAX_REGENERATES: usize = 2;
+
+/// Where a submap goes on the Combo map
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SubMapPlacement {
+    /// Top-left corner at a fixed point
+    At(WorldPoint),
+    Centered,
+    /// Anywhere it fits without covering other submaps, not counting ones
+    /// that cover the whole map
+    Random,
+}
+
+/// Which of the Combo map's tiles a submap may replace
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Blend {
+    #[default]
+    Overwrite,
+    /// Only replace walls, eg. to carve a submap into rock
+    Walls,
+    /// Only replace floors, eg. to scatter ruins over open ground
+    Floors,
+}
+
+impl Blend {
+    fn replaces(&self, tile: &Tile) -> bool {
+        match self {
+            Blend::Overwrite => true,
+            Blend::Walls => matches!(tile, Tile::Wall(_)),
+            Blend::Floors => matches!(tile, Tile::Floor(_)),
+        }
+    }
+}
+
+/// The shape cut out of a submap before it's composited
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum Mask {
+    #[default]
+    Rect,
+    /// The largest ellipse that fits in the submap
+    Circle,
+    /// An ellipse with a noisy edge. Roughness from 0 (smooth) to 1.
+    Blob { roughness: f32 },
+    /// Wherever noise is above the threshold, from -1 to 1
+    Noise { threshold: f32 },
+}
+
+impl Mask {
+    fn noise(&self, rng: &mut RandomNumberGenerator) -> Option<FastNoise> {
+        matches!(self, Mask::Blob { .. } | Mask::Noise { .. }).then(|| {
+            let mut noise = FastNoise::seeded(rng.next_u64());
+            noise.set_noise_type(NoiseType::SimplexFractal);
+            noise.set_frequency(0.08);
+            noise
+        })
+    }
+
+    /// How far inside the mask a tile is, in tiles. Tiles at 0 or below are
+    /// outside it.
+    fn depth(&self, point: WorldPoint, size: WorldSize, noise: Option<&FastNoise>) -> f32 {
+        let edge = (point.x + 1)
+            .min(point.y + 1)
+            .min(size.width - point.x)
+            .min(size.height - point.y) as f32;
+        let sample = || noise.map_or(0., |noise| noise.get_noise(point.x as f32, point.y as f32));
+        let ellipse = |scale: f32| {
+            let radius = size.to_f32() / 2.;
+            let dx = (point.x as f32 + 0.5 - radius.width) / radius.width;
+            let dy = (point.y as f32 + 0.5 - radius.height) / radius.height;
+            (1. - (dx * dx + dy * dy).sqrt() / scale) * radius.width.min(radius.height)
+        };
+
+        match self {
+            Mask::Rect => edge,
+            Mask::Circle => ellipse(1.),
+            Mask::Blob { roughness } => ellipse((1. + roughness * sample()).max(0.1)),
+            Mask::Noise { threshold } => {
+                if sample() > *threshold {
+                    edge
+                } else {
+                    0.
+                }
+            }
+        }
+    }
+}
 
 /// SubMap is an internal representation of an inner MapGenerator for Combo's MapTemplate
 pub struct SubMap {
     mapgen: Box<dyn MapGenerator>,
     size: WorldSize,
-    dest_point: WorldPoint,
+    placement: SubMapPlacement,
+    blend: Blend,
+    mask: Mask,
+    /// Tiles within this far of the mask's edge are dropped more the closer
+    /// they get to it
+    feather: i32,
 }
 
 impl SubMap {
@@ -25,20 +121,157 @@ impl SubMap {
         Self {
             mapgen,
             size,
-            dest_point,
+            placement: SubMapPlacement::At(dest_point),
+            blend: Blend::default(),
+            mask: Mask::default(),
+            feather: 0,
+        }
+    }
+
+    pub fn with_placement(mut self, placement: SubMapPlacement) -> Self {
+        self.placement = placement;
+        self
+    }
+
+    pub fn with_blend(mut self, blend: Blend) -> Self {
+        self.blend = blend;
+        self
+    }
+
+    pub fn with_mask(mut self, mask: Mask) -> Self {
+        self.mask = mask;
+        self
+    }
+
+    pub fn with_feather(mut self, feather: i32) -> Self {
+        self.feather = feather;
+        self
+    }
+
+    /// Pick a size and spot for the submap, shrinking it until it fits
+    fn place(
+        &self,
+        bounds: WorldRect,
+        placed: &[WorldRect],
+        rng: &mut RandomNumberGenerator,
+    ) -> Option<WorldRect> {
+        let mut size = self.size;
+        for _ in 0..=MAX_SHRINKS {
+            if let Some(dest) = self.find_spot(size, bounds, placed, rng) {
+                return Some(WorldRect::new(dest, size));
+            }
+
+            let smaller = (size * 3 / 4)
+                .max(WorldSize::splat(MIN_MAP_SIZE))
+                .min(bounds.size);
+            if smaller == size {
+                break;
+            }
+            size = smaller;
+            tracing::debug!(?size, placement = ?self.placement, "Shrinking submap to fit");
+        }
+        None
+    }
+
+    fn find_spot(
+        &self,
+        size: WorldSize,
+        bounds: WorldRect,
+        placed: &[WorldRect],
+        rng: &mut RandomNumberGenerator,
+    ) -> Option<WorldPoint> {
+        let fits = |dest: WorldPoint| bounds.contains_rect(&WorldRect::new(dest, size));
+        match self.placement {
+            SubMapPlacement::At(dest) => Some(dest).filter(|dest| fits(*dest)),
+            SubMapPlacement::Centered => Some(bounds.origin + (bounds.size - size).to_vector() / 2)
+                .filter(|dest| fits(*dest)),
+            SubMapPlacement::Random => {
+                let spots: Vec<WorldPoint> = (0..=bounds.height() - size.height)
+                    .flat_map(|y| {
+                        (0..=bounds.width() - size.width).map(move |x| WorldPoint::new(x, y))
+                    })
+                    .map(|point| point + bounds.origin.to_vector())
+                    .filter(|dest| {
+                        let rect = WorldRect::new(*dest, size);
+                        fits(*dest) && !placed.iter().any(|other| other.intersects(&rect))
+                    })
+                    .collect();
+                (!spots.is_empty()).then(|| spots[rng.range(0, spots.len())])
+            }
         }
     }
 
     fn generate(
         &mut self,
+        size: WorldSize,
         resources: &mut Resources,
         mapgen_history: &mut Vec<Map>,
         mut sector_info: SectorInfo,
     ) -> Map {
-        // TODO: Submap vs. Map generation. Submap needs a size, total map just needs SectorInfo
-        sector_info.size = self.size;
-        self.mapgen
-            .generate(&sector_info, resources, mapgen_history)
+        sector_info.size = size;
+        self.mapgen.resize(size);
+        // Submaps don't line up with the sector's edges, Combo blends them in afterwards
+        sector_info.borders = SectorBorders::default();
+
+        let mut fallbacks = resources.mapgen_fallbacks;
+        let mut submap = self
+            .mapgen
+            .generate(&sector_info, resources, mapgen_history);
+        for attempt in 1..=MAX_REGENERATES {
+            if resources.mapgen_fallbacks == fallbacks {
+                break;
+            }
+            fallbacks = resources.mapgen_fallbacks;
+            tracing::warn!(attempt, "Submap generator gave up; generating it again");
+            submap = self
+                .mapgen
+                .generate(&sector_info, resources, mapgen_history);
+        }
+        submap
+    }
+
+    /// Copy the submap's tiles onto the Combo map with its top-left corner at
+    /// `dest`, through the mask and blend. Returns whether every tile made it.
+    fn composite(
+        &self,
+        map: &mut Map,
+        submap: &Map,
+        dest: WorldPoint,
+        rng: &mut RandomNumberGenerator,
+    ) -> bool {
+        // Create a transform to translate submap space into Combo map space
+        let xform =
+            Transform2D::<i32, WorldSpace, WorldSpace>::from_points(submap.get_rect().origin, dest);
+        let size = submap.get_rect().size;
+        let noise = self.mask.noise(rng);
+
+        let mut changed = Vec::new();
+        for (subpoint, subtile) in submap.iter_tiles() {
+            let map_point = xform.transform_point(subpoint);
+            if !map.contains(map_point) || !self.blend.replaces(&map[&map_point]) {
+                continue;
+            }
+
+            let depth = self.mask.depth(subpoint, size, noise.as_ref());
+            if depth <= 0. {
+                continue;
+            }
+            // Tiles get less likely to make it the closer they are to the edge
+            let feather = self.feather as f32;
+            if depth < feather && rng.range(0., feather) >= depth {
+                continue;
+            }
+
+            map[&map_point] = *subtile;
+            changed.push(map_point);
+        }
+
+        // Anything but a plain rectangle leaves walls with the wrong shape
+        let plain = self.blend == Blend::Overwrite && self.mask == Mask::Rect && self.feather <= 0;
+        if !plain {
+            map.reshape_walls(&changed);
+        }
+        changed.len() == submap.get_rect().area() as usize
     }
 }
 
@@ -63,11 +296,15 @@ impl MapTemplate {
 /// Combo combines multiple MapGenerator impls and composites them together into a megamap
 pub struct Combo {
     template: MapTemplate,
+    layout: Option<MapLayout>,
 }
 
 impl Combo {
     pub fn new(template: MapTemplate) -> Self {
-        Self { template }
+        Self {
+            template,
+            layout: None,
+        }
     }
 }
 
@@ -86,24 +323,30 @@ impl MapGenerator for Combo {
         );
 
         mapgen_history.push(map.clone());
+        let mut sublayouts: Vec<MapLayout> = Vec::new();
 
         // Composite submaps in-order onto the Combo map
+        let mut placed: Vec<WorldRect> = Vec::new();
         for gen in &mut self.template.submaps {
-            let submap = gen.generate(resources, mapgen_history, sector_info.clone());
-
-            // Create a transform to translate submap space into Combo map space
-            let xform = Transform2D::<i32, WorldSpace, WorldSpace>::from_points(
-                submap.get_rect().origin,
-                gen.dest_point,
-            );
-
-            // Iterate over the submap, translate to the Combo map's
-            // coordinates, and overwrite the tile if it fits.
-            for (i, (subpoint, subtile)) in submap.iter_tiles().enumerate() {
-                let map_point = xform.transform_point(subpoint);
-                if map.contains(map_point) {
-                    map[&map_point] = *subtile;
-                }
+            let Some(rect) = gen.place(*map.get_rect(), &placed, &mut resources.rng) else {
+                tracing::warn!(size = ?gen.size, placement = ?gen.placement, "Submap doesn't fit");
+                continue;
+            };
+            let submap = gen.generate(rect.size, resources, mapgen_history, sector_info.clone());
+            let whole = gen.composite(&mut map, &submap, rect.origin, &mut resources.rng);
+
+            // Submaps covering the whole map are backgrounds that others go on top of
+            if rect != *map.get_rect() {
+                placed.push(rect);
+            }
+
+            // Rooms are only where the layout says if nothing was masked,
+            // blended or feathered away, and nothing was drawn over them since
+            sublayouts
+                .retain(|sublayout| !sublayout.rooms.iter().any(|room| room.intersects(&rect)));
+            if let Some(mut sublayout) = gen.mapgen.layout().filter(|_| whole) {
+                sublayout.translate(rect.origin - submap.get_rect().origin);
+                sublayouts.push(sublayout);
             }
 
             // Create a frame after each submap is composited. Submaps will also
@@ -111,32 +354,135 @@ impl MapGenerator for Combo {
             mapgen_history.push(map.clone())
         }
 
+        if !sector_info.borders.is_empty() {
+            blend_borders(&mut map, &sector_info.borders);
+            mapgen_history.push(map.clone());
+        }
+
+        self.layout = sublayouts.into_iter().reduce(|mut layout, sublayout| {
+            layout.extend(sublayout);
+            layout
+        });
         map
     }
+
+    fn layout(&self) -> Option<MapLayout> {
+        self.layout.clone()
+    }
 }
 
 impl Spawner for Combo {
     fn spawn(&self, map: &Map, world: &mut hecs::World, _resources: &mut Resources) {
-        for point in map.iter_points() {
-            if let Tile::Floor(_) = map[&point] {
-                // Add the player
-                world.spawn((
-                    Position::new(point),
-                    // Renderable::new(
-                    //     Glyph::new('@', COMMON.four, Palette::empty()),
-                    //     PLAYER,
-                    //     5,
-                    //     None,
-                    // ),
-                    Viewshed::default().with_init().with_range(100),
-                    Player {},
-                    Actor::new(0, 100, 100, 20, 0, ActorKind::Player(None)),
-                ));
-
-                // Add the camera
-                world.spawn((Position::new(map.get_rect().center()), Camera {}));
-                break;
-            }
+        // Start in the largest region so that the player can reach most of the map
+        if let Some(point) = spawn_point(map) {
+            spawn_player(world, point, map.get_rect().center());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        procgen::{Dla, DlaParams},
+        sector::{FloorKind, WallKind},
+    };
+
+    use super::*;
+
+    fn submap(size: WorldSize, placement: SubMapPlacement) -> SubMap {
+        // Never generated; only placement and compositing are tested
+        let mapgen = Box::new(Dla::new(size, DlaParams::default()));
+        SubMap::new(mapgen, size, WorldPoint::zero()).with_placement(placement)
+    }
+
+    #[test]
+    fn places_and_shrinks() {
+        let bounds = WorldRect::new(WorldPoint::zero(), WorldSize::new(30, 20));
+        let mut rng = RandomNumberGenerator::seeded(1);
+
+        let centered = submap(WorldSize::new(10, 10), SubMapPlacement::Centered);
+        let rect = centered.place(bounds, &[], &mut rng).unwrap();
+        assert_eq!(rect.origin, WorldPoint::new(10, 5));
+
+        // Too big for the map, so it's shrunk until it fits
+        let big = submap(
+            WorldSize::new(40, 40),
+            SubMapPlacement::At(WorldPoint::new(2, 2)),
+        );
+        let rect = big.place(bounds, &[], &mut rng).unwrap();
+        assert!(bounds.contains_rect(&rect));
+        assert_eq!(rect.origin, WorldPoint::new(2, 2));
+
+        // Random spots stay clear of what's already placed
+        let taken = WorldRect::new(WorldPoint::zero(), WorldSize::new(20, 20));
+        let random = submap(WorldSize::new(8, 8), SubMapPlacement::Random);
+        for _ in 0..10 {
+            let rect = random.place(bounds, &[taken], &mut rng).unwrap();
+            assert!(rect.min_x() >= 20 && bounds.contains_rect(&rect));
         }
+
+        let outside = submap(
+            WorldSize::new(8, 8),
+            SubMapPlacement::At(WorldPoint::new(40, 0)),
+        );
+        assert_eq!(outside.place(bounds, &[], &mut rng), None);
+    }
+
+    #[test]
+    fn masks_and_blends() {
+        let wall = Tile::Wall(WallKind::WallDefault);
+        let floor = Tile::Floor(FloorKind::FloorDefault);
+        let interior = Tile::Floor(FloorKind::FloorInterior);
+        let size = WorldSize::new(10, 10);
+        let patch = Map::init("patch".into(), size, interior);
+        let mut rng = RandomNumberGenerator::seeded(4);
+
+        // A plain rectangle copies every tile
+        let mut map = Map::init("test".into(), WorldSize::new(12, 12), floor);
+        assert!(submap(size, SubMapPlacement::Centered).composite(
+            &mut map,
+            &patch,
+            WorldPoint::new(1, 1),
+            &mut rng
+        ));
+
+        // A circle leaves the corners alone
+        let mut map = Map::init("test".into(), WorldSize::new(12, 12), floor);
+        let whole = submap(size, SubMapPlacement::Centered)
+            .with_mask(Mask::Circle)
+            .composite(&mut map, &patch, WorldPoint::new(1, 1), &mut rng);
+        assert!(!whole);
+        assert_eq!(map[&WorldPoint::new(1, 1)], floor);
+        assert_eq!(map[&WorldPoint::new(6, 6)], interior);
+
+        // Only walls are replaced, so the floor on the left stays as it was
+        let mut map = Map::init("test".into(), WorldSize::new(10, 10), wall);
+        for y in 0..10 {
+            map[&WorldPoint::new(0, y)] = floor;
+        }
+        let whole = submap(size, SubMapPlacement::Centered)
+            .with_blend(Blend::Walls)
+            .composite(&mut map, &patch, WorldPoint::zero(), &mut rng);
+        assert!(!whole);
+        assert!(map.iter_tiles().all(|(point, tile)| if point.x == 0 {
+            *tile == floor
+        } else {
+            *tile == interior
+        }));
+
+        // A feathered edge drops some of the tiles near it but keeps the middle
+        let mut map = Map::init("test".into(), WorldSize::new(10, 10), wall);
+        let whole = submap(size, SubMapPlacement::Centered)
+            .with_feather(3)
+            .composite(&mut map, &patch, WorldPoint::zero(), &mut rng);
+        assert!(!whole);
+        let edge: Vec<Tile> = map
+            .iter_tiles()
+            .filter(|(point, _)| point.x == 0 || point.y == 0 || point.x == 9 || point.y == 9)
+            .map(|(_, tile)| *tile)
+            .collect();
+        assert!(edge.iter().any(|tile| matches!(tile, Tile::Wall(_))));
+        assert!(edge.contains(&interior));
+        assert_eq!(map[&WorldPoint::new(5, 5)], interior);
     }
 }
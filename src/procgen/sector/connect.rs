@@ -0,0 +1,163 @@
+//! Post-processing that makes every open tile of a generated map reachable
+use std::collections::VecDeque;
+
+use hecs::{Entity, World};
+
+use crate::{
+    component::{Actor, ActorKind, Camera, Cardinal, Player, Position, Viewshed},
+    sector::{Map, Tile, WallKind},
+    util::{PointExt, WorldPoint},
+};
+
+/// Pockets with fewer tiles than this are filled in instead of connected
+pub const MIN_REGION_SIZE: usize = 6;
+
+/// Fill in small pockets, then dig a tunnel from every other region to the
+/// largest one. Regions touching the edge of the map are never filled since
+/// they may line up with a neighbouring sector.
+pub fn connect_regions(map: &mut Map, min_region_size: usize) {
+    let regions = map.regions();
+    let Some(largest) = regions.largest() else {
+        return;
+    };
+
+    let width = map.get_width();
+    let mut connected = vec![false; (width * map.get_height()) as usize];
+    for point in regions.points(largest) {
+        connected[point.to_index(width)] = true;
+    }
+
+    let mut changed = Vec::new();
+    for label in (0..regions.len()).filter(|label| *label != largest) {
+        let points: Vec<WorldPoint> = regions.points(label).collect();
+
+        if points.len() < min_region_size && !points.iter().any(|point| is_edge(map, point)) {
+            for point in points {
+                map[&point] = Tile::Wall(WallKind::default());
+                changed.push(point);
+            }
+            continue;
+        }
+
+        let floor = map[&points[0]];
+        for point in tunnel(map, &points, &connected).unwrap_or_default() {
+            if !map[&point].is_passable() {
+                map[&point] = floor;
+                changed.push(point);
+            }
+            connected[point.to_index(width)] = true;
+        }
+        for point in points {
+            connected[point.to_index(width)] = true;
+        }
+    }
+
+    map.reshape_walls(&changed);
+}
+
+/// Shortest straight-line path from any of `from` to a connected tile. Tunnels
+/// stay off the map's edges so that sector borders are left alone.
+fn tunnel(map: &Map, from: &[WorldPoint], connected: &[bool]) -> Option<Vec<WorldPoint>> {
+    let width = map.get_width();
+    let mut parents: Vec<Option<WorldPoint>> = vec![None; connected.len()];
+    let mut seen = vec![false; connected.len()];
+    let mut queue = VecDeque::new();
+    for point in from {
+        seen[point.to_index(width)] = true;
+        queue.push_back(*point);
+    }
+
+    while let Some(point) = queue.pop_front() {
+        if connected[point.to_index(width)] {
+            let mut path = vec![point];
+            let mut cursor = point;
+            while let Some(parent) = parents[cursor.to_index(width)] {
+                path.push(parent);
+                cursor = parent;
+            }
+            return Some(path);
+        }
+
+        for cardinal in [Cardinal::N, Cardinal::S, Cardinal::E, Cardinal::W] {
+            let next = point + *cardinal.to_vector();
+            if !map.contains(next) || seen[next.to_index(width)] {
+                continue;
+            }
+            if is_edge(map, &next) && !connected[next.to_index(width)] {
+                continue;
+            }
+            seen[next.to_index(width)] = true;
+            parents[next.to_index(width)] = Some(point);
+            queue.push_back(next);
+        }
+    }
+
+    None
+}
+
+/// Whether a point is on the outermost ring of the map
+pub fn is_edge(map: &Map, point: &WorldPoint) -> bool {
+    point.x == 0
+        || point.y == 0
+        || point.x == map.get_width() - 1
+        || point.y == map.get_height() - 1
+}
+
+/// Where to put the player: the tile of the largest region closest to the
+/// middle of the map
+pub fn spawn_point(map: &Map) -> Option<WorldPoint> {
+    let regions = map.regions();
+    let center = map.get_rect().center();
+    regions
+        .points(regions.largest()?)
+        .min_by_key(|point| (*point - center).square_length())
+}
+
+/// Spawn the player at `point`, and the camera looking at `center`
+pub fn spawn_player(world: &mut World, point: WorldPoint, center: WorldPoint) -> Entity {
+    let player = world.spawn((
+        Position::new(point),
+        Viewshed::default().with_init().with_range(100),
+        Player {},
+        Actor::new(0, 100, 100, 20, 0, ActorKind::Player(None)),
+    ));
+    world.spawn((Position::new(center), Camera {}));
+    player
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sector::FloorKind;
+
+    use super::*;
+
+    #[test]
+    fn connects_and_fills() {
+        let w = Tile::Wall(WallKind::default());
+        let f = Tile::Floor(FloorKind::default());
+
+        #[rustfmt::skip]
+        let tiles: Vec<Tile> = vec![
+            w, w, w, w, w, w, w, w, w, w,
+            w, f, f, f, w, w, w, f, f, w,
+            w, f, f, f, w, w, w, f, f, w,
+            w, f, f, f, w, w, w, f, f, w,
+            w, w, w, w, w, w, w, w, w, w,
+            w, w, w, w, w, w, w, w, w, w,
+            w, w, w, w, w, f, w, w, w, w,
+            w, w, w, w, w, w, w, w, w, w,
+        ];
+        let mut map = Map::new(String::from("test"), 10, 8, tiles);
+        assert_eq!(map.regions().len(), 3);
+
+        connect_regions(&mut map, MIN_REGION_SIZE);
+
+        let regions = map.regions();
+        assert_eq!(regions.len(), 1);
+        assert!(!map[&WorldPoint::new(5, 6)].is_passable(), "pocket filled");
+        assert!(map[&WorldPoint::new(7, 1)].is_passable(), "room kept");
+
+        let spawn = spawn_point(&map).unwrap();
+        assert_eq!(regions.label(spawn), regions.largest());
+    }
+}
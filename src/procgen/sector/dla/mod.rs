@@ -0,0 +1,189 @@
+//! Diffusion-limited aggregation: particles drift in from the edges and stick
+//! to the floor they bump into, growing branching lava tubes out from the
+//! middle of the map.
+//!
+//! For more info: https://www.roguebasin.com/index.php/Diffusion-limited_aggregation
+use bracket_random::prelude::RandomNumberGenerator;
+use serde::{Deserialize, Serialize};
+
+use crate::component::Cardinal;
+use crate::overworld::SectorInfo;
+use crate::procgen::Spawner;
+use crate::resource::Resources;
+use crate::sector::{FloorKind, Map, Tile, WallKind};
+use crate::util::{WorldPoint, WorldSize};
+
+use super::{blend_borders, is_edge, spawn_player, spawn_point, MapGenerator};
+
+/// Give up on the floor target after this many particles
+const MAX_PARTICLES: usize = 50_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DlaParams {
+    /// Percent chance that a particle steps towards the center rather than
+    /// in a random direction. Higher values grow straighter spokes.
+    pub attraction: i32,
+    /// Stop once this fraction of the map is floor
+    pub floor_percent: f32,
+    /// Particles that stick between history frames
+    pub frame_every: usize,
+}
+
+impl Default for DlaParams {
+    fn default() -> Self {
+        Self {
+            attraction: 25,
+            floor_percent: 0.3,
+            frame_every: 25,
+        }
+    }
+}
+
+pub struct Dla {
+    size: WorldSize,
+    params: DlaParams,
+}
+
+impl Dla {
+    pub fn new(size: WorldSize, params: DlaParams) -> Self {
+        Self { size, params }
+    }
+
+    /// Grow floor out from the center of the map until enough of it is floor
+    pub fn grow(&self, rng: &mut RandomNumberGenerator, mapgen_history: &mut Vec<Map>) -> Map {
+        let floor = Tile::Floor(FloorKind::FloorDefault);
+        let mut map = Map::init("dla".into(), self.size, Tile::Wall(WallKind::default()));
+        if self.size.width < 3 || self.size.height < 3 {
+            return map;
+        }
+
+        // Seed the attractor with a small cross
+        let center = map.get_rect().center();
+        let mut floors = 0;
+        for point in std::iter::once(center).chain(
+            [Cardinal::N, Cardinal::S, Cardinal::E, Cardinal::W]
+                .iter()
+                .map(|cardinal| center + *cardinal.to_vector()),
+        ) {
+            if !is_edge(&map, &point) {
+                map[&point] = floor;
+                floors += 1;
+            }
+        }
+        mapgen_history.push(map.clone());
+
+        let interior = (self.size.width - 2) * (self.size.height - 2);
+        let target = (interior as f32 * self.params.floor_percent.clamp(0., 1.)) as usize;
+
+        let mut particles = 0;
+        let mut stuck = 0;
+        while floors < target && particles < MAX_PARTICLES {
+            particles += 1;
+            let mut particle = WorldPoint::new(
+                rng.range(1, self.size.width - 1),
+                rng.range(1, self.size.height - 1),
+            );
+            if map[&particle].is_passable() {
+                continue;
+            }
+
+            // Wander through the rock until the next step would hit floor
+            loop {
+                let next = if rng.roll_dice(1, 100) <= self.params.attraction {
+                    towards(particle, center)
+                } else {
+                    let direction = [Cardinal::N, Cardinal::S, Cardinal::E, Cardinal::W]
+                        [rng.range(0, 4) as usize];
+                    particle + *direction.to_vector()
+                };
+
+                if is_edge(&map, &next) {
+                    continue;
+                }
+                if map[&next].is_passable() {
+                    break;
+                }
+                particle = next;
+            }
+
+            map[&particle] = floor;
+            floors += 1;
+            stuck += 1;
+            if stuck % self.params.frame_every.max(1) == 0 {
+                mapgen_history.push(map.clone());
+            }
+        }
+
+        let points: Vec<WorldPoint> = map.iter_points().collect();
+        map.reshape_walls(&points);
+        mapgen_history.push(map.clone());
+
+        map
+    }
+}
+
+/// One step along whichever axis is furthest from the target
+fn towards(from: WorldPoint, to: WorldPoint) -> WorldPoint {
+    let delta = to - from;
+    if delta.x.abs() >= delta.y.abs() {
+        WorldPoint::new(from.x + delta.x.signum(), from.y)
+    } else {
+        WorldPoint::new(from.x, from.y + delta.y.signum())
+    }
+}
+
+impl MapGenerator for Dla {
+    fn generate(
+        &mut self,
+        sector_info: &SectorInfo,
+        resources: &mut Resources,
+        mapgen_history: &mut Vec<Map>,
+    ) -> Map {
+        let mut map = self.grow(&mut resources.rng, mapgen_history);
+
+        // Carry on the tubes of neighbouring sectors
+        if !sector_info.borders.is_empty() {
+            blend_borders(&mut map, &sector_info.borders);
+            mapgen_history.push(map.clone());
+        }
+
+        map
+    }
+
+    fn resize(&mut self, size: WorldSize) {
+        self.size = size;
+    }
+}
+
+impl Spawner for Dla {
+    fn spawn(&self, map: &Map, world: &mut hecs::World, _resources: &mut Resources) {
+        if let Some(point) = spawn_point(map) {
+            spawn_player(world, point, map.get_rect().center());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grows_from_the_center() {
+        let dla = Dla::new(WorldSize::new(40, 30), DlaParams::default());
+        let mut history = Vec::new();
+        let map = dla.grow(&mut RandomNumberGenerator::seeded(5), &mut history);
+
+        let floors = map
+            .iter_tiles()
+            .filter(|(_, tile)| tile.is_passable())
+            .count();
+        assert!(floors >= (38 * 28) * 3 / 10, "{}", floors);
+        assert!(history.len() > 2);
+
+        // Every particle sticks to the aggregate, so it's all one piece
+        let regions = map.regions();
+        assert_eq!(regions.len(), 1);
+        assert_eq!(regions.label(map.get_rect().center()), Some(0));
+    }
+}
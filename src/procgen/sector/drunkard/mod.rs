@@ -0,0 +1,183 @@
+//! Tunnels dug by random walkers, eg. for mines.
+//!
+//! For more info: https://www.roguebasin.com/index.php/Random_Walk_Cave_Generation
+use bracket_random::prelude::RandomNumberGenerator;
+use serde::{Deserialize, Serialize};
+
+use crate::component::Cardinal;
+use crate::overworld::SectorInfo;
+use crate::procgen::Spawner;
+use crate::resource::Resources;
+use crate::sector::{FloorKind, Map, Tile, WallKind};
+use crate::util::{WorldPoint, WorldSize};
+
+use super::{blend_borders, is_edge, spawn_player, spawn_point, MapGenerator};
+
+/// Give up on the floor target after this many walkers
+const MAX_WALKERS: usize = 10_000;
+
+/// Where each new walker starts digging
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WalkerSpawn {
+    /// Always the middle of the map, for one sprawling hub
+    Center,
+    /// Anywhere, for scattered tunnels (connected up afterwards)
+    Random,
+    /// A random tile that's already been dug, for branching tunnels
+    Floor,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DrunkardParams {
+    pub spawn: WalkerSpawn,
+    /// Walkers that set off together. Each batch is one history frame.
+    pub walkers: usize,
+    /// Steps a walker takes before it passes out
+    pub lifetime: usize,
+    /// Stop once this fraction of the map is floor
+    pub floor_percent: f32,
+}
+
+impl Default for DrunkardParams {
+    fn default() -> Self {
+        Self {
+            spawn: WalkerSpawn::Floor,
+            walkers: 4,
+            lifetime: 200,
+            floor_percent: 0.4,
+        }
+    }
+}
+
+pub struct DrunkardsWalk {
+    size: WorldSize,
+    params: DrunkardParams,
+}
+
+impl DrunkardsWalk {
+    pub fn new(size: WorldSize, params: DrunkardParams) -> Self {
+        Self { size, params }
+    }
+
+    /// Send out batches of walkers until enough of the map is floor
+    pub fn dig(&self, rng: &mut RandomNumberGenerator, mapgen_history: &mut Vec<Map>) -> Map {
+        let floor = Tile::Floor(FloorKind::FloorDefault);
+        let mut map = Map::init(
+            "drunkard's walk".into(),
+            self.size,
+            Tile::Wall(WallKind::default()),
+        );
+
+        let center = map.get_rect().center();
+        let interior = (self.size.width - 2).max(0) * (self.size.height - 2).max(0);
+        let target = (interior as f32 * self.params.floor_percent.clamp(0., 1.)) as usize;
+
+        let mut floors = vec![center];
+        map[&center] = floor;
+
+        let mut spawned = 0;
+        while floors.len() < target && spawned < MAX_WALKERS {
+            for _ in 0..self.params.walkers.max(1) {
+                let mut walker = match self.params.spawn {
+                    WalkerSpawn::Center => center,
+                    WalkerSpawn::Random => WorldPoint::new(
+                        rng.range(1, self.size.width - 1),
+                        rng.range(1, self.size.height - 1),
+                    ),
+                    WalkerSpawn::Floor => *rng.random_slice_entry(&floors).unwrap_or(&center),
+                };
+                spawned += 1;
+
+                for _ in 0..self.params.lifetime {
+                    if !map[&walker].is_passable() {
+                        map[&walker] = floor;
+                        floors.push(walker);
+                    }
+
+                    let direction = [Cardinal::N, Cardinal::S, Cardinal::E, Cardinal::W]
+                        [rng.range(0, 4) as usize];
+                    let next = walker + *direction.to_vector();
+                    // Stay off the edges so the map is closed off
+                    if map.contains(next) && !is_edge(&map, &next) {
+                        walker = next;
+                    }
+                }
+            }
+            mapgen_history.push(map.clone());
+        }
+
+        let points: Vec<WorldPoint> = map.iter_points().collect();
+        map.reshape_walls(&points);
+        mapgen_history.push(map.clone());
+
+        map
+    }
+}
+
+impl MapGenerator for DrunkardsWalk {
+    fn generate(
+        &mut self,
+        sector_info: &SectorInfo,
+        resources: &mut Resources,
+        mapgen_history: &mut Vec<Map>,
+    ) -> Map {
+        let mut map = self.dig(&mut resources.rng, mapgen_history);
+
+        // Carry on the tunnels of neighbouring sectors
+        if !sector_info.borders.is_empty() {
+            blend_borders(&mut map, &sector_info.borders);
+            mapgen_history.push(map.clone());
+        }
+
+        map
+    }
+
+    fn resize(&mut self, size: WorldSize) {
+        self.size = size;
+    }
+}
+
+impl Spawner for DrunkardsWalk {
+    fn spawn(&self, map: &Map, world: &mut hecs::World, _resources: &mut Resources) {
+        if let Some(point) = spawn_point(map) {
+            spawn_player(world, point, map.get_rect().center());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::procgen::sector::{connect_regions, MIN_REGION_SIZE};
+
+    use super::*;
+
+    #[test]
+    fn digs_to_target() {
+        for spawn in [WalkerSpawn::Center, WalkerSpawn::Random, WalkerSpawn::Floor] {
+            let params = DrunkardParams {
+                spawn,
+                ..Default::default()
+            };
+            let walk = DrunkardsWalk::new(WorldSize::new(40, 30), params);
+            let mut history = Vec::new();
+            let mut map = walk.dig(&mut RandomNumberGenerator::seeded(11), &mut history);
+
+            let floors = map
+                .iter_tiles()
+                .filter(|(_, tile)| tile.is_passable())
+                .count();
+            assert!(floors >= (38 * 28) * 2 / 5, "{:?}: {}", spawn, floors);
+            assert!(history.len() > 1);
+            // Walkers that start on dug floor can't leave anything cut off
+            if spawn == WalkerSpawn::Random {
+                connect_regions(&mut map, MIN_REGION_SIZE);
+            }
+            assert_eq!(map.regions().len(), 1, "{:?}", spawn);
+            assert!(map
+                .iter_points()
+                .filter(|point| is_edge(&map, point))
+                .all(|point| !map[&point].is_passable()));
+        }
+    }
+}
@@ -0,0 +1,365 @@
+//! Lock-and-key puzzles over a sector's [`MapLayout`].
+//!
+//! Rooms along the way from the entrance to the furthest room get locked
+//! doors, in order. Each key is placed somewhere reachable with only the keys
+//! before it, so the player can always open every lock, and the furthest room
+//! holds the rewards.
+use std::collections::{HashSet, VecDeque};
+
+use bracket_random::prelude::RandomNumberGenerator;
+use hecs::World;
+
+use crate::{
+    component::{BlocksTile, Door, Item, Key, Locked, Player, Position},
+    data::{LootContext, LootTables},
+    overworld::SectorInfo,
+    resource::Resources,
+    sector::{Map, MapLayout},
+    util::WorldPoint,
+};
+
+/// Most locks a single sector gets
+pub const MAX_LOCKS: usize = 3;
+/// Loot table rolled for the reward room
+pub const REWARD_TABLE: &str = "vault";
+/// Item name of the keys
+pub const KEY_ITEM: &str = "keycard";
+
+/// A locked room and where its key is
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lock {
+    pub key_id: u32,
+    pub room: usize,
+    /// Every doorway into the room
+    pub doors: Vec<WorldPoint>,
+    pub key: WorldPoint,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LockPlan {
+    /// In the order the player can open them
+    pub locks: Vec<Lock>,
+    /// Behind the last lock
+    pub reward_room: usize,
+}
+
+impl LockPlan {
+    /// Plan up to `max_locks` locks for a player starting at `start`.
+    /// Returns None if the layout has no room worth locking.
+    pub fn new(
+        map: &Map,
+        layout: &MapLayout,
+        start: WorldPoint,
+        max_locks: usize,
+        rng: &mut RandomNumberGenerator,
+    ) -> Option<LockPlan> {
+        let mut layout = layout.clone();
+        layout.set_entrance(layout.room_at(start)?);
+
+        // Candidates are the rooms on the way to the furthest one, in order
+        let goal = layout
+            .furthest_room()
+            .filter(|goal| *goal != layout.entrance())?;
+        let route = route(&layout, goal);
+        let mut rooms: Vec<usize> = route[1..route.len() - 1].to_vec();
+        while rooms.len() + 1 > max_locks.max(1) {
+            rooms.remove(rng.range(0, rooms.len() as i32) as usize);
+        }
+        rooms.push(goal);
+
+        // Only rooms that can't be reached without going through a doorway
+        let mut locks: Vec<(usize, Vec<WorldPoint>)> = rooms
+            .into_iter()
+            .filter_map(|room| {
+                let mut doors: Vec<WorldPoint> =
+                    layout.doors_of(room).map(|door| door.point).collect();
+                doors.sort_by_key(|point| (point.y, point.x));
+                doors.dedup();
+
+                let sealed = !doors.is_empty() && {
+                    let reach = reachable(map, start, &doors);
+                    !room_tiles(&layout, room).any(|point| reach.contains(&point))
+                };
+                sealed.then_some((room, doors))
+            })
+            .collect();
+        if locks.last().map(|(room, _)| *room) != Some(goal) {
+            return None;
+        }
+
+        // Each key goes behind the lock before it, where possible
+        let mut plan = Vec::with_capacity(locks.len());
+        let mut previous_reach: HashSet<WorldPoint> = HashSet::new();
+        for i in 0..locks.len() {
+            let blocked: Vec<WorldPoint> = locks[i..]
+                .iter()
+                .flat_map(|(_, doors)| doors.iter().copied())
+                .collect();
+            let reach = reachable(map, start, &blocked);
+            let spots: Vec<WorldPoint> = (0..layout.rooms.len())
+                .flat_map(|room| room_tiles(&layout, room))
+                .filter(|point| reach.contains(point) && !blocked.contains(point))
+                .collect();
+            let newly_reached: Vec<WorldPoint> = spots
+                .iter()
+                .filter(|point| !previous_reach.contains(point))
+                .copied()
+                .collect();
+
+            let key = *rng
+                .random_slice_entry(&newly_reached)
+                .or_else(|| rng.random_slice_entry(&spots))?;
+            let (room, doors) = std::mem::take(&mut locks[i]);
+            plan.push(Lock {
+                key_id: i as u32,
+                room,
+                doors,
+                key,
+            });
+            previous_reach = reach;
+        }
+
+        Some(LockPlan {
+            locks: plan,
+            reward_room: goal,
+        })
+    }
+
+    /// Collect every key the player can get to and open its locks until
+    /// nothing changes. Returns the tiles the player ends up able to reach.
+    pub fn solve(&self, map: &Map, start: WorldPoint) -> HashSet<WorldPoint> {
+        let mut held: HashSet<u32> = HashSet::new();
+        loop {
+            let blocked: Vec<WorldPoint> = self
+                .locks
+                .iter()
+                .filter(|lock| !held.contains(&lock.key_id))
+                .flat_map(|lock| lock.doors.iter().copied())
+                .collect();
+            let reach = reachable(map, start, &blocked);
+
+            let keys: HashSet<u32> = self
+                .locks
+                .iter()
+                .filter(|lock| reach.contains(&lock.key))
+                .map(|lock| lock.key_id)
+                .collect();
+            if keys == held {
+                return reach;
+            }
+            held = keys;
+        }
+    }
+
+    /// Spawn a locked door on every doorway, the keys, and `rewards` in the
+    /// reward room
+    pub fn spawn(
+        &self,
+        layout: &MapLayout,
+        rewards: &[String],
+        world: &mut World,
+        rng: &mut RandomNumberGenerator,
+    ) {
+        for lock in self.locks.iter() {
+            for door in lock.doors.iter() {
+                world.spawn((
+                    Position::new(*door),
+                    Door { opened: false },
+                    Locked(lock.key_id),
+                    BlocksTile {},
+                ));
+            }
+            world.spawn((
+                Position::new(lock.key),
+                Item {
+                    name: KEY_ITEM.to_owned(),
+                },
+                Key(lock.key_id),
+            ));
+        }
+
+        let room = layout.rooms[self.reward_room];
+        for name in rewards {
+            let point = WorldPoint::new(
+                rng.range(room.min_x(), room.max_x()),
+                rng.range(room.min_y(), room.max_y()),
+            );
+            world.spawn((Position::new(point), Item { name: name.clone() }));
+        }
+    }
+}
+
+/// Rooms from the entrance to `goal`, both included
+fn route(layout: &MapLayout, goal: usize) -> Vec<usize> {
+    let mut parents: Vec<Option<usize>> = vec![None; layout.rooms.len()];
+    let mut queue = VecDeque::from([layout.entrance()]);
+    let mut seen = vec![false; layout.rooms.len()];
+    seen[layout.entrance()] = true;
+
+    while let Some(room) = queue.pop_front() {
+        for (next, _) in layout.neighbours(room) {
+            if !seen[next] {
+                seen[next] = true;
+                parents[next] = Some(room);
+                queue.push_back(next);
+            }
+        }
+    }
+
+    let mut route = vec![goal];
+    while let Some(parent) = parents[*route.last().unwrap()] {
+        route.push(parent);
+    }
+    route.reverse();
+    route
+}
+
+fn room_tiles(layout: &MapLayout, room: usize) -> impl Iterator<Item = WorldPoint> {
+    let rect = layout.rooms[room];
+    rect.y_range()
+        .flat_map(move |y| rect.x_range().map(move |x| WorldPoint::new(x, y)))
+}
+
+/// Tiles a player at `start` can walk to without crossing `blocked`
+fn reachable(map: &Map, start: WorldPoint, blocked: &[WorldPoint]) -> HashSet<WorldPoint> {
+    let mut seen = HashSet::from([start]);
+    let mut queue = VecDeque::from([start]);
+    while let Some(point) = queue.pop_front() {
+        for (next, _) in map.neighbors(&point) {
+            if !blocked.contains(&next) && seen.insert(next) {
+                queue.push_back(next);
+            }
+        }
+    }
+    seen
+}
+
+/// Where the player is, once spawned
+pub fn player_point(world: &World) -> Option<WorldPoint> {
+    world
+        .query::<(&Position, &Player)>()
+        .iter()
+        .next()
+        .map(|(_, (position, _))| position.p)
+}
+
+/// Lock up a sector's rooms, eg. so that Civilization sectors play like
+/// facilities. Does nothing if the player hasn't spawned inside a room.
+pub fn place_locks(
+    map: &Map,
+    layout: &MapLayout,
+    sector_info: &SectorInfo,
+    world: &mut World,
+    resources: &mut Resources,
+) {
+    let start = player_point(world);
+    let Some(plan) =
+        start.and_then(|start| LockPlan::new(map, layout, start, MAX_LOCKS, &mut resources.rng))
+    else {
+        return;
+    };
+    tracing::info!(
+        locks = plan.locks.len(),
+        reward_room = plan.reward_room,
+        "Placing locked doors"
+    );
+
+    let context = LootContext {
+        element: sector_info.planet_info.element,
+        planet_type: sector_info.planet_info.planet_type,
+        depth: plan.locks.len() as i32,
+    };
+    let rewards = match resources
+        .assets_cache
+        .load::<LootTables>("data.loot_tables")
+    {
+        Ok(tables) => tables
+            .read()
+            .roll(REWARD_TABLE, &context, resources.rng.get_rng()),
+        Err(err) => {
+            tracing::warn!("No rewards for locked rooms: {}", err);
+            Vec::new()
+        }
+    };
+    plan.spawn(layout, &rewards, world, &mut resources.rng);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        sector::{FloorKind, Tile, WallKind},
+        util::{WorldRect, WorldSize},
+    };
+
+    use super::*;
+
+    /// A row of rooms joined by corridors, plus a side room off the second
+    fn facility() -> (Map, MapLayout) {
+        let mut map = Map::init(
+            "facility".into(),
+            WorldSize::new(40, 20),
+            Tile::Wall(WallKind::default()),
+        );
+        let size = WorldSize::new(4, 4);
+        let rooms = vec![
+            WorldRect::new(WorldPoint::new(1, 1), size),
+            WorldRect::new(WorldPoint::new(10, 1), size),
+            WorldRect::new(WorldPoint::new(20, 1), size),
+            WorldRect::new(WorldPoint::new(30, 1), size),
+            WorldRect::new(WorldPoint::new(10, 12), size),
+        ];
+        let mut layout = MapLayout::new(rooms.clone());
+
+        let floor = Tile::Floor(FloorKind::FloorDefault);
+        for room in rooms.iter() {
+            for point in room_tiles(&layout, layout.room_at(room.origin).unwrap()) {
+                map[&point] = floor;
+            }
+        }
+        let mut dig = |path: Vec<WorldPoint>| {
+            for point in path.iter() {
+                map[point] = floor;
+            }
+            layout.add_path(&path);
+        };
+        dig((4..=30).map(|x| WorldPoint::new(x, 2)).collect());
+        dig((4..=12).map(|y| WorldPoint::new(11, y)).collect());
+
+        (map, layout)
+    }
+
+    #[test]
+    fn keys_come_before_locks() {
+        let (map, layout) = facility();
+        let start = WorldPoint::new(2, 2);
+        let all = reachable(&map, start, &[]);
+
+        for seed in 0..20 {
+            let mut rng = RandomNumberGenerator::seeded(seed);
+            let plan = LockPlan::new(&map, &layout, start, MAX_LOCKS, &mut rng).unwrap();
+
+            assert_eq!(plan.reward_room, 3);
+            assert_eq!(plan.locks.last().unwrap().room, 3);
+            assert!(plan.locks.len() <= MAX_LOCKS);
+
+            // Keys are never behind their own lock
+            for lock in plan.locks.iter() {
+                assert!(!lock.doors.contains(&lock.key));
+                assert!(!room_tiles(&layout, lock.room).any(|point| point == lock.key));
+            }
+
+            // Everything can be opened in the end
+            assert_eq!(plan.solve(&map, start), all, "seed {}", seed);
+
+            // but not without the keys
+            let mut locked = plan.clone();
+            locked
+                .locks
+                .iter_mut()
+                .for_each(|lock| lock.key = WorldPoint::new(0, 0));
+            assert!(!locked
+                .solve(&map, start)
+                .contains(&layout.rooms[plan.reward_room].center()));
+        }
+    }
+}
@@ -7,7 +7,45 @@ pub use wfcgen::*;
 mod combo;
 pub use combo::*;
 
-use crate::{overworld::SectorInfo, resource::Resources, sector::Map};
+mod cellular;
+pub use cellular::*;
+
+mod drunkard;
+pub use drunkard::*;
+
+mod dla;
+pub use dla::*;
+
+mod tiledmap;
+pub use tiledmap::*;
+
+mod border;
+pub use border::*;
+
+mod connect;
+pub use connect::*;
+
+mod lock;
+pub use lock::*;
+
+mod populate;
+pub use populate::*;
+
+mod prefab;
+pub use prefab::*;
+
+mod registry;
+pub use registry::*;
+
+use crate::{
+    overworld::SectorInfo,
+    resource::Resources,
+    sector::{Map, MapLayout},
+    util::WorldSize,
+};
+
+/// Smallest map every generator can make, in tiles across
+pub const MIN_MAP_SIZE: i32 = 5;
 
 pub trait MapGenerator {
     fn generate(
@@ -16,4 +54,21 @@ pub trait MapGenerator {
         resources: &mut Resources,
         mapgen_history: &mut Vec<Map>,
     ) -> Map;
+
+    /// The rooms and corridors of the last generated map, for generators that
+    /// lay them out
+    fn layout(&self) -> Option<MapLayout> {
+        None
+    }
+
+    /// Make maps of a different size from now on. Generators that go by
+    /// `sector_info.size` don't need to do anything.
+    fn resize(&mut self, _size: WorldSize) {}
+
+    /// Whether the loader should connect regions, stamp prefabs and scatter
+    /// monsters and items over the generated map. Hand-crafted maps are used
+    /// as they are.
+    fn postprocess(&self) -> bool {
+        true
+    }
 }
@@ -0,0 +1,181 @@
+//! Scatters monsters and items over a sector by its [`SectorProbability`]
+use std::collections::HashSet;
+
+use bracket_random::prelude::RandomNumberGenerator;
+use hecs::World;
+
+use crate::{
+    component::{Item, Position},
+    data::{LootContext, LootTables, Monsters, SectorProbability},
+    overworld::SectorInfo,
+    resource::Resources,
+    sector::Map,
+    util::WorldPoint,
+};
+
+/// Loot table rolled for items lying around a sector
+pub const FLOOR_ITEM_TABLE: &str = "floor_items";
+
+/// What to scatter over a sector, and how much of it
+pub struct Population<'a> {
+    pub monsters: &'a Monsters,
+    pub loot_tables: &'a LootTables,
+    /// The rolled difficulty is the depth of every loot roll
+    pub context: LootContext,
+    pub monster_density: f32,
+    pub item_density: f32,
+}
+
+impl Population<'_> {
+    /// Give each open tile without anything on it a monster, or failing
+    /// that an item
+    pub fn scatter(&self, map: &Map, world: &mut World, rng: &mut RandomNumberGenerator) {
+        let occupied: HashSet<WorldPoint> = world
+            .query::<&Position>()
+            .iter()
+            .map(|(_, position)| position.grid_point())
+            .collect();
+        let open: Vec<WorldPoint> = map
+            .iter_tiles()
+            .filter(|(point, tile)| tile.is_passable() && !occupied.contains(point))
+            .map(|(point, _)| point)
+            .collect();
+
+        for point in open {
+            if rng.rand::<f32>() < self.monster_density {
+                self.monsters.spawn_random(point, world, rng);
+            } else if rng.rand::<f32>() < self.item_density {
+                for name in self
+                    .loot_tables
+                    .roll(FLOOR_ITEM_TABLE, &self.context, rng.get_rng())
+                {
+                    world.spawn((Position::new(point), Item { name }));
+                }
+            }
+        }
+    }
+}
+
+/// Roll the sector's difficulty and scatter monsters and items over it
+pub fn populate(map: &Map, sector_info: &SectorInfo, world: &mut World, resources: &mut Resources) {
+    let SectorProbability {
+        monster_density,
+        item_density,
+        difficulty,
+        ..
+    } = sector_info.probability.clone();
+    let difficulty = resources.rng.roll(difficulty).max(1);
+    tracing::info!(difficulty, "Populating sector");
+
+    let monsters = match resources.assets_cache.load::<Monsters>("data.monsters") {
+        Ok(monsters) => monsters.read().clone(),
+        Err(err) => {
+            tracing::warn!("No monsters: {}", err);
+            Monsters::default()
+        }
+    };
+    let loot_tables = match resources
+        .assets_cache
+        .load::<LootTables>("data.loot_tables")
+    {
+        Ok(tables) => tables.read().clone(),
+        Err(err) => {
+            tracing::warn!("No floor items: {}", err);
+            LootTables::default()
+        }
+    };
+
+    let population = Population {
+        monsters: &monsters,
+        loot_tables: &loot_tables,
+        context: LootContext {
+            element: sector_info.planet_info.element,
+            planet_type: sector_info.planet_info.planet_type,
+            depth: difficulty,
+        },
+        // Harder sectors are more crowded
+        monster_density: monster_density * difficulty as f32,
+        item_density,
+    };
+    population.scatter(map, world, &mut resources.rng);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        component::{Actor, Player},
+        data::{Element, PlanetType},
+        sector::{FloorKind, Tile, WallKind},
+        util::WorldSize,
+    };
+
+    use super::*;
+
+    const MONSTERS: &str = r#"
+- name: Kobold
+  glyph: "k"
+  fg: { r: 255, g: 255, b: 255, a: 255 }
+  bg: { r: 0, g: 0, b: 0, a: 255 }
+  view_range: 10
+  energy_capacity: 100
+  movement_cost: 20
+  behavior: !FollowNearest
+"#;
+
+    const LOOT: &str = r#"
+floor_items:
+  entries:
+    - weight: 1.0
+      entry: !Item ration
+"#;
+
+    #[test]
+    fn density_sets_how_much_spawns() {
+        let mut map = Map::init(
+            "room".into(),
+            WorldSize::new(20, 20),
+            Tile::Floor(FloorKind::FloorDefault),
+        );
+        map[&WorldPoint::new(3, 3)] = Tile::Wall(WallKind::default());
+        let monsters: Monsters = serde_yaml::from_str(MONSTERS).unwrap();
+        let loot_tables: LootTables = serde_yaml::from_str(LOOT).unwrap();
+
+        let count = |monster_density, item_density| {
+            let mut world = World::new();
+            let start = WorldPoint::new(0, 0);
+            world.spawn((Position::new(start), Player {}));
+            let population = Population {
+                monsters: &monsters,
+                loot_tables: &loot_tables,
+                context: LootContext {
+                    element: Element::Fire,
+                    planet_type: PlanetType::Barren,
+                    depth: 1,
+                },
+                monster_density,
+                item_density,
+            };
+            population.scatter(&map, &mut world, &mut RandomNumberGenerator::seeded(5));
+
+            // Nothing lands on the player or in walls
+            let spawned: Vec<WorldPoint> = world
+                .query::<&Position>()
+                .without::<&Player>()
+                .iter()
+                .map(|(_, position)| position.grid_point())
+                .collect();
+            assert!(!spawned.contains(&start));
+            assert!(spawned.iter().all(|point| map[point].is_passable()));
+
+            let monsters = world.query::<&Actor>().without::<&Player>().iter().count();
+            let items = world.query::<&Item>().iter().count();
+            (monsters, items)
+        };
+
+        assert_eq!(count(0., 0.), (0, 0));
+        assert_eq!(count(1., 0.), (398, 0));
+        assert_eq!(count(0., 1.), (0, 398));
+        let (monsters, items) = count(0.1, 0.1);
+        assert!(monsters > 0 && items > 0 && monsters + items < 100);
+    }
+}
@@ -0,0 +1,256 @@
+//! Stamps hand-authored [`Prefab`]s into generated maps
+use bracket_random::prelude::RandomNumberGenerator;
+use hecs::World;
+use rand::seq::SliceRandom;
+
+use crate::{
+    component::{Door, Item, Loot, Position},
+    data::{Placement, Prefab, PrefabGrid, PrefabSpawn, Prefabs},
+    overworld::SectorInfo,
+    resource::Resources,
+    sector::Map,
+    util::{WorldPoint, WorldRect, WorldVector},
+};
+
+/// Most placements checked for connectivity before giving up on a prefab
+const MAX_CONNECTIVITY_CHECKS: usize = 40;
+
+/// A prefab that fits somewhere in a map
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Stamp {
+    pub origin: WorldPoint,
+    pub grid: PrefabGrid,
+}
+
+impl Stamp {
+    pub fn rect(&self) -> WorldRect {
+        WorldRect::new(self.origin, self.grid.size)
+    }
+
+    fn cells(&self) -> impl Iterator<Item = (WorldPoint, char)> + '_ {
+        self.grid
+            .iter()
+            .map(|(offset, c)| (self.origin + offset, c))
+    }
+
+    /// Find a rotation and spot for a prefab that matches its placement,
+    /// stays off the map's edges and other stamps, and doesn't cut off any
+    /// part of the map
+    pub fn find(
+        map: &Map,
+        prefab: &Prefab,
+        occupied: &[WorldRect],
+        rng: &mut RandomNumberGenerator,
+    ) -> Option<Stamp> {
+        let mut candidates: Vec<Stamp> = prefab
+            .variants()
+            .into_iter()
+            .flat_map(|grid| {
+                let xs = 1..map.get_width() - grid.size.width;
+                let ys = 1..map.get_height() - grid.size.height;
+                ys.flat_map(move |y| xs.clone().map(move |x| WorldPoint::new(x, y)))
+                    .map(move |origin| Stamp {
+                        origin,
+                        grid: grid.clone(),
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .filter(|stamp| {
+                !occupied
+                    .iter()
+                    .any(|rect| rect.intersects(&stamp.rect().inflate(1, 1)))
+                    && stamp.fits(map, prefab)
+            })
+            .collect();
+        candidates.shuffle(rng.get_rng());
+
+        let regions = map.regions().len();
+        candidates
+            .into_iter()
+            .take(MAX_CONNECTIVITY_CHECKS)
+            .find(|stamp| {
+                let mut stamped = map.clone();
+                stamp.apply(&mut stamped, prefab);
+                stamped.regions().len() <= regions
+            })
+    }
+
+    /// Whether the footprint is all floor or all wall (see [`Placement`]).
+    /// Rooms dug into walls also have to open onto floor.
+    fn fits(&self, map: &Map, prefab: &Prefab) -> bool {
+        match prefab.placement {
+            Placement::Floor => self.cells().all(|(point, _)| map[&point].is_passable()),
+            Placement::Wall => {
+                let rect = self.rect();
+                let footprint: Vec<WorldPoint> = self.cells().map(|(point, _)| point).collect();
+                footprint.iter().all(|point| !map[point].is_passable())
+                    && self.cells().any(|(point, c)| {
+                        prefab.tile(c).is_some_and(|tile| tile.is_passable())
+                            && [(0, 1), (0, -1), (1, 0), (-1, 0)].iter().any(|(dx, dy)| {
+                                let next = point + WorldVector::new(*dx, *dy);
+                                (!rect.contains(next) || !footprint.contains(&next))
+                                    && map.get(next).is_some_and(|tile| tile.is_passable())
+                            })
+                    })
+            }
+        }
+    }
+
+    /// Overwrite the map's tiles with the prefab's
+    pub fn apply(&self, map: &mut Map, prefab: &Prefab) {
+        let mut changed = Vec::new();
+        for (point, c) in self.cells() {
+            if let Some(tile) = prefab.tile(c) {
+                map[&point] = tile;
+                changed.push(point);
+            }
+        }
+        map.reshape_walls(&changed);
+    }
+
+    /// Spawn the prefab's legend entities
+    pub fn spawn(&self, prefab: &Prefab, world: &mut World) {
+        for (point, c) in self.cells() {
+            let position = Position::new(point);
+            match prefab.spawn(c) {
+                Some(PrefabSpawn::Item(name)) => {
+                    world.spawn((position, Item { name: name.clone() }));
+                }
+                Some(PrefabSpawn::Container(table)) => {
+                    world.spawn((
+                        position,
+                        Loot {
+                            table: table.clone(),
+                        },
+                    ));
+                }
+                Some(PrefabSpawn::Door) => {
+                    world.spawn((position, Door { opened: false }));
+                }
+                None => {}
+            }
+        }
+    }
+}
+
+/// Pick prefabs for a sector by its SectorType and stamp as many as fit
+pub fn place_prefabs(
+    map: &mut Map,
+    sector_info: &SectorInfo,
+    world: &mut World,
+    resources: &mut Resources,
+) {
+    let prefabs = match resources.assets_cache.load::<Prefabs>("data.prefabs") {
+        Ok(prefabs) => prefabs.read(),
+        Err(err) => {
+            tracing::warn!("No prefabs: {}", err);
+            return;
+        }
+    };
+
+    let mut occupied = Vec::new();
+    for _ in 0..prefabs.per_sector {
+        let Some(name) = prefabs.choose(sector_info.sector_type, resources.rng.get_rng()) else {
+            return;
+        };
+        let prefab = prefabs.get(name).expect("chosen prefab exists");
+
+        match Stamp::find(map, prefab, &occupied, &mut resources.rng) {
+            Some(stamp) => {
+                tracing::info!(prefab = name, origin = ?stamp.origin, "Stamping prefab");
+                stamp.apply(map, prefab);
+                stamp.spawn(prefab, world);
+                occupied.push(stamp.rect());
+            }
+            None => tracing::debug!(prefab = name, "Prefab doesn't fit"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use crate::{
+        data::{LegendEntry, SectorType},
+        sector::{FloorKind, Tile, WallKind},
+        util::WorldSize,
+    };
+
+    use super::*;
+
+    fn prefab(placement: Placement, grid: &str) -> Prefab {
+        Prefab {
+            grid: grid.to_owned(),
+            legend: BTreeMap::from([(
+                'c',
+                LegendEntry {
+                    tile: '_',
+                    spawn: PrefabSpawn::Container("container".to_owned()),
+                },
+            )]),
+            placement,
+            weight: BTreeMap::from([(SectorType::Barren, 1.0)]),
+            rotate: true,
+            mirror: true,
+        }
+    }
+
+    /// Open ground on the left, solid rock on the right
+    fn half_cave() -> Map {
+        let mut map = Map::init(
+            "test".into(),
+            WorldSize::new(30, 20),
+            Tile::Wall(WallKind::default()),
+        );
+        for x in 1..15 {
+            for y in 1..19 {
+                map[&WorldPoint::new(x, y)] = Tile::Floor(FloorKind::FloorDefault);
+            }
+        }
+        map
+    }
+
+    #[test]
+    fn stamps_into_floor_and_wall() {
+        let mut map = half_cave();
+        let mut rng = RandomNumberGenerator::seeded(2);
+        let mut world = World::new();
+
+        let wreck = prefab(Placement::Floor, "###\n#c.\n###");
+        let stamp = Stamp::find(&map, &wreck, &[], &mut rng).unwrap();
+        assert!(stamp.rect().max_x() <= 15);
+        stamp.apply(&mut map, &wreck);
+        stamp.spawn(&wreck, &mut world);
+        assert_eq!(world.query::<&Loot>().iter().count(), 1);
+
+        let room = prefab(Placement::Wall, "####\n#c_.\n####");
+        let placed = Stamp::find(&map, &room, &[stamp.rect()], &mut rng).unwrap();
+        assert!(!placed.rect().intersects(&stamp.rect()));
+        assert!(placed.rect().max_x() > 15);
+        placed.apply(&mut map, &room);
+
+        // Everything is still one connected area
+        assert_eq!(map.regions().len(), 1);
+    }
+
+    #[test]
+    fn keeps_connectivity() {
+        // A wall across a one tile wide corridor would cut it in two
+        let mut map = Map::init(
+            "test".into(),
+            WorldSize::new(12, 5),
+            Tile::Wall(WallKind::default()),
+        );
+        for x in 1..11 {
+            map[&WorldPoint::new(x, 2)] = Tile::Floor(FloorKind::FloorDefault);
+        }
+        let plug = prefab(Placement::Floor, "#");
+        let mut rng = RandomNumberGenerator::seeded(1);
+        let stamp = Stamp::find(&map, &plug, &[], &mut rng).unwrap();
+        assert!(stamp.origin.x == 1 || stamp.origin.x == 10);
+
+        let blocker = prefab(Placement::Floor, ".#.");
+        assert_eq!(Stamp::find(&map, &blocker, &[], &mut rng), None);
+    }
+}
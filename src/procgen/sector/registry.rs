@@ -0,0 +1,403 @@
+//! Picks and parametrises a sector's MapGenerator from its SectorInfo
+//!
+//! Generators are defined by name in `data/sector_recipes.yaml`. Rules in the
+//! same file choose between them based on the overworld tile, SectorType,
+//! Element and PlanetType, falling back to the SectorProbability's generators.
+use std::{borrow::Cow, collections::BTreeMap};
+
+use assets_manager::{asset::load_yaml, AssetCache, BoxedError, FileAsset};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    data::{Element, PlanetType, ProbabilityDistribution, SectorType},
+    overworld::{Overworld, OverworldTile, SectorInfo},
+    procgen::{Layer, LocationSeed, Spawner},
+    resource::Resources,
+    sector::{FloorKind, Tile},
+    util::{WorldPoint, WorldSize},
+};
+
+use super::{
+    seed, Blend, Bsp, CaveParams, CellularCave, Combo, Dla, DlaParams, DrunkardParams,
+    DrunkardsWalk, MapGenerator, MapTemplate, Mask, SubMap, SubMapPlacement, TiledMap, WfcGen,
+};
+
+/// Nested Combo recipes deeper than this are assumed to be a cycle
+const MAX_RECIPE_DEPTH: usize = 8;
+
+/// Anything that can both generate a sector's map and populate it
+pub trait SectorGenerator: MapGenerator + Spawner {}
+impl<T: MapGenerator + Spawner> SectorGenerator for T {}
+
+impl MapGenerator for Box<dyn SectorGenerator> {
+    fn generate(
+        &mut self,
+        sector_info: &SectorInfo,
+        resources: &mut Resources,
+        mapgen_history: &mut Vec<crate::sector::Map>,
+    ) -> crate::sector::Map {
+        (**self).generate(sector_info, resources, mapgen_history)
+    }
+
+    fn layout(&self) -> Option<crate::sector::MapLayout> {
+        (**self).layout()
+    }
+
+    fn resize(&mut self, size: WorldSize) {
+        (**self).resize(size)
+    }
+
+    fn postprocess(&self) -> bool {
+        (**self).postprocess()
+    }
+}
+
+impl Spawner for Box<dyn SectorGenerator> {
+    fn spawn(&self, map: &crate::sector::Map, world: &mut hecs::World, resources: &mut Resources) {
+        (**self).spawn(map, world, resources)
+    }
+}
+
+/// How to build a named generator
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum GeneratorRecipe {
+    Bsp,
+    /// Cellular automaton cave, shaped by the planet
+    Cave(CaveParams),
+    /// Tunnels dug by random walkers
+    DrunkardsWalk(DrunkardParams),
+    /// Branching tubes grown by diffusion-limited aggregation
+    Dla(DlaParams),
+    /// A hand-crafted map from `assets/maps` (see [`TiledMap`])
+    Tiled {
+        map: String,
+    },
+    /// Wave function collapse from a named seed (see [`seed::load`])
+    Wfc {
+        seed: String,
+    },
+    /// Composite other recipes on top of each other, in order
+    Combo {
+        /// Character for the tile under everything (see [`Tile::from_char`])
+        default_tile: char,
+        submaps: Vec<SubMapRecipe>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SubMapRecipe {
+    pub generator: String,
+    /// Defaults to the whole sector
+    #[serde(default)]
+    pub size: Option<WorldSize>,
+    /// Top-left corner in the sector. Defaults to the origin.
+    #[serde(default)]
+    pub dest: Option<WorldPoint>,
+    /// Where to put it instead of `dest`
+    #[serde(default)]
+    pub placement: Option<SubMapPlacement>,
+    #[serde(default)]
+    pub blend: Blend,
+    #[serde(default)]
+    pub mask: Mask,
+    /// Width of the mask's soft edge in tiles
+    #[serde(default)]
+    pub feather: i32,
+}
+
+/// Restricts when a rule applies. Empty lists match everything.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RecipeCondition {
+    #[serde(default)]
+    pub tile: Vec<OverworldTile>,
+    #[serde(default)]
+    pub sector_type: Vec<SectorType>,
+    #[serde(default)]
+    pub element: Vec<Element>,
+    #[serde(default)]
+    pub planet_type: Vec<PlanetType>,
+}
+
+impl RecipeCondition {
+    pub fn matches(&self, sector_info: &SectorInfo) -> bool {
+        fn any_of<T: PartialEq>(items: &[T], item: &T) -> bool {
+            items.is_empty() || items.contains(item)
+        }
+
+        any_of(&self.tile, &sector_info.tile)
+            && any_of(&self.sector_type, &sector_info.sector_type)
+            && any_of(&self.element, &sector_info.planet_info.element)
+            && any_of(&self.planet_type, &sector_info.planet_info.planet_type)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecipeRule {
+    #[serde(default)]
+    pub when: RecipeCondition,
+    pub generator: ProbabilityDistribution<String>,
+}
+
+/// Named generator recipes and the rules for picking them
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SectorRecipes {
+    generators: BTreeMap<String, GeneratorRecipe>,
+    /// Checked in order; the first matching rule wins
+    #[serde(default)]
+    rules: Vec<RecipeRule>,
+    /// Generator for the sector the player first lands on
+    #[serde(default)]
+    landing_site: Option<String>,
+}
+
+impl FileAsset for SectorRecipes {
+    const EXTENSION: &'static str = "yaml";
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Result<Self, BoxedError> {
+        load_yaml(&bytes)
+    }
+}
+
+impl SectorRecipes {
+    pub fn get(&self, name: &str) -> Option<&GeneratorRecipe> {
+        self.generators.get(name)
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &String> {
+        self.generators.keys()
+    }
+
+    /// The generator names (and weights) to pick from for a sector
+    pub fn candidates<'a>(
+        &'a self,
+        sector_info: &'a SectorInfo,
+    ) -> &'a ProbabilityDistribution<String> {
+        self.rules
+            .iter()
+            .find(|rule| rule.when.matches(sector_info))
+            .map(|rule| &rule.generator)
+            .unwrap_or(&sector_info.probability.generator)
+    }
+
+    /// Pick a generator name for a sector, unless it's pinned to one
+    pub fn choose<R: Rng>(&self, sector_info: &SectorInfo, rng: &mut R) -> String {
+        match &sector_info.generator {
+            Some(name) => name.clone(),
+            None => self.candidates(sector_info).next_element(rng),
+        }
+    }
+
+    pub fn landing_site(&self) -> Option<&str> {
+        self.landing_site.as_deref()
+    }
+
+    /// Build a named generator for a map of the given size, loading any
+    /// WFC seeds it needs from `cache`
+    pub fn build(
+        &self,
+        name: &str,
+        size: WorldSize,
+        cache: &AssetCache,
+    ) -> anyhow::Result<Box<dyn SectorGenerator>> {
+        self.build_nested(name, size, cache, 0)
+    }
+
+    fn build_nested(
+        &self,
+        name: &str,
+        size: WorldSize,
+        cache: &AssetCache,
+        depth: usize,
+    ) -> anyhow::Result<Box<dyn SectorGenerator>> {
+        if depth > MAX_RECIPE_DEPTH {
+            anyhow::bail!(
+                "Generator recipe {:?} is nested too deeply; is there a cycle?",
+                name
+            );
+        }
+
+        let recipe = self
+            .get(name)
+            .ok_or_else(|| anyhow::anyhow!("Unknown generator recipe {:?}", name))?;
+
+        Ok(match recipe {
+            GeneratorRecipe::Bsp => Box::new(Bsp::new(size)),
+            GeneratorRecipe::Cave(params) => Box::new(CellularCave::new(size, *params)),
+            GeneratorRecipe::DrunkardsWalk(params) => Box::new(DrunkardsWalk::new(size, *params)),
+            GeneratorRecipe::Dla(params) => Box::new(Dla::new(size, *params)),
+            GeneratorRecipe::Tiled { map } => Box::new(
+                TiledMap::load(cache, map)
+                    .map_err(|err| anyhow::anyhow!("Tiled map {:?}: {}", map, err))?,
+            ),
+            GeneratorRecipe::Wfc { seed: seed_name } => {
+                let seed = seed::load(cache, seed_name)
+                    .map_err(|err| anyhow::anyhow!("WFC seed {:?}: {}", seed_name, err))?;
+                Box::new(WfcGen::new(seed))
+            }
+            GeneratorRecipe::Combo {
+                default_tile,
+                submaps,
+            } => {
+                let default_tile =
+                    Tile::from_char(*default_tile).unwrap_or(Tile::Floor(FloorKind::FloorDefault));
+                let submaps = submaps
+                    .iter()
+                    .map(|submap| {
+                        let submap_size = submap.size.unwrap_or(size);
+                        let mapgen =
+                            self.build_nested(&submap.generator, submap_size, cache, depth + 1)?;
+                        let mut gen =
+                            SubMap::new(mapgen, submap_size, submap.dest.unwrap_or_default())
+                                .with_blend(submap.blend)
+                                .with_mask(submap.mask)
+                                .with_feather(submap.feather);
+                        if let Some(placement) = submap.placement {
+                            gen = gen.with_placement(placement);
+                        }
+                        Ok(gen)
+                    })
+                    .collect::<anyhow::Result<Vec<_>>>()?;
+
+                Box::new(Combo::new(MapTemplate::new(size, default_tile, submaps)))
+            }
+        })
+    }
+}
+
+/// Pin the sector the player lands on to the landing site generator, if
+/// there is one
+pub fn pin_landing_site(planet: &mut Overworld, resources: &Resources) {
+    let recipes = resources
+        .load_asset::<SectorRecipes>("data.sector_recipes")
+        .read();
+    let Some(name) = recipes.landing_site() else {
+        return;
+    };
+
+    let center = planet.center();
+    if let Some(sector_info) = planet.get_sector_info_mut(&center) {
+        tracing::info!(generator = name, point = ?center, "Pinned landing site");
+        sector_info.generator = Some(name.to_owned());
+    }
+}
+
+/// Pick and build the generator for a sector. The choice only depends on the
+/// sector's location seed.
+pub fn sector_generator(
+    sector_info: &SectorInfo,
+    seed: LocationSeed,
+    resources: &Resources,
+) -> Box<dyn SectorGenerator> {
+    let recipes = resources
+        .load_asset::<SectorRecipes>("data.sector_recipes")
+        .read();
+
+    let mut rng = seed.rng(Layer::Recipe);
+    let name = recipes.choose(sector_info, rng.get_rng());
+    tracing::info!(generator = name, tile = ?sector_info.tile, "Picked sector generator");
+
+    recipes
+        .build(&name, sector_info.size, &resources.assets_cache)
+        .unwrap_or_else(|err| {
+            tracing::error!("{}; falling back to bsp", err);
+            Box::new(Bsp::new(sector_info.size))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use std::fs;
+
+    use crate::{
+        game::consts::SECTOR_SIZE,
+        resource::{test_asset_cache, test_asset_roots, MOD_MANIFEST},
+    };
+
+    use super::*;
+
+    #[test]
+    fn every_recipe_builds() {
+        let cache = test_asset_cache();
+        let recipes = cache
+            .load::<SectorRecipes>("data.sector_recipes")
+            .unwrap()
+            .read();
+        for name in recipes.names() {
+            assert!(recipes.build(name, SECTOR_SIZE, &cache).is_ok(), "{}", name);
+        }
+
+        // Every generator that could be picked for any sector has a recipe
+        let mut rng = StdRng::seed_from_u64(3);
+        for sector_type in [
+            SectorType::Barren,
+            SectorType::Civilization,
+            SectorType::ImpassibleTerrain,
+            SectorType::ImpassibleLiquid,
+            SectorType::PassibleLiquid,
+        ] {
+            let sector_info = SectorInfo::standalone(
+                PlanetType::Lush,
+                Element::Water,
+                sector_type,
+                SECTOR_SIZE,
+                &cache,
+            )
+            .unwrap();
+            for _ in 0..20 {
+                let name = recipes.choose(&sector_info, &mut rng);
+                assert!(recipes.get(&name).is_some(), "{}", name);
+            }
+
+            // Pinned sectors always get their generator
+            let mut pinned = sector_info.clone();
+            pinned.generator = recipes.landing_site().map(str::to_owned);
+            assert!(recipes.get(pinned.generator.as_ref().unwrap()).is_some());
+            assert_eq!(Some(recipes.choose(&pinned, &mut rng)), pinned.generator);
+        }
+    }
+
+    #[test]
+    fn mod_rules_override_base_rules() {
+        let tmp = std::env::temp_dir().join(format!("blastoff-recipes-{}", std::process::id()));
+        fs::create_dir_all(tmp.join("data")).unwrap();
+        fs::write(
+            tmp.join(MOD_MANIFEST),
+            "name: cave cities\nversion: 0.1.0\nload_order: 10\n",
+        )
+        .unwrap();
+        fs::write(
+            tmp.join("data/sector_recipes.yaml"),
+            "rules:\n  - when:\n      tile: [City]\n    generator:\n      items:\n        cave: 1.0\n",
+        )
+        .unwrap();
+
+        let cache = test_asset_roots()
+            .with_mod(tmp.clone())
+            .unwrap()
+            .asset_cache()
+            .unwrap();
+        let recipes = cache
+            .load::<SectorRecipes>("data.sector_recipes")
+            .unwrap()
+            .read();
+        let sector_info = SectorInfo::standalone(
+            PlanetType::Lush,
+            Element::Water,
+            SectorType::Civilization,
+            SECTOR_SIZE,
+            &cache,
+        )
+        .unwrap();
+        let mut rng = StdRng::seed_from_u64(5);
+        for _ in 0..10 {
+            assert_eq!(recipes.choose(&sector_info, &mut rng), "cave");
+        }
+        // The base game's rules still apply after the mod's
+        assert!(recipes.rules.len() > 1);
+
+        fs::remove_dir_all(tmp).unwrap();
+    }
+}
@@ -0,0 +1,356 @@
+//! Hand-crafted sectors drawn in [Tiled](https://www.mapeditor.org/).
+//!
+//! Maps live in `assets/maps` and use `tileset/tileset_transparent.tsx`. Tile
+//! layers are stacked in order and imported through `maps/tiles.yaml`; object
+//! layers hold spawns, picked by each object's class:
+//!
+//! - `player`: where the player starts
+//! - `monster`: an enemy, with optional `behavior` (see [`InitialBehavior`])
+//!   and `loot` table properties
+//! - `door`: a closed door, locked if it has a `key` int property
+use std::{borrow::Cow, collections::BTreeMap, path::Path};
+
+use assets_manager::{
+    asset::load_yaml,
+    source::{DirEntry, Source},
+    AssetCache, BoxedError, FileAsset,
+};
+use serde::{Deserialize, Serialize};
+use tiled::{LayerType, Loader, ObjectShape, PropertyValue, TileLayer};
+
+use crate::{
+    component::{
+        Actor, ActorKind, Behavior, BehaviorKind, BlocksTile, Door, InitialBehavior, Locked, Loot,
+        Position, Viewshed,
+    },
+    overworld::SectorInfo,
+    procgen::Spawner,
+    resource::{read_asset_path, Resources},
+    sector::{Map, Tile},
+    util::{WorldPoint, WorldSize, WorldVector},
+};
+
+use super::{spawn_player, spawn_point, MapGenerator};
+
+/// Directory the maps are loaded from
+pub const MAPS_DIR: &str = "maps";
+
+/// How tileset tiles import as map tiles, loaded from `maps/tiles.yaml`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TiledTiles {
+    /// Character for the sector around maps smaller than it. Maps can set
+    /// their own with a `fill` property.
+    pub fill: char,
+    pub tiles: BTreeMap<u32, char>,
+}
+
+impl FileAsset for TiledTiles {
+    const EXTENSION: &'static str = "yaml";
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Result<Self, BoxedError> {
+        load_yaml(&bytes)
+    }
+}
+
+/// Something placed on an object layer
+#[derive(Debug, Clone, PartialEq)]
+pub enum TiledSpawn {
+    Player,
+    Monster {
+        behavior: InitialBehavior,
+        loot: Option<String>,
+    },
+    Door {
+        key: Option<u32>,
+    },
+}
+
+/// A map imported from a `.tmx` file, in the map's own coordinates
+#[derive(Debug, Clone)]
+pub struct TiledMap {
+    map: Map,
+    fill: Tile,
+    spawns: Vec<(WorldPoint, TiledSpawn)>,
+    /// Where the map ended up in the last generated sector
+    offset: WorldVector,
+}
+
+impl TiledMap {
+    /// Load `maps/<name>.tmx` and any tilesets it uses through the asset cache
+    pub fn load(cache: &AssetCache, name: &str) -> anyhow::Result<Self> {
+        let tiles = cache.load::<TiledTiles>("maps.tiles")?.read();
+        let mut loader = Loader::with_reader(|path: &Path| read_asset_path(cache, path));
+        let tmx = loader.load_tmx_map(format!("{}/{}.tmx", MAPS_DIR, name))?;
+        Self::from_tmx(name, &tmx, &tiles)
+    }
+
+    pub fn from_tmx(name: &str, tmx: &tiled::Map, tiles: &TiledTiles) -> anyhow::Result<Self> {
+        let size = WorldSize::new(tmx.width as i32, tmx.height as i32);
+        let fill = match tmx.properties.get("fill") {
+            Some(PropertyValue::StringValue(fill)) => fill.chars().next(),
+            _ => None,
+        }
+        .unwrap_or(tiles.fill);
+        let fill =
+            Tile::from_char(fill).ok_or_else(|| anyhow::anyhow!("{:?} isn't a tile", fill))?;
+
+        let mut map = Map::init(name.to_owned(), size, fill);
+        let mut spawns = Vec::new();
+        for layer in tmx.layers().filter(|layer| layer.visible) {
+            match layer.layer_type() {
+                LayerType::Tiles(layer) => import_tiles(&mut map, &layer, tiles)?,
+                LayerType::Objects(layer) => {
+                    for object in layer.objects() {
+                        let point = object_point(tmx, &object);
+                        if !map.contains(point) {
+                            anyhow::bail!("{:?} is outside the map at {:?}", object.name, point);
+                        }
+                        if let Some(spawn) = object_spawn(&object)? {
+                            spawns.push((point, spawn));
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(Self {
+            map,
+            fill,
+            spawns,
+            offset: WorldVector::zero(),
+        })
+    }
+
+    pub fn map(&self) -> &Map {
+        &self.map
+    }
+
+    pub fn spawns(&self) -> &[(WorldPoint, TiledSpawn)] {
+        &self.spawns
+    }
+
+    /// Center the map in a sector of `size`, cropping it if it's larger.
+    /// Returns the map and how far it moved.
+    pub fn fit(&self, size: WorldSize) -> (Map, WorldVector) {
+        let mut sector = Map::init(self.map.get_name().to_owned(), size, self.fill);
+        let offset = (size.to_vector() - self.map.get_rect().size.to_vector()) / 2;
+        for (point, tile) in self.map.iter_tiles() {
+            let point = point + offset;
+            if sector.contains(point) {
+                sector[&point] = *tile;
+            }
+        }
+        (sector, offset)
+    }
+}
+
+impl MapGenerator for TiledMap {
+    fn generate(
+        &mut self,
+        sector_info: &SectorInfo,
+        _resources: &mut Resources,
+        mapgen_history: &mut Vec<Map>,
+    ) -> Map {
+        let (map, offset) = self.fit(sector_info.size);
+        self.offset = offset;
+        mapgen_history.push(map.clone());
+        map
+    }
+
+    fn postprocess(&self) -> bool {
+        false
+    }
+}
+
+impl Spawner for TiledMap {
+    fn spawn(&self, map: &Map, world: &mut hecs::World, _resources: &mut Resources) {
+        let spawns: Vec<(WorldPoint, &TiledSpawn)> = self
+            .spawns
+            .iter()
+            .map(|(point, spawn)| (*point + self.offset, spawn))
+            .filter(|(point, _)| map.contains(*point))
+            .collect();
+
+        // Fall back to the biggest open area if the map has no player start
+        let start = spawns
+            .iter()
+            .find(|(_, spawn)| *spawn == &TiledSpawn::Player)
+            .map(|(point, _)| *point)
+            .or_else(|| spawn_point(map));
+        if let Some(start) = start {
+            spawn_player(world, start, map.get_rect().center());
+        }
+
+        for (point, spawn) in spawns {
+            let position = Position::new(point);
+            match spawn {
+                TiledSpawn::Player => {}
+                TiledSpawn::Monster { behavior, loot } => {
+                    let mut viewshed = Viewshed::default().with_range(10);
+                    viewshed.set_dirty();
+                    let monster = world.spawn((
+                        position,
+                        viewshed,
+                        Actor::new(0, 100, 100, 25, 0, ActorKind::Computer(None)),
+                        Behavior::new(BehaviorKind::Initial(behavior.clone())),
+                    ));
+                    if let Some(table) = loot {
+                        world
+                            .insert_one(
+                                monster,
+                                Loot {
+                                    table: table.clone(),
+                                },
+                            )
+                            .unwrap();
+                    }
+                }
+                TiledSpawn::Door { key } => {
+                    let door = world.spawn((position, Door { opened: false }));
+                    if let Some(key) = key {
+                        world.insert(door, (Locked(*key), BlocksTile {})).unwrap();
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Every map name in `assets/maps`, sorted
+pub fn map_names(cache: &AssetCache) -> Vec<String> {
+    let mut names = Vec::new();
+    let _ = cache.source().read_dir(MAPS_DIR, &mut |entry| {
+        if let DirEntry::File(id, "tmx") = entry {
+            names.push(
+                id.trim_start_matches(MAPS_DIR)
+                    .trim_start_matches('.')
+                    .to_owned(),
+            );
+        }
+    });
+    names.sort();
+    names
+}
+
+fn import_tiles(map: &mut Map, layer: &TileLayer, tiles: &TiledTiles) -> anyhow::Result<()> {
+    for point in map.iter_points().collect::<Vec<_>>() {
+        let Some(layer_tile) = layer.get_tile(point.x, point.y) else {
+            continue;
+        };
+        let glyph = match layer_tile
+            .get_tile()
+            .and_then(|tile| tile.properties.get("glyph").cloned())
+        {
+            Some(PropertyValue::StringValue(glyph)) => glyph.chars().next(),
+            _ => tiles.tiles.get(&layer_tile.id()).copied(),
+        };
+        let tile = glyph.and_then(Tile::from_char).ok_or_else(|| {
+            anyhow::anyhow!(
+                "Tileset tile {} at {:?} has no glyph in maps/tiles.yaml",
+                layer_tile.id(),
+                point
+            )
+        })?;
+        map[&point] = tile;
+    }
+    Ok(())
+}
+
+/// The tile an object sits on. Tile objects are anchored at their bottom
+/// left corner, shapes at their top left.
+fn object_point(tmx: &tiled::Map, object: &tiled::Object) -> WorldPoint {
+    let (width, height) = match object.shape {
+        ObjectShape::Rect { width, height } | ObjectShape::Ellipse { width, height } => {
+            (width, height)
+        }
+        _ => (0., 0.),
+    };
+    let top = if object.tile_data().is_some() {
+        object.y - height
+    } else {
+        object.y
+    };
+    let x = (object.x + width / 2.) / tmx.tile_width as f32;
+    let y = (top + height / 2.) / tmx.tile_height as f32;
+    WorldPoint::new(x.floor() as i32, y.floor() as i32)
+}
+
+fn object_spawn(object: &tiled::Object) -> anyhow::Result<Option<TiledSpawn>> {
+    let string = |name: &str| match object.properties.get(name) {
+        Some(PropertyValue::StringValue(value)) => Some(value.clone()),
+        _ => None,
+    };
+
+    Ok(match object.user_type.as_str() {
+        "player" => Some(TiledSpawn::Player),
+        "monster" => {
+            let behavior = match string("behavior") {
+                Some(behavior) => serde_yaml::from_str(&behavior).map_err(|err| {
+                    anyhow::anyhow!("{:?} has an unknown behavior: {}", object.name, err)
+                })?,
+                None => InitialBehavior::FollowNearest,
+            };
+            Some(TiledSpawn::Monster {
+                behavior,
+                loot: string("loot"),
+            })
+        }
+        "door" => {
+            let key = match object.properties.get("key") {
+                Some(PropertyValue::IntValue(key)) => Some(*key as u32),
+                _ => None,
+            };
+            Some(TiledSpawn::Door { key })
+        }
+        "" => None,
+        other => {
+            tracing::warn!("Ignoring {:?} with unknown class {:?}", object.name, other);
+            None
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{game::consts::SECTOR_SIZE, resource::test_asset_cache};
+
+    use super::*;
+
+    #[test]
+    fn imports_landing_site() {
+        let cache = test_asset_cache();
+        assert!(map_names(&cache).contains(&"landing_site".to_owned()));
+
+        let tiled = TiledMap::load(&cache, "landing_site").unwrap();
+        assert_eq!(tiled.map().get_rect().size, WorldSize::new(40, 30));
+        assert!(tiled
+            .map()
+            .iter_tiles()
+            .any(|(_, tile)| !tile.is_passable()));
+
+        let (start, _) = tiled
+            .spawns()
+            .iter()
+            .find(|(_, spawn)| *spawn == TiledSpawn::Player)
+            .unwrap();
+        assert!(tiled.map()[start].is_passable());
+        assert!(tiled
+            .spawns()
+            .iter()
+            .any(|(_, spawn)| matches!(spawn, TiledSpawn::Door { key: None })));
+        assert!(tiled.spawns().iter().any(|(_, spawn)| matches!(
+            spawn,
+            TiledSpawn::Monster {
+                behavior: InitialBehavior::Wander,
+                ..
+            }
+        )));
+
+        // Centered in a full sized sector
+        let (map, offset) = tiled.fit(SECTOR_SIZE);
+        assert_eq!(map.get_rect().size, SECTOR_SIZE);
+        assert_eq!(offset, WorldVector::new(30, 35));
+        assert_eq!(map[&(*start + offset)], tiled.map()[start]);
+    }
+}
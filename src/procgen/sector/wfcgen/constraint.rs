@@ -0,0 +1,126 @@
+//! Extra rules a [`super::seed::WfcSeed`] can put on its output
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    sector::{Map, Tile},
+    util::{WorldPoint, WorldSize},
+};
+
+/// Pins a cell of the output to a tile, eg. a landing pad or an exit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Anchor {
+    /// Negative values count back from the right edge
+    pub x: i32,
+    /// Negative values count back from the bottom edge
+    pub y: i32,
+    /// Measure from the middle of the output instead of its edges
+    #[serde(default)]
+    pub centered: bool,
+    pub tile: char,
+}
+
+impl Anchor {
+    /// Where the anchor lands in an output of the given size
+    pub fn point(&self, size: WorldSize) -> WorldPoint {
+        if self.centered {
+            return WorldPoint::new(size.width / 2 + self.x, size.height / 2 + self.y);
+        }
+
+        let wrap = |value: i32, length: i32| {
+            if value < 0 {
+                length + value
+            } else {
+                value
+            }
+        };
+        WorldPoint::new(wrap(self.x, size.width), wrap(self.y, size.height))
+    }
+
+    pub fn tile(&self) -> Option<Tile> {
+        Tile::from_char(self.tile)
+    }
+}
+
+/// Bounds how many of a tile the output may contain, as shares of its area
+/// so that the same seed works for sectors and small submaps alike
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TileCount {
+    pub tile: char,
+    #[serde(default)]
+    pub min: f32,
+    #[serde(default)]
+    pub max: Option<f32>,
+}
+
+impl TileCount {
+    /// The fewest and most tiles allowed in an output of `area` tiles. Both
+    /// round towards allowing more, so outputs too small for even one tile
+    /// of the minimum don't need any.
+    pub fn bounds(&self, area: usize) -> (usize, Option<usize>) {
+        let area = area as f32;
+        let min = (self.min * area).floor() as usize;
+        let max = self.max.map(|max| (max * area).ceil() as usize);
+        (min, max)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct WfcConstraints {
+    /// Force the edges to the patterns in the seed's corners (see
+    /// [`super::ForceBorderForbid`]). Edges next to generated sectors are
+    /// left to line up with them instead.
+    #[serde(default)]
+    pub force_border: bool,
+    #[serde(default)]
+    pub anchors: Vec<Anchor>,
+    #[serde(default)]
+    pub tile_counts: Vec<TileCount>,
+}
+
+impl WfcConstraints {
+    /// Check the rules that can only be checked once the output is complete
+    pub fn check(&self, map: &Map) -> Result<(), TileCountError> {
+        let area = map.get_rect().area() as usize;
+        for tile_count in self.tile_counts.iter() {
+            let count = map
+                .iter_tiles()
+                .filter(|(_, tile)| tile.glyph() == tile_count.tile)
+                .count();
+            let (min, max) = tile_count.bounds(area);
+            if count < min || max.is_some_and(|max| count > max) {
+                return Err(TileCountError {
+                    tile: tile_count.tile,
+                    count,
+                    min,
+                    max,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The output had too few or too many of a tile
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TileCountError {
+    pub tile: char,
+    pub count: usize,
+    pub min: usize,
+    pub max: Option<usize>,
+}
+
+impl fmt::Display for TileCountError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {:?} tiles, expected at least {}",
+            self.count, self.tile, self.min
+        )?;
+        if let Some(max) = self.max {
+            write!(f, " and at most {}", max)?;
+        }
+        Ok(())
+    }
+}
@@ -3,65 +3,275 @@ use std::collections::HashSet;
 use coord_2d::Coord;
 use rand::Rng;
 
-use wfc::{ForbidInterface, ForbidPattern, PatternId, Wrap};
+use wfc::{ForbidInterface, ForbidPattern, PatternId, Size, Wrap};
 
-use super::TilePattern;
+use crate::{
+    overworld::{SectorBorders, SectorEdges},
+    sector::Tile,
+    util::{WorldPoint, WorldSize},
+};
 
-/// Used to prevent wrapping on the edges in the result
-///
-///
-/// # Examples
-/// A fully functional example can be found under `examples/anchor.rs`
-/// ```
-/// let pattern = TilePattern::from_csv(input_path,
-///     NonZeroU32::new(pattern_size).unwrap(),
-///     &[Orientation::Original]).expect("Error while creating pattern");
+use super::{Anchor, TilePattern};
+
+/// Forbid every pattern but `allowed` in a cell. Returns false if that
+/// contradicts earlier constraints, which also resets the wave.
+fn restrict<W: Wrap, R: Rng>(
+    fi: &mut ForbidInterface<W>,
+    coord: Coord,
+    allowed: &HashSet<PatternId>,
+    num_patterns: usize,
+    rng: &mut R,
+) -> bool {
+    (0..num_patterns as PatternId)
+        .filter(|pattern_id| !allowed.contains(pattern_id))
+        .all(|pattern_id| fi.forbid_pattern(coord, pattern_id, rng).is_ok())
+}
+
+/// Forces the edges of the output to the tiles in the corners of the input,
+/// eg. so that a cave seed with solid rock in its corners produces a cave
+/// that's closed off. The top and left edges use the top-left tile, the
+/// bottom and right edges use the bottom-right one.
 ///
-/// let forbid = ForceBorderForbid::new(&pattern, pattern_size);
-/// let grid = pattern.run_collapse(output_size, attempts,
-///  WrapXY, forbid, &mut rand::thread_rng()).expect("Error in WFC");
-/// ```
+/// See [`super::WfcConstraints::force_border`]
 #[derive(Clone)]
 pub struct ForceBorderForbid {
-    pattern_ids: HashSet<PatternId>,
-    offset: i32,
+    top_left_ids: HashSet<PatternId>,
+    bottom_right_ids: HashSet<PatternId>,
+    sides: SectorEdges,
+    num_patterns: usize,
 }
 
 impl ForbidPattern for ForceBorderForbid {
     fn forbid<W: Wrap, R: Rng>(&mut self, fi: &mut ForbidInterface<W>, rng: &mut R) {
         let output_size = fi.wave_size();
-        (0..(output_size.width() as i32))
-            .map(|x| Coord::new(x, output_size.height() as i32 - self.offset as i32))
-            .chain(
-                (0..(output_size.width() as i32))
-                    .map(|y| Coord::new(output_size.width() as i32 - self.offset as i32, y)),
-            )
-            .for_each(|coord| {
-                self.pattern_ids.iter().for_each(|&pattern_id| {
-                    fi.forbid_all_patterns_except(coord, pattern_id, rng)
-                        .unwrap();
-                });
-            });
+        let width = output_size.width() as i32;
+        let height = output_size.height() as i32;
+
+        let mut edges: Vec<(Coord, &HashSet<PatternId>)> = Vec::new();
+        if self.sides.north {
+            edges.extend((0..width).map(|x| (Coord::new(x, 0), &self.top_left_ids)));
+        }
+        if self.sides.west {
+            edges.extend((0..height).map(|y| (Coord::new(0, y), &self.top_left_ids)));
+        }
+        if self.sides.south {
+            edges.extend((0..width).map(|x| (Coord::new(x, height - 1), &self.bottom_right_ids)));
+        }
+        if self.sides.east {
+            edges.extend((0..height).map(|y| (Coord::new(width - 1, y), &self.bottom_right_ids)));
+        }
+
+        for (coord, allowed) in edges {
+            if !restrict(fi, coord, allowed, self.num_patterns, rng) {
+                tracing::warn!(?coord, "Forced WFC border contradicts the patterns");
+                return;
+            }
+        }
     }
 }
+
 impl ForceBorderForbid {
-    pub fn new(pattern: &TilePattern, pattern_size: u32) -> ForceBorderForbid {
-        let input_size = pattern.overlapping_patterns.grid().size();
-        let bottom_right_offset = pattern_size - (pattern_size / 2);
-        let id_grid = pattern.overlapping_patterns.id_grid();
-        let bottom_right_coord = Coord::new(
-            input_size.width() as i32 - bottom_right_offset as i32,
-            input_size.height() as i32 - bottom_right_offset as i32,
-        );
-        let bottom_right_ids = id_grid
-            .get_checked(bottom_right_coord)
-            .iter()
-            .cloned()
-            .collect::<HashSet<_>>();
+    pub fn new(pattern: &TilePattern, sides: SectorEdges) -> ForceBorderForbid {
+        let patterns = &pattern.overlapping_patterns;
+        let input_size = patterns.grid().size();
+        let num_patterns = patterns.pattern_descriptions().len();
+
+        // A cell becomes the top-left tile of the pattern it collapses to
+        let ids_for = |coord| {
+            let tile = patterns.grid().get_checked(coord);
+            (0..num_patterns as PatternId)
+                .filter(|id| patterns.pattern_top_left_value(*id) == tile)
+                .collect::<HashSet<_>>()
+        };
 
         ForceBorderForbid {
-            pattern_ids: bottom_right_ids,
-            offset: bottom_right_offset as i32,
+            top_left_ids: ids_for(Coord::new(0, 0)),
+            bottom_right_ids: ids_for(Coord::new(
+                input_size.width() as i32 - 1,
+                input_size.height() as i32 - 1,
+            )),
+            sides,
+            num_patterns,
+        }
+    }
+}
+
+/// Pins single output cells to tiles: the edges of already-generated
+/// neighbouring sectors (see [`SectorBorders`]) and a seed's [`Anchor`]s
+#[derive(Clone)]
+pub struct PinForbid {
+    /// Each pinned cell and the patterns it may still collapse to
+    pins: Vec<(Coord, HashSet<PatternId>)>,
+    num_patterns: usize,
+}
+
+impl ForbidPattern for PinForbid {
+    fn forbid<W: Wrap, R: Rng>(&mut self, fi: &mut ForbidInterface<W>, rng: &mut R) {
+        for (coord, allowed) in self.pins.iter() {
+            // A contradiction resets the wave, so leave the rest unpinned
+            if !restrict(fi, *coord, allowed, self.num_patterns, rng) {
+                tracing::warn!(?coord, "Pinned tile contradicts the WFC patterns");
+                return;
+            }
+        }
+    }
+}
+
+impl PinForbid {
+    pub fn new(
+        pattern: &TilePattern,
+        cells: impl IntoIterator<Item = (WorldPoint, Tile)>,
+    ) -> PinForbid {
+        let patterns = &pattern.overlapping_patterns;
+        let num_patterns = patterns.pattern_descriptions().len();
+        // A cell becomes the top-left tile of the pattern it collapses to
+        let top_left: Vec<(PatternId, Tile)> = (0..num_patterns as PatternId)
+            .map(|id| (id, *patterns.pattern_top_left_value(id)))
+            .collect();
+        let allowed = |matches: &dyn Fn(&Tile) -> bool| -> HashSet<PatternId> {
+            top_left
+                .iter()
+                .filter(|(_, tile)| matches(tile))
+                .map(|(id, _)| *id)
+                .collect()
+        };
+
+        let pins = cells
+            .into_iter()
+            .filter_map(|(point, tile)| {
+                // Tiles the seed doesn't know about fall back to any floor or any wall
+                let mut ids = allowed(&|other| *other == tile);
+                if ids.is_empty() {
+                    ids = allowed(&|other| {
+                        std::mem::discriminant(other) == std::mem::discriminant(&tile)
+                    });
+                }
+
+                let pinned = !ids.is_empty() && ids.len() < num_patterns;
+                pinned.then_some((Coord::new(point.x, point.y), ids))
+            })
+            .collect();
+
+        PinForbid { pins, num_patterns }
+    }
+
+    /// Pin the edges next to generated sectors, then a seed's anchors
+    pub fn for_sector(
+        pattern: &TilePattern,
+        borders: &SectorBorders,
+        anchors: &[Anchor],
+        output_size: Size,
+    ) -> PinForbid {
+        let size = WorldSize::new(output_size.width() as i32, output_size.height() as i32);
+        let cells = borders
+            .cells(size)
+            .into_iter()
+            .map(|(point, tile, _)| (point, tile))
+            .chain(anchors.iter().filter_map(|anchor| {
+                let point = anchor.point(size);
+                let in_bounds =
+                    point.x >= 0 && point.y >= 0 && point.x < size.width && point.y < size.height;
+                anchor
+                    .tile()
+                    .filter(|_| in_bounds)
+                    .map(|tile| (point, tile))
+            }));
+        Self::new(pattern, cells)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pins.is_empty()
+    }
+}
+
+/// Everything a seed's constraints and the neighbouring sectors forbid
+#[derive(Clone)]
+pub struct WfcForbid {
+    border: Option<ForceBorderForbid>,
+    pins: PinForbid,
+}
+
+impl ForbidPattern for WfcForbid {
+    fn forbid<W: Wrap, R: Rng>(&mut self, fi: &mut ForbidInterface<W>, rng: &mut R) {
+        if let Some(border) = self.border.as_mut() {
+            border.forbid(fi, rng);
+        }
+        self.pins.forbid(fi, rng);
+    }
+}
+
+impl WfcForbid {
+    pub fn new(border: Option<ForceBorderForbid>, pins: PinForbid) -> Self {
+        Self { border, pins }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.border.is_none() && self.pins.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+    use wfc::wrap::WrapNone;
+
+    use crate::{
+        component::Cardinal,
+        overworld::SectorEdges,
+        sector::{FloorKind, Map, Tile},
+        util::WorldPoint,
+    };
+
+    use super::*;
+
+    #[test]
+    fn sides_next_to_sectors_are_not_forced() {
+        let borders = SectorBorders {
+            west: Some(vec![Tile::Floor(FloorKind::FloorDefault); 3]),
+            ..Default::default()
+        };
+        assert_eq!(
+            borders.open_sides(),
+            SectorEdges {
+                north: true,
+                south: true,
+                east: true,
+                west: false
+            }
+        );
+    }
+
+    #[test]
+    fn pins_border_tiles() {
+        let pattern = super::super::seed::test_seed("cave").tile_pattern();
+        let size = Size::new(24, 24);
+        let mut rng = StdRng::seed_from_u64(3);
+
+        let collapse = |forbid: PinForbid, rng: &mut StdRng| {
+            let grid = pattern
+                .run_collapse(size, 100, WrapNone, forbid, rng)
+                .expect("WFC collapse");
+            Map::new("test".into(), 24, 24, grid.iter().cloned().collect())
+        };
+
+        let empty = PinForbid::for_sector(&pattern, &SectorBorders::default(), &[], size);
+        assert!(empty.is_empty());
+        let west = collapse(empty, &mut rng);
+
+        // A new sector east of the first one continues its right column
+        let mut borders = SectorBorders::default();
+        borders.insert(Cardinal::W, &west);
+        let forbid = PinForbid::for_sector(&pattern, &borders, &[], size);
+        assert!(!forbid.is_empty());
+        let east = collapse(forbid, &mut rng);
+
+        for y in 0..24 {
+            assert_eq!(
+                east[&WorldPoint::new(0, y)],
+                west[&WorldPoint::new(23, y)],
+                "y = {}",
+                y
+            );
         }
     }
 }
@@ -1,5 +1,8 @@
 //! For more info: https://www.gridbugs.org/wave-function-collapse/
 //!
+mod constraint;
+pub use constraint::*;
+
 mod forbid;
 pub use forbid::*;
 
@@ -12,19 +15,16 @@ use rand::Rng;
 use wfc::orientation::Orientation;
 use wfc::overlapping::OverlappingPatterns;
 use wfc::wrap::WrapNone;
-use wfc::{retry, ForbidNothing, ForbidPattern, PropagateError, RunOwn, Wrap};
+use wfc::{retry, ForbidPattern, PropagateError, RunOwn, Wrap};
 
 use coord_2d::{Coord, Size};
 
-use crate::camera::Glyph;
-use crate::color::{Palette, COMMON};
-use crate::component::{Actor, ActorKind, Camera, Player, Position, Renderable, Viewshed};
-use crate::overworld::SectorInfo;
+use crate::overworld::{SectorBorders, SectorInfo};
 use crate::procgen::Spawner;
 use crate::resource::Resources;
 use crate::sector::{FloorKind, Map, Tile};
 
-use super::MapGenerator;
+use super::{blend_borders, spawn_player, spawn_point, MapGenerator};
 
 const WFC_INNER_RETRIES: usize = 100;
 const WFC_OUTER_RETRIES: usize = 25;
@@ -93,6 +93,13 @@ impl TilePattern {
     }
 }
 
+/// Why a single WFC attempt was thrown away
+#[derive(Debug)]
+pub enum WfcError {
+    Contradiction(PropagateError),
+    TileCount(TileCountError),
+}
+
 pub struct WfcGen {
     seed: seed::WfcSeed,
 }
@@ -100,6 +107,54 @@ impl WfcGen {
     pub fn new(seed: seed::WfcSeed) -> Self {
         Self { seed }
     }
+
+    /// Combine the seed's constraints with the edges of neighbouring sectors
+    pub fn forbid(
+        &self,
+        pattern: &TilePattern,
+        borders: &SectorBorders,
+        output_size: Size,
+    ) -> WfcForbid {
+        let constraints = &self.seed.constraints;
+        let border = constraints
+            .force_border
+            .then(|| ForceBorderForbid::new(pattern, borders.open_sides()));
+        let pins = PinForbid::for_sector(pattern, borders, &constraints.anchors, output_size);
+        WfcForbid::new(border, pins)
+    }
+
+    /// Run the collapse once and check the output against the seed's constraints
+    pub fn collapse<R: Rng>(
+        &self,
+        pattern: &TilePattern,
+        forbid: &WfcForbid,
+        output_size: Size,
+        rng: &mut R,
+    ) -> Result<Map, WfcError> {
+        let grid = pattern
+            .run_collapse(
+                output_size,
+                WFC_INNER_RETRIES,
+                WrapNone,
+                forbid.clone(),
+                rng,
+            )
+            .map_err(WfcError::Contradiction)?;
+
+        let tilevec: Vec<Tile> = grid.iter().map(move |tile| tile.to_owned()).collect();
+        let map = Map::new(
+            "WFC tester".into(),
+            output_size.width() as i32,
+            output_size.height() as i32,
+            tilevec,
+        );
+
+        self.seed
+            .constraints
+            .check(&map)
+            .map_err(WfcError::TileCount)?;
+        Ok(map)
+    }
 }
 
 impl MapGenerator for WfcGen {
@@ -116,7 +171,7 @@ impl MapGenerator for WfcGen {
 
         let pattern = self.seed.tile_pattern();
         let pattern_size = pattern.grid.size();
-        let _forbid = ForceBorderForbid::new(&pattern, self.seed.pattern_size);
+        let forbid = self.forbid(&pattern, &sector_info.borders, output_size);
 
         tracing::info!(
             output_width = output_size.width(),
@@ -125,6 +180,8 @@ impl MapGenerator for WfcGen {
             input_height = pattern_size.height(),
             seed_pattern_size = self.seed.pattern_size,
             orientation_count = self.seed.orientation.len(),
+            constraints = ?self.seed.constraints,
+            pinned_border = !sector_info.borders.is_empty(),
             inner_retries = WFC_INNER_RETRIES,
             outer_retries = WFC_OUTER_RETRIES,
             "Starting WFC sector generation"
@@ -140,30 +197,21 @@ impl MapGenerator for WfcGen {
                 "Running WFC collapse"
             );
 
-            match pattern.run_collapse(
-                output_size,
-                WFC_INNER_RETRIES,
-                WrapNone,
-                ForbidNothing,
-                // forbid.clone(),
-                resources.rng.get_rng(), // &mut rand::thread_rng(),
-            ) {
-                Ok(grid) => {
+            match self.collapse(&pattern, &forbid, output_size, resources.rng.get_rng()) {
+                Ok(mut map) => {
                     tracing::info!(
                         attempt,
                         elapsed_ms = start.elapsed().as_millis(),
                         "WFC sector generation succeeded"
                     );
-
-                    let tilevec: Vec<Tile> = grid.iter().map(move |tile| tile.to_owned()).collect();
-                    let map = Map::new(
-                        "WFC tester".into(),
-                        output_size.width() as i32,
-                        output_size.height() as i32,
-                        tilevec,
-                    );
                     mapgen_history.push(map.clone());
 
+                    // Anything the pins couldn't hold gets blended in
+                    if !sector_info.borders.is_empty() {
+                        blend_borders(&mut map, &sector_info.borders);
+                        mapgen_history.push(map.clone());
+                    }
+
                     return map;
                 }
                 Err(err) => {
@@ -193,11 +241,13 @@ impl MapGenerator for WfcGen {
             "WFC sector generation exhausted retries; using simple fallback map"
         );
 
-        let fallback = Map::init(
-            "WFC fallback after exhausted retries".into(),
+        resources.mapgen_fallbacks += 1;
+        let mut fallback = Map::init(
+            "WFC fallback".into(),
             sector_info.size,
             Tile::Floor(FloorKind::FloorDefault),
         );
+        blend_borders(&mut fallback, &sector_info.borders);
         mapgen_history.push(fallback.clone());
         fallback
     }
@@ -205,26 +255,116 @@ impl MapGenerator for WfcGen {
 
 impl Spawner for WfcGen {
     fn spawn(&self, map: &Map, world: &mut hecs::World, _resources: &mut Resources) {
-        for point in map.iter_points() {
-            if let Tile::Floor(_) = map[&point] {
-                // Add the player
-                world.spawn((
-                    Position::new(point),
-                    // Renderable::new(
-                    //     Glyph::new('@', COMMON.four, Palette::empty()),
-                    //     PLAYER,
-                    //     5,
-                    //     None,
-                    // ),
-                    Viewshed::default().with_init().with_range(100),
-                    Player {},
-                    Actor::new(0, 100, 100, 20, 0, ActorKind::Player(None)),
-                ));
-
-                // Add the camera
-                world.spawn((Position::new(map.get_rect().center()), Camera {}));
-                break;
+        // Start in the largest region so that the player can reach most of the map
+        if let Some(point) = spawn_point(map) {
+            spawn_player(world, point, map.get_rect().center());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bracket_random::prelude::RandomNumberGenerator;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use crate::{
+        data::{Element, PlanetType, SectorType},
+        resource::test_asset_roots,
+        sector::{MapMetrics, MapReport},
+        util::{WorldPoint, WorldSize},
+    };
+
+    use super::*;
+
+    /// Highest share of sectors WFC may give up on before a seed or
+    /// constraint change counts as a regression
+    const MAX_FALLBACK_RATE: f32 = 0.2;
+
+    #[test]
+    fn constraints_hold() {
+        let wfc = WfcGen::new(seed::test_seed("cave"));
+        let pattern = wfc.seed.tile_pattern();
+        let size = Size::new(32, 32);
+        let forbid = wfc.forbid(&pattern, &SectorBorders::default(), size);
+        let mut rng = StdRng::seed_from_u64(5);
+
+        let map = (0..WFC_OUTER_RETRIES)
+            .find_map(|_| wfc.collapse(&pattern, &forbid, size, &mut rng).ok())
+            .expect("a map that meets the constraints");
+
+        // The forced border closes the cave off with rock
+        for i in 0..32 {
+            for point in [
+                WorldPoint::new(i, 0),
+                WorldPoint::new(0, i),
+                WorldPoint::new(i, 31),
+                WorldPoint::new(31, i),
+            ] {
+                assert!(!map[&point].is_passable(), "{:?}", point);
             }
         }
+
+        // The exit anchor
+        assert_eq!(map[&WorldPoint::new(29, 29)].glyph(), '.');
+
+        let floors = map.iter_tiles().filter(|(_, tile)| tile.glyph() == '.');
+        assert!(floors.count() >= 100);
+    }
+
+    #[test]
+    fn tile_counts() {
+        let constraints = WfcConstraints {
+            tile_counts: vec![TileCount {
+                tile: '.',
+                min: 0.25,
+                max: Some(0.4),
+            }],
+            ..Default::default()
+        };
+        let floor = Tile::Floor(FloorKind::FloorDefault);
+        let map = |floors: usize| {
+            let mut tiles = vec![Tile::from_char('#').unwrap(); 9];
+            tiles[..floors].fill(floor);
+            Map::new("test".into(), 3, 3, tiles)
+        };
+
+        assert!(constraints.check(&map(1)).is_err());
+        assert!(constraints.check(&map(3)).is_ok());
+        assert_eq!(
+            constraints.check(&map(5)).unwrap_err().count,
+            5,
+            "too many floors"
+        );
+    }
+
+    #[test]
+    fn fallback_rate() {
+        let mut resources = Resources::headless(0, test_asset_roots()).unwrap();
+
+        // Full sectors, and the small submaps Combo asks for
+        for (name, size) in [("cave", 32), ("cave", 16), ("city", 8)] {
+            let sector_info = SectorInfo::standalone(
+                PlanetType::Barren,
+                Element::Water,
+                SectorType::Barren,
+                WorldSize::splat(size),
+                &resources.assets_cache,
+            )
+            .unwrap();
+
+            let mut wfc = WfcGen::new(seed::test_seed(name));
+            let mut report = MapReport::new(&format!("wfc:{} at {}x{}", name, size, size));
+            for seed in 0..10 {
+                resources.rng = RandomNumberGenerator::seeded(seed);
+                let fallbacks = resources.mapgen_fallbacks;
+                let map = wfc.generate(&sector_info, &mut resources, &mut Vec::new());
+                report.add(MapMetrics {
+                    fallback: resources.mapgen_fallbacks > fallbacks,
+                    ..MapMetrics::measure(&map, &mut resources.rng)
+                });
+            }
+
+            assert!(report.fallback_rate() <= MAX_FALLBACK_RATE, "{}", report);
+        }
     }
 }
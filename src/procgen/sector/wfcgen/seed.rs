@@ -1,28 +1,129 @@
-use std::num::NonZeroU32;
+//! Sample inputs for [`super::WfcGen`], loaded from `assets/wfc/*.yaml`
+use std::{borrow::Cow, num::NonZeroU32};
 
+use assets_manager::{asset::load_yaml, AssetCache, BoxedError, FileAsset};
 use coord_2d::Size;
+use serde::{Deserialize, Serialize};
 use wfc::orientation::Orientation;
 
 use crate::sector::Tile;
 
-use super::TilePattern;
+use super::{TilePattern, WfcConstraints};
 
+/// Asset directory every seed lives in. A seed's name is its file stem.
+pub const SEED_DIR: &str = "wfc";
+
+/// Which rotations and reflections of the sample WFC may use
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SeedOrientation {
+    Original,
+    Clockwise90,
+    Clockwise180,
+    Clockwise270,
+    DiagonallyFlipped,
+    DiagonallyFlippedClockwise90,
+    DiagonallyFlippedClockwise180,
+    DiagonallyFlippedClockwise270,
+}
+
+impl From<SeedOrientation> for Orientation {
+    fn from(orientation: SeedOrientation) -> Self {
+        match orientation {
+            SeedOrientation::Original => Orientation::Original,
+            SeedOrientation::Clockwise90 => Orientation::Clockwise90,
+            SeedOrientation::Clockwise180 => Orientation::Clockwise180,
+            SeedOrientation::Clockwise270 => Orientation::Clockwise270,
+            SeedOrientation::DiagonallyFlipped => Orientation::DiagonallyFlipped,
+            SeedOrientation::DiagonallyFlippedClockwise90 => {
+                Orientation::DiagonallyFlippedClockwise90
+            }
+            SeedOrientation::DiagonallyFlippedClockwise180 => {
+                Orientation::DiagonallyFlippedClockwise180
+            }
+            SeedOrientation::DiagonallyFlippedClockwise270 => {
+                Orientation::DiagonallyFlippedClockwise270
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct WfcSeed {
-    pub pattern: &'static str,
-    pub orientation: &'static [Orientation],
+    /// One row of tile characters per line (see [`Tile::from_char`])
+    pub pattern: String,
+    #[serde(default = "WfcSeed::default_orientation")]
+    pub orientation: Vec<SeedOrientation>,
     pub pattern_size: u32,
+    #[serde(default)]
+    pub constraints: WfcConstraints,
+}
+
+impl FileAsset for WfcSeed {
+    const EXTENSION: &'static str = "yaml";
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Result<Self, BoxedError> {
+        let seed: WfcSeed = load_yaml(&bytes)?;
+        seed.validate()?;
+        Ok(seed)
+    }
 }
 
 impl WfcSeed {
+    fn default_orientation() -> Vec<SeedOrientation> {
+        vec![SeedOrientation::Original]
+    }
+
+    fn rows(&self) -> impl Iterator<Item = &str> {
+        self.pattern.lines().filter(|line| !line.trim().is_empty())
+    }
+
     fn input_size(&self) -> Size {
-        let width = self.pattern.lines().next().unwrap().len();
-        let height = self.pattern.lines().count();
+        let width = self.rows().next().unwrap_or_default().chars().count();
+        let height = self.rows().count();
 
         Size::new(width as u32, height as u32)
     }
 
+    /// Catch mistakes that would otherwise panic deep inside WFC
+    pub fn validate(&self) -> anyhow::Result<()> {
+        let size = self.input_size();
+        if self
+            .rows()
+            .any(|row| row.chars().count() as u32 != size.width())
+        {
+            anyhow::bail!("Every row of a WFC pattern needs the same width");
+        }
+        if self.pattern_size == 0
+            || self.pattern_size > size.width()
+            || self.pattern_size > size.height()
+        {
+            anyhow::bail!(
+                "Pattern size {} doesn't fit in a {}x{} pattern",
+                self.pattern_size,
+                size.width(),
+                size.height()
+            );
+        }
+        if self.orientation.is_empty() {
+            anyhow::bail!("A WFC seed needs at least one orientation");
+        }
+        if let Some(c) = self
+            .rows()
+            .flat_map(|row| row.chars())
+            .find(|c| Tile::from_char(*c).is_none())
+        {
+            anyhow::bail!("Unknown tile {:?} in WFC pattern", c);
+        }
+        Ok(())
+    }
+
     pub fn tile_pattern(&self) -> TilePattern {
-        let tiles = self.pattern.chars().filter_map(Tile::from_char).collect();
+        let tiles = self
+            .rows()
+            .flat_map(|row| row.chars())
+            .filter_map(Tile::from_char)
+            .collect();
+        let orientation: Vec<Orientation> = self.orientation.iter().map(|o| (*o).into()).collect();
 
         // Extract patterns from input
         TilePattern::from_vec(
@@ -32,98 +133,74 @@ impl WfcSeed {
             // What size tiles to use examining the input?
             NonZeroU32::new(self.pattern_size).unwrap(),
             // Can we rotate tiles?
-            // &[Orientation::Original, Orientation::Clockwise180],
-            self.orientation,
+            &orientation,
         )
     }
 }
 
-pub const CITY: WfcSeed = WfcSeed {
-    pattern: "\
-.........
-.╔═╦═╗...
-.║___║...
-.║___║...
-.║___║...
-.║___║...
-.╚═╩═╝...
-.........
-.........",
-    orientation: &[Orientation::Original],
-    pattern_size: 3,
-};
-
-pub const CAVE: WfcSeed = WfcSeed {
-    pattern: "\
-#################
-##...####.####.##
-#.....##...##...#
-#.....##...##...#
-#.....##...##...#
-#####.####.####.#
-#####.####......#
-#####.######.####
-##....###......##
-##...####.####.##
-#.....##...##...#
-#.....##...##...#
-#.....##...##...#
-#####.####.####.#
-#####.####......#
-#####.########.##
-##....########.##
-##...####.####.##
-#.....##...##...#
-#.....##........#
-#.....##...##...#
-#####.####.####.#
-#####......####.#
-#################",
-    orientation: &[
-        Orientation::Original,
-        // Orientation::Clockwise90,
-        // Orientation::Clockwise180,
-        // Orientation::Clockwise270,
-    ],
-    pattern_size: 3,
-};
-
-pub const CRATERS: WfcSeed = WfcSeed {
-    pattern: "\
-,,,,,,,,,,,,,,,,,,
-,,,░,,,,,,,,,,,,*,
-,,,,,,░░,,,,,,,,,,
-,,░░░░░░░,,,,,,,,,
-,,░▒▒▒▒▒░,,,,*,,,,
-,░░▒▓▓▓▒░░,,,,*,,,
-,,░▒▓▓▓▒░,,,,,,,,,
-,,░▒▓▓▓▒░░,,░,,,,,
-,,░▒▒▒▒▒░░,,,,,,,,
-,░░░░░░░░,,,,,,,,,
-,,,░░░░░░,,,,,,,,,
-,░,,,░░,,,,,,,,,,,
-,,,,,,,,,,,,,░░,,,
-,,,,,,,,,,,,░▒▒░,,
-,,,,,,,,,,,,,▒▓▒,,
-,,,,*,,,,,,,░▒▒░,,
-,,,,,,,,,,,,,░,,,,
-,,,,,,,,,,,,,,,,,,",
-    orientation: &[
-        Orientation::Original,
-        Orientation::Clockwise90,
-        Orientation::Clockwise180,
-        Orientation::Clockwise270,
-    ],
-    pattern_size: 3,
-};
+/// Load a seed by name, eg. `"cave"` for `assets/wfc/cave.yaml`. The cache
+/// keeps the seed up to date, so call this whenever a generator is built
+/// rather than holding on to the result.
+pub fn load(cache: &AssetCache, name: &str) -> anyhow::Result<WfcSeed> {
+    Ok(cache
+        .load::<WfcSeed>(&format!("{}.{}", SEED_DIR, name))?
+        .read()
+        .clone())
+}
+
+/// The names of every seed in the assets, eg. for picking one from a menu
+pub fn names(cache: &AssetCache) -> anyhow::Result<Vec<String>> {
+    let prefix = format!("{}.", SEED_DIR);
+    let mut names: Vec<String> = cache
+        .load_dir::<WfcSeed>(SEED_DIR)?
+        .read()
+        .ids()
+        .map(|id| id.trim_start_matches(&prefix).to_owned())
+        .collect();
+    names.sort();
+    Ok(names)
+}
+
+#[cfg(test)]
+pub(crate) fn test_seed(name: &str) -> WfcSeed {
+    load(&crate::resource::test_asset_cache(), name).unwrap()
+}
 
 #[cfg(test)]
 mod tests {
+    use crate::resource::test_asset_cache;
+
     use super::*;
 
     #[test]
     fn test_sizes() {
-        assert_eq!(CITY.pattern.chars().next().unwrap(), '.');
-        assert_eq!(CITY.input_size(), Size::new(9, 9));
+        let city = test_seed("city");
+        assert_eq!(city.pattern.chars().next().unwrap(), '.');
+        assert_eq!(city.input_size(), Size::new(9, 9));
+
+        // Box drawing characters are more than one byte wide
+        let craters = test_seed("craters");
+        assert_eq!(craters.input_size(), Size::new(18, 18));
+    }
+
+    #[test]
+    fn list_and_validate() {
+        let cache = test_asset_cache();
+
+        let names = names(&cache).unwrap();
+        assert_eq!(names, vec!["cave", "city", "craters"]);
+        for name in names {
+            load(&cache, &name).unwrap();
+        }
+
+        let ragged: Result<WfcSeed, _> = WfcSeed::from_bytes(Cow::Borrowed(
+            b"pattern: |\n  ...\n  ..\n  ...\npattern_size: 2\n",
+        ));
+        assert!(ragged.is_err());
+
+        let unknown: Result<WfcSeed, _> = WfcSeed::from_bytes(Cow::Borrowed(
+            b"pattern: |\n  ...\n  .\\.\n  ...\npattern_size: 2\n",
+        ));
+        assert!(unknown.is_err());
     }
 }
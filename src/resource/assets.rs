@@ -1,6 +1,38 @@
+use std::{
+    io::{self, Cursor},
+    path::{Component, Path},
+};
+
+use assets_manager::{source::Source, AssetCache};
+
 use crate::data::{BitmapFont, Tileset};
 
 pub struct Assets {
     pub tileset: Tileset,
     pub monospace_font: BitmapFont,
 }
+
+/// Read a file by its path relative to the assets directory, eg.
+/// `maps/../tileset/tileset_transparent.tsx`, through the asset cache so that
+/// it can come from a mod. This is how Tiled and image files get loaded.
+pub fn read_asset_path(cache: &AssetCache, path: &Path) -> io::Result<Cursor<Vec<u8>>> {
+    let stem = path.with_extension("");
+    let mut parts: Vec<&str> = Vec::new();
+    for component in stem.components() {
+        match component {
+            Component::Normal(part) => parts.push(
+                part.to_str()
+                    .ok_or_else(|| io::Error::from(io::ErrorKind::InvalidInput))?,
+            ),
+            Component::ParentDir => {
+                parts.pop();
+            }
+            _ => {}
+        }
+    }
+    let ext = path.extension().and_then(|ext| ext.to_str()).unwrap_or("");
+
+    let source = cache.source();
+    let bytes = source.read(&parts.join("."), ext)?.into_bytes();
+    Ok(Cursor::new(bytes))
+}
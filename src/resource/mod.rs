@@ -1,16 +1,21 @@
+use std::path::Path;
+
 use assets_manager::{Asset, AssetCache, Handle};
 use bracket_random::prelude::RandomNumberGenerator;
 
 mod viewport;
-use macroquad::texture::{load_texture, FilterMode};
 pub use viewport::*;
 
 mod assets;
 pub use assets::*;
 
+mod overlay;
+pub use overlay::*;
+
 use crate::{
-    data::{BitmapFont, Tileset},
+    data::{load_texture, BitmapFont, Tileset},
     game::consts,
+    sector::TILESET_PATH,
     util::{
         PixelSize, SpriteSize, ViewportPoint, ViewportRect, ViewportSize, WorldSpace,
         WorldToViewport,
@@ -19,52 +24,120 @@ use crate::{
 
 pub struct Resources {
     pub rng: RandomNumberGenerator,
+    /// The seed every generated location derives its own seed from
+    pub seed: u64,
     pub viewport: Viewport<WorldSpace>,
 
     // TODO: deprecate AssetCache (not wasm/android compatible)
     pub assets_cache: AssetCache,
-    pub assets: Assets,
+    pub asset_roots: AssetRoots,
+    /// Textures and fonts. Missing in headless Resources.
+    assets: Option<Assets>,
+    /// How many times a map generator gave up and handed back a fallback map
+    pub mapgen_fallbacks: usize,
 }
 
 impl Resources {
-    pub async fn try_new(rng_seed: u64, assets_cache: AssetCache) -> anyhow::Result<Self> {
-        let rng = RandomNumberGenerator::seeded(rng_seed);
-        let viewport = Viewport::new(
-            ViewportRect::new(
-                ViewportPoint::new(0, 0),
-                ViewportSize::new(consts::VIEWPORT_WIDTH, consts::VIEWPORT_HEIGHT),
-            ),
-            WorldToViewport::default(),
-        );
+    /// Textures are loaded through the asset cache like everything else, so
+    /// mods can replace them too
+    pub fn try_new(rng_seed: u64, asset_roots: AssetRoots) -> anyhow::Result<Self> {
+        let mut resources = Self::headless(rng_seed, asset_roots)?;
 
         tracing::info!("Loading Tileset");
-        let tileset = Tileset::try_from_file("tileset/tileset_transparent.tsx").await?;
+        let tileset = Tileset::load(&resources.assets_cache, TILESET_PATH)?;
 
         tracing::info!("Loading Font");
-        let monospace_font_texture = load_texture("fonts/zx_evolution_8x8.png").await?;
-        monospace_font_texture.set_filter(FilterMode::Nearest);
+        let monospace_font_texture = load_texture(
+            &resources.assets_cache,
+            Path::new("fonts/zx_evolution_8x8.png"),
+        )?;
         let monospace_font = BitmapFont::new(
             monospace_font_texture,
             PixelSize::new(8, 8),
             SpriteSize::new(16, 16),
         );
 
-        let assets = Assets {
+        resources.assets = Some(Assets {
             tileset,
             monospace_font,
-        };
+        });
+        Ok(resources)
+    }
+
+    /// Resources without any textures, for running procgen without a window
+    pub fn headless(rng_seed: u64, asset_roots: AssetRoots) -> anyhow::Result<Self> {
+        for root in asset_roots.iter() {
+            match &root.manifest {
+                Some(manifest) => tracing::info!(
+                    "Adding mod {} v{} (load order {}) from {:?}",
+                    manifest.name,
+                    manifest.version,
+                    manifest.load_order,
+                    root.path
+                ),
+                None => tracing::info!("Adding assets path {:?}", root.path),
+            }
+        }
+        let assets_cache = asset_roots.asset_cache()?;
+
+        let rng = RandomNumberGenerator::seeded(rng_seed);
+        let viewport = Viewport::new(
+            ViewportRect::new(
+                ViewportPoint::new(0, 0),
+                ViewportSize::new(consts::VIEWPORT_WIDTH, consts::VIEWPORT_HEIGHT),
+            ),
+            WorldToViewport::default(),
+        );
 
         Ok(Self {
             rng,
+            seed: rng_seed,
             viewport,
             assets_cache,
-            assets,
+            asset_roots,
+            assets: None,
+            mapgen_fallbacks: 0,
         })
     }
 
+    /// Textures and fonts for drawing
+    ///
+    /// Panics in headless Resources, which are never used to draw.
+    pub fn assets(&self) -> &Assets {
+        self.assets
+            .as_ref()
+            .expect("headless Resources have no textures")
+    }
+
+    /// Run `f` with `rng` in place of the shared RNG, so that anything generated
+    /// inside only depends on that RNG's seed
+    pub fn with_rng<T>(&mut self, rng: RandomNumberGenerator, f: impl FnOnce(&mut Self) -> T) -> T {
+        let parent = std::mem::replace(&mut self.rng, rng);
+        let result = f(self);
+        self.rng = parent;
+        result
+    }
+
     pub fn load_asset<T: Asset>(&self, id: &str) -> &Handle<T> {
         self.assets_cache
             .load::<T>(id)
             .unwrap_or_else(|err| panic!("asset load from {:?}: {}", id, err))
     }
 }
+
+/// The base game's assets, for tests
+#[cfg(test)]
+pub fn test_asset_cache() -> AssetCache {
+    AssetCache::new(test_asset_path()).unwrap()
+}
+
+/// Asset roots with only the base game's assets, for tests
+#[cfg(test)]
+pub fn test_asset_roots() -> AssetRoots {
+    AssetRoots::new(test_asset_path())
+}
+
+#[cfg(test)]
+fn test_asset_path() -> std::path::PathBuf {
+    std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR")).join(consts::RESOURCE_PATH)
+}
@@ -0,0 +1,395 @@
+//! Layered asset sources: the base game, then mods, then the user dir.
+//!
+//! Plain files resolve to the last root that contains them. YAML files are
+//! merged across every root so that mods can extend data tables instead of
+//! replacing them wholesale (eg. a mod's `data/monsters.yaml` appends its
+//! monsters to the base game's spawn list). Files in [`REPLACE_LISTS_IN`]
+//! describe a single thing, so their lists are replaced instead, and the keys
+//! in [`LIST_MERGE_KEYS`] merge their own way.
+use std::{
+    collections::BTreeSet,
+    io,
+    path::{Path, PathBuf},
+};
+
+use assets_manager::{
+    hot_reloading::EventSender,
+    source::{DirEntry, FileContent, FileSystem, Source},
+    AssetCache, BoxedError,
+};
+use serde::{Deserialize, Serialize};
+use serde_yaml::Value;
+
+/// Filename of the manifest at the root of every mod directory
+pub const MOD_MANIFEST: &str = "mod.yaml";
+
+/// Asset directories whose YAML files are one thing each (eg. a WFC seed's
+/// `orientation` and `anchors`) rather than tables to extend
+pub const REPLACE_LISTS_IN: &[&str] = &["wfc"];
+
+/// Top-level keys of a file whose lists don't merge like the rest of it, by
+/// asset id. Recipe rules are first-match, so a mod's rules go first to be
+/// able to override the base game's.
+pub const LIST_MERGE_KEYS: &[(&str, &str, ListMerge)] =
+    &[("data.sector_recipes", "rules", ListMerge::Prepend)];
+
+/// What [`merge_yaml`] does when both documents have a sequence
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListMerge {
+    /// Base entries first, then the override's
+    Append,
+    /// The override's entries first, then the base's
+    Prepend,
+    /// Only the override's entries
+    Replace,
+}
+
+impl ListMerge {
+    /// How lists merge under a top-level key of the file with this asset id
+    pub fn for_key(id: &str, key: &str) -> Self {
+        LIST_MERGE_KEYS
+            .iter()
+            .find(|(file, list, _)| *file == id && *list == key)
+            .map_or_else(|| Self::for_id(id), |(_, _, lists)| *lists)
+    }
+
+    /// How lists merge in the file with this asset id
+    pub fn for_id(id: &str) -> Self {
+        let dir = id.split('.').next().unwrap_or_default();
+        if REPLACE_LISTS_IN.contains(&dir) {
+            ListMerge::Replace
+        } else {
+            ListMerge::Append
+        }
+    }
+}
+
+/// Metadata describing a content pack
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModManifest {
+    pub name: String,
+    pub version: String,
+    /// Mods with a lower load order are applied first (and can be overridden by later mods)
+    #[serde(default)]
+    pub load_order: i32,
+}
+
+impl ModManifest {
+    pub fn try_from_dir(dir: &Path) -> anyhow::Result<Self> {
+        let path = dir.join(MOD_MANIFEST);
+        let file = std::fs::File::open(&path)
+            .map_err(|err| anyhow::anyhow!("reading mod manifest {:?}: {}", path, err))?;
+        Ok(serde_yaml::from_reader(file)?)
+    }
+}
+
+/// A directory of assets along with the mod that provided it (if any)
+#[derive(Debug, Clone)]
+pub struct AssetRoot {
+    pub path: PathBuf,
+    pub manifest: Option<ModManifest>,
+}
+
+/// Ordered list of asset directories. Later roots take precedence.
+#[derive(Debug, Clone, Default)]
+pub struct AssetRoots {
+    base: Option<AssetRoot>,
+    mods: Vec<AssetRoot>,
+    user: Option<AssetRoot>,
+}
+
+impl AssetRoots {
+    pub fn new(base: PathBuf) -> Self {
+        Self {
+            base: Some(AssetRoot {
+                path: base,
+                manifest: None,
+            }),
+            ..Default::default()
+        }
+    }
+
+    /// Add a mod directory. Each mod must contain a [`MOD_MANIFEST`].
+    pub fn with_mod(mut self, dir: PathBuf) -> anyhow::Result<Self> {
+        let manifest = ModManifest::try_from_dir(&dir)?;
+        self.mods.push(AssetRoot {
+            path: dir,
+            manifest: Some(manifest),
+        });
+
+        // Stable sort so that mods with equal load order keep their CLI order
+        self.mods.sort_by_key(|root| {
+            root.manifest
+                .as_ref()
+                .map(|manifest| manifest.load_order)
+                .unwrap_or_default()
+        });
+        Ok(self)
+    }
+
+    /// Add the user's asset directory. Missing directories are ignored.
+    pub fn with_user_dir(mut self, dir: PathBuf) -> Self {
+        if dir.is_dir() {
+            self.user = Some(AssetRoot {
+                path: dir,
+                manifest: None,
+            });
+        }
+        self
+    }
+
+    pub fn base(&self) -> Option<&Path> {
+        self.base.as_ref().map(|root| root.path.as_path())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &AssetRoot> {
+        self.base
+            .iter()
+            .chain(self.mods.iter())
+            .chain(self.user.iter())
+    }
+
+    pub fn asset_cache(&self) -> io::Result<AssetCache> {
+        Ok(AssetCache::with_source(self.clone().into_source()?))
+    }
+
+    pub fn into_source(self) -> io::Result<OverlaySource> {
+        let roots = self
+            .iter()
+            .map(|root| FileSystem::new(&root.path))
+            .collect::<io::Result<Vec<_>>>()?;
+        Ok(OverlaySource::new(roots))
+    }
+}
+
+/// An assets_manager [`Source`] that layers several directories on top of each other
+#[derive(Debug)]
+pub struct OverlaySource {
+    roots: Vec<FileSystem>,
+}
+
+impl OverlaySource {
+    pub fn new(roots: Vec<FileSystem>) -> Self {
+        Self { roots }
+    }
+
+    fn read_yaml(&self, id: &str, ext: &str) -> io::Result<FileContent<'_>> {
+        let mut merged: Option<Value> = None;
+
+        for root in self.roots.iter() {
+            let bytes = match root.read(id, ext) {
+                Ok(content) => content.into_bytes(),
+                Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+                Err(err) => return Err(err),
+            };
+
+            let layer: Value = serde_yaml::from_slice(&bytes)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+
+            merged = Some(match merged {
+                Some(base) => merge_file(id, base, layer),
+                None => layer,
+            });
+        }
+
+        let merged = merged.ok_or_else(|| io::Error::from(io::ErrorKind::NotFound))?;
+        let bytes = serde_yaml::to_string(&merged)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+
+        Ok(FileContent::Buffer(bytes.into_bytes()))
+    }
+}
+
+impl Source for OverlaySource {
+    fn read(&self, id: &str, ext: &str) -> io::Result<FileContent<'_>> {
+        if ext == "yaml" || ext == "yml" {
+            return self.read_yaml(id, ext);
+        }
+
+        // Last root wins for everything that can't be merged
+        self.roots
+            .iter()
+            .rev()
+            .find(|root| root.exists(DirEntry::File(id, ext)))
+            .ok_or_else(|| io::Error::from(io::ErrorKind::NotFound))?
+            .read(id, ext)
+    }
+
+    fn read_dir(&self, id: &str, f: &mut dyn FnMut(DirEntry)) -> io::Result<()> {
+        let mut files = BTreeSet::new();
+        let mut dirs = BTreeSet::new();
+        let mut found = false;
+
+        for root in self.roots.iter() {
+            let result = root.read_dir(id, &mut |entry| match entry {
+                DirEntry::File(id, ext) => {
+                    files.insert((id.to_owned(), ext.to_owned()));
+                }
+                DirEntry::Directory(id) => {
+                    dirs.insert(id.to_owned());
+                }
+            });
+            found |= result.is_ok();
+        }
+
+        if !found {
+            return Err(io::Error::from(io::ErrorKind::NotFound));
+        }
+
+        for dir in dirs.iter() {
+            f(DirEntry::Directory(dir));
+        }
+        for (id, ext) in files.iter() {
+            f(DirEntry::File(id, ext));
+        }
+
+        Ok(())
+    }
+
+    fn exists(&self, entry: DirEntry) -> bool {
+        self.roots.iter().any(|root| root.exists(entry))
+    }
+
+    /// Watch every root, so that editing eg. a mod's WFC seed reloads it
+    fn configure_hot_reloading(&self, events: EventSender) -> Result<(), BoxedError> {
+        for root in self.roots.iter() {
+            root.configure_hot_reloading(events.clone())?;
+        }
+        Ok(())
+    }
+}
+
+/// [`merge_yaml`] with the list merging picked per top-level key
+fn merge_file(id: &str, base: Value, layer: Value) -> Value {
+    match (base, layer) {
+        (Value::Mapping(mut base), Value::Mapping(layer)) => {
+            for (key, value) in layer {
+                let lists = match key.as_str() {
+                    Some(name) => ListMerge::for_key(id, name),
+                    None => ListMerge::for_id(id),
+                };
+                let merged = match base.remove(&key) {
+                    Some(existing) => merge_yaml(existing, value, lists),
+                    None => value,
+                };
+                base.insert(key, merged);
+            }
+            Value::Mapping(base)
+        }
+        (base, layer) => merge_yaml(base, layer, ListMerge::for_id(id)),
+    }
+}
+
+/// Merge an overriding YAML document onto a base document
+///
+/// - Mappings are merged key-by-key (recursively)
+/// - Sequences are concatenated or replaced, depending on `lists`
+/// - Anything else is replaced by the override
+pub fn merge_yaml(base: Value, layer: Value, lists: ListMerge) -> Value {
+    match (base, layer) {
+        (Value::Mapping(mut base), Value::Mapping(layer)) => {
+            for (key, value) in layer {
+                let merged = match base.remove(&key) {
+                    Some(existing) => merge_yaml(existing, value, lists),
+                    None => value,
+                };
+                base.insert(key, merged);
+            }
+            Value::Mapping(base)
+        }
+        (Value::Sequence(mut base), Value::Sequence(layer)) if lists == ListMerge::Append => {
+            base.extend(layer);
+            Value::Sequence(base)
+        }
+        (Value::Sequence(base), Value::Sequence(mut layer)) if lists == ListMerge::Prepend => {
+            layer.extend(base);
+            Value::Sequence(layer)
+        }
+        (_, layer) => layer,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    fn yaml(text: &str) -> Value {
+        serde_yaml::from_str(text).unwrap()
+    }
+
+    #[test]
+    fn merge_tables() {
+        let base = yaml("planet_type:\n  items:\n    Barren: 1.0\n    Lush: 1.0\n");
+        let layer = yaml("planet_type:\n  items:\n    Lush: 3.0\n    Mountains: 2.0\n");
+
+        assert_eq!(
+            merge_yaml(base, layer, ListMerge::Append),
+            yaml("planet_type:\n  items:\n    Barren: 1.0\n    Lush: 3.0\n    Mountains: 2.0\n")
+        );
+    }
+
+    #[test]
+    fn merge_lists() {
+        let base = yaml("- name: Kobold\n");
+        let layer = yaml("- name: Goblin\n");
+
+        assert_eq!(
+            merge_yaml(base.clone(), layer.clone(), ListMerge::Append),
+            yaml("- name: Kobold\n- name: Goblin\n")
+        );
+        assert_eq!(
+            merge_yaml(base.clone(), layer.clone(), ListMerge::Prepend),
+            yaml("- name: Goblin\n- name: Kobold\n")
+        );
+        assert_eq!(
+            merge_yaml(base, layer, ListMerge::Replace),
+            yaml("- name: Goblin\n")
+        );
+
+        // A mod's seed replaces the base seed's lists
+        assert_eq!(ListMerge::for_id("wfc.cave"), ListMerge::Replace);
+        assert_eq!(ListMerge::for_id("data.monsters"), ListMerge::Append);
+        assert_eq!(
+            ListMerge::for_key("data.sector_recipes", "rules"),
+            ListMerge::Prepend
+        );
+        assert_eq!(
+            ListMerge::for_key("data.sector_recipes", "generators"),
+            ListMerge::Append
+        );
+    }
+
+    #[test]
+    fn overlay_roots() {
+        let tmp = std::env::temp_dir().join(format!("blastoff-overlay-{}", std::process::id()));
+        let base = tmp.join("base");
+        let modded = tmp.join("mod");
+        fs::create_dir_all(base.join("data")).unwrap();
+        fs::create_dir_all(modded.join("data")).unwrap();
+
+        fs::write(base.join("data/monsters.yaml"), "- Kobold\n").unwrap();
+        fs::write(base.join("data/names.txt"), "base").unwrap();
+        fs::write(modded.join("data/monsters.yaml"), "- Goblin\n").unwrap();
+        fs::write(modded.join("data/names.txt"), "mod").unwrap();
+        fs::write(
+            modded.join(MOD_MANIFEST),
+            "name: test mod\nversion: 0.1.0\nload_order: 10\n",
+        )
+        .unwrap();
+
+        let roots = AssetRoots::new(base).with_mod(modded).unwrap();
+        let manifest = roots.iter().last().unwrap().manifest.clone().unwrap();
+        assert_eq!(manifest.name, "test mod");
+        assert_eq!(manifest.load_order, 10);
+
+        let source = roots.into_source().unwrap();
+        let monsters: Vec<String> =
+            serde_yaml::from_slice(source.read("data.monsters", "yaml").unwrap().as_ref()).unwrap();
+        assert_eq!(monsters, vec!["Kobold".to_owned(), "Goblin".to_owned()]);
+        assert_eq!(source.read("data.names", "txt").unwrap().as_ref(), b"mod");
+
+        fs::remove_dir_all(tmp).unwrap();
+    }
+}
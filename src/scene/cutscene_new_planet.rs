@@ -70,12 +70,12 @@ impl Scene<Resources> for CutsceneNewPlanet {
         let planet: Ref<Overworld> = (*self.planet).borrow();
         planet.info().draw(
             PixelPoint::new(pos.x.round() as i32, pos.y.round() as i32),
-            &resources.assets.tileset,
+            &resources.assets().tileset,
             Some(scale),
         );
 
         // Draw text on top
-        resources.assets.monospace_font.draw(
+        resources.assets().monospace_font.draw(
             &format!("You travel to {}", (*self.planet).borrow()),
             PixelPoint::new(5 * TILE_SIZE.width, 5 * TILE_SIZE.height),
             None,
@@ -1,12 +1,15 @@
-//! The Galaxy scene allows players to select a planet to travel to, and then initialize an Overworld Scene to push to the Scene Stack.
+//! The Galaxy scene allows players to select a star system, then a planet or moon in that system to travel to, and then initialize an Overworld Scene to push to the Scene Stack.
 
 use macroquad::prelude::*;
 
 use crate::{
-    galaxy::Galaxy,
+    data::PlanetGenerator,
+    galaxy::{Galaxy, OrbitalBody, StarSystem},
     game::consts::{FONT_SIZE, MAX_PLANET_SPRITE_SIZE, PIXEL_RECT, TILE_SIZE},
-    overworld::PlanetInfo,
-    procgen::{GalaxyGenerator, OverworldProcgenLoader, StaticGalaxy, StaticPlanet},
+    procgen::{
+        pin_landing_site, GalaxyGenerator, NoisePlanet, OverworldProcgenLoader, StaticGalaxy,
+        StaticPlanet,
+    },
     resource::Resources,
     util::{GalaxyPoint, PixelPoint, PixelSpace, PixelVector, Scene, SceneSwitch},
 };
@@ -14,18 +17,27 @@ use crate::{
 // use super::{MenuResult, OverworldMap};
 use super::{MenuResult, OverworldMap};
 
-/// The GalaxyTravel Scene allows players to select a planet for landing
+/// The most items a Carousel shows at once
+const CAROUSEL_SIZE: usize = 5;
+
+/// The GalaxyTravel Scene allows players to select a star system, then a body for landing
 pub struct GalaxyTravel {
     galaxy: Galaxy,
     state: MenuResult<GalaxyPoint>,
+    /// The system view: which star system is open and which body is selected
+    system: Option<(GalaxyPoint, MenuResult<usize>)>,
 }
 
 impl GalaxyTravel {
     pub fn new(galaxy: Galaxy, state: MenuResult<GalaxyPoint>) -> Self {
-        Self { galaxy, state }
+        Self {
+            galaxy,
+            state,
+            system: None,
+        }
     }
 
-    /// Use Resources to procgen a Galaxy of planets
+    /// Use Resources to procgen a Galaxy of star systems
     pub fn create(resources: &mut Resources) -> GalaxyTravel {
         let mut loader = StaticGalaxy {};
         let galaxy = loader.generate(resources);
@@ -33,123 +45,204 @@ impl GalaxyTravel {
         // Initialize selection with whatever's first in the vec
         let state = MenuResult::Unconfirmed {
             selection: galaxy
-                .iter_planet_infos()
-                .map(|(point, _info)| *point)
+                .iter_systems()
+                .map(|(point, _system)| *point)
                 .next()
-                .expect("any planets exist"),
+                .expect("any systems exist"),
         };
 
-        let planet_infos: Vec<&(GalaxyPoint, PlanetInfo)> = galaxy.iter_planet_infos().collect();
-        tracing::info!("Created new galaxy with planets: {:?}", planet_infos);
+        let systems: Vec<&(GalaxyPoint, StarSystem)> = galaxy.iter_systems().collect();
+        tracing::info!("Created new galaxy with systems: {:?}", systems);
 
         Self::new(galaxy, state)
     }
-}
 
-impl Scene<Resources> for GalaxyTravel {
-    fn poll_input(&mut self, _resources: &mut Resources) -> anyhow::Result<()> {
-        let selection = self.state.selection();
-        let planets: Vec<_> = self.galaxy.iter_planet_infos().collect();
+    fn poll_galaxy_input(&mut self) {
+        let selection = *self.state.selection();
+        let points: Vec<GalaxyPoint> = self.galaxy.iter_systems().map(|(p, _)| *p).collect();
+        let idx = points.iter().position(|point| *point == selection).unwrap();
 
-        self.state = if is_key_pressed(KeyCode::Escape) {
+        let selection = if is_key_pressed(KeyCode::Escape) {
             todo!("Return to main menu")
         } else if is_key_pressed(KeyCode::Left) {
-            let idx = planets
-                .iter()
-                .position(|(point, _overworld)| point == selection)
-                .unwrap();
-
-            let (new_selection, _) = planets[(idx + planets.len() - 1) % planets.len()];
-
-            MenuResult::Unconfirmed {
-                selection: *new_selection,
-            }
+            points[(idx + points.len() - 1) % points.len()]
         } else if is_key_pressed(KeyCode::Right) {
-            let idx = planets
-                .iter()
-                .position(|(point, _overworld)| point == selection)
-                .unwrap();
-
-            let (new_selection, _) = planets[(idx + planets.len() + 1) % planets.len()];
-
-            MenuResult::Unconfirmed {
-                selection: *new_selection,
-            }
+            points[(idx + 1) % points.len()]
         } else if is_key_pressed(KeyCode::Enter) {
-            MenuResult::Confirmed {
-                selection: *selection,
-            }
+            // Open the system view
+            self.system = Some((selection, MenuResult::new(0)));
+            selection
         } else {
-            MenuResult::Unconfirmed {
-                selection: *selection,
-            }
+            selection
         };
 
-        Ok(())
+        self.state = MenuResult::Unconfirmed { selection };
     }
 
-    fn update(&mut self, resources: &mut Resources) -> SceneSwitch<Resources> {
-        match self.state {
-            MenuResult::Unconfirmed { selection: _ } => SceneSwitch::None,
-            MenuResult::Confirmed {
-                selection: galaxy_point,
-            } => {
-                if let Some(planet) = self.galaxy.get_planet(&galaxy_point) {
-                    // If the planet already exists, don't recreate it!
-                    SceneSwitch::Push(Box::new(OverworldMap::new(planet)))
-                } else {
-                    // Create the planet when selected
-                    let overworld_gen = StaticPlanet {};
-                    let mut loader = OverworldProcgenLoader::new(overworld_gen, resources);
-                    let planet = self.galaxy.create_planet(&galaxy_point, &mut loader);
-                    SceneSwitch::Push(Box::new(OverworldMap::new(planet)))
-                }
-            }
-        }
+    fn poll_system_input(&mut self, point: GalaxyPoint, body: usize) {
+        let num_bodies = self
+            .galaxy
+            .system(&point)
+            .map(|system| system.bodies().len())
+            .unwrap_or_default()
+            .max(1);
+
+        self.system = if is_key_pressed(KeyCode::Escape) {
+            // Back to the galaxy view
+            None
+        } else if is_key_pressed(KeyCode::Left) {
+            Some((point, MenuResult::new((body + num_bodies - 1) % num_bodies)))
+        } else if is_key_pressed(KeyCode::Right) {
+            Some((point, MenuResult::new((body + 1) % num_bodies)))
+        } else if is_key_pressed(KeyCode::Enter) {
+            Some((point, MenuResult::Confirmed { selection: body }))
+        } else {
+            Some((point, MenuResult::new(body)))
+        };
     }
 
-    fn draw(&mut self, resources: &mut Resources) -> anyhow::Result<()> {
+    fn draw_galaxy(&self, resources: &mut Resources) {
         let selected_point = self.state.selection();
-        let planet_infos: Vec<&(GalaxyPoint, PlanetInfo)> =
-            self.galaxy.iter_planet_infos().collect();
-        let selected_idx = planet_infos
+        let systems: Vec<&(GalaxyPoint, StarSystem)> = self.galaxy.iter_systems().collect();
+        let selected_idx = systems
             .iter()
             .position(|(point, _)| point == selected_point)
             .expect("Selected point should be a point in this galaxy");
 
-        let carousel = Carousel::new(selected_idx, &planet_infos);
-        // TODO: create carousel size constant
-        let visible = carousel.visible(5);
+        let carousel = Carousel::new(selected_idx, &systems);
+        let visible = carousel.visible(carousel_size(systems.len()));
 
-        for (i, (point, planet_info)) in visible.enumerate() {
-            let planetsprite_x_pos = i as f32 * MAX_PLANET_SPRITE_SIZE + 16.;
-            let planet_pixel_point =
-                PixelPoint::new(planetsprite_x_pos as i32, 1 * TILE_SIZE.width);
+        for (i, (point, system)) in visible.enumerate() {
+            let sprite_x_pos = i as f32 * MAX_PLANET_SPRITE_SIZE + 16.;
+            let pixel_point = PixelPoint::new(sprite_x_pos as i32, TILE_SIZE.width);
 
             if selected_point == point {
-                // TODO: center this on the bottom
-                let text_origin = PixelPoint::new(
-                    PIXEL_RECT.center().x / 2,
-                    PIXEL_RECT.center().y + (PIXEL_RECT.center().y / 2),
+                let lanes = self.galaxy.neighbors(point).len();
+                resources.assets().monospace_font.draw(
+                    &format!("{} at {:?}, {} lanes", system, *point, lanes),
+                    text_origin(),
+                    None,
+                    None,
                 );
-                resources.assets.monospace_font.draw(
-                    &format!("{} at {:?}", planet_info, *point),
-                    text_origin,
+
+                system.draw(pixel_point, &resources.assets().tileset, Some(2.));
+            } else {
+                system.draw(pixel_point, &resources.assets().tileset, Some(0.5));
+            }
+        }
+    }
+
+    fn draw_system(&self, resources: &mut Resources, point: GalaxyPoint, selected: usize) {
+        let Some(system) = self.galaxy.system(&point) else {
+            return;
+        };
+        let bodies: Vec<(usize, &OrbitalBody)> = system.bodies().iter().enumerate().collect();
+        if bodies.is_empty() {
+            return;
+        }
+
+        let carousel = Carousel::new(selected, &bodies);
+        let visible = carousel.visible(carousel_size(bodies.len()));
+
+        for (i, (idx, body)) in visible.enumerate() {
+            let planetsprite_x_pos = i as f32 * MAX_PLANET_SPRITE_SIZE + 16.;
+            let planet_pixel_point = PixelPoint::new(planetsprite_x_pos as i32, TILE_SIZE.width);
+
+            if *idx == selected {
+                resources.assets().monospace_font.draw(
+                    &format!(
+                        "{} - {:?} orbit at {:.2} AU",
+                        body.info, body.zone, body.orbit
+                    ),
+                    text_origin(),
                     None,
                     None,
                 );
 
                 // TODO: this looks ugly
                 resources
-                    .assets
+                    .assets()
                     .tileset
                     .spr_flip_x(1063, PIXEL_RECT.center()); // rocket pic
 
-                planet_info.draw(planet_pixel_point, &resources.assets.tileset, Some(2.));
+                body.info
+                    .draw(planet_pixel_point, &resources.assets().tileset, Some(2.));
             } else {
-                planet_info.draw(planet_pixel_point, &resources.assets.tileset, Some(0.5));
+                body.info
+                    .draw(planet_pixel_point, &resources.assets().tileset, Some(0.5));
             }
         }
+    }
+}
+
+/// Carousels need an odd number of items to have a center
+fn carousel_size(len: usize) -> usize {
+    let size = len.min(CAROUSEL_SIZE);
+    if size > 0 && size.is_multiple_of(2) {
+        size - 1
+    } else {
+        size
+    }
+}
+
+fn text_origin() -> PixelPoint {
+    // TODO: center this on the bottom
+    PixelPoint::new(
+        PIXEL_RECT.center().x / 2,
+        PIXEL_RECT.center().y + (PIXEL_RECT.center().y / 2),
+    )
+}
+
+impl Scene<Resources> for GalaxyTravel {
+    fn poll_input(&mut self, _resources: &mut Resources) -> anyhow::Result<()> {
+        match self.system {
+            Some((point, body)) => self.poll_system_input(point, *body.selection()),
+            None => self.poll_galaxy_input(),
+        }
+
+        Ok(())
+    }
+
+    fn update(&mut self, resources: &mut Resources) -> SceneSwitch<Resources> {
+        let Some((galaxy_point, MenuResult::Confirmed { selection: body })) = self.system else {
+            return SceneSwitch::None;
+        };
+
+        if let Some(planet) = self.galaxy.get_planet(&galaxy_point, body) {
+            // If the planet already exists, don't recreate it!
+            return SceneSwitch::Push(Box::new(OverworldMap::new(planet)));
+        }
+
+        // The first planet visited starts at the hand-crafted landing site
+        let first_planet = self.galaxy.iter_planets().next().is_none();
+
+        // Create the planet when selected, using the planet's generator
+        let generator = self
+            .galaxy
+            .find(&galaxy_point, body)
+            .map(|info| info.generator)
+            .unwrap_or_default();
+        let planet = match generator {
+            PlanetGenerator::Static => {
+                let mut loader = OverworldProcgenLoader::new(StaticPlanet {}, resources);
+                self.galaxy.create_planet(&galaxy_point, body, &mut loader)
+            }
+            PlanetGenerator::Noise => {
+                let mut loader = OverworldProcgenLoader::new(NoisePlanet {}, resources);
+                self.galaxy.create_planet(&galaxy_point, body, &mut loader)
+            }
+        };
+        if first_planet {
+            pin_landing_site(&mut planet.borrow_mut(), resources);
+        }
+        SceneSwitch::Push(Box::new(OverworldMap::new(planet)))
+    }
+
+    fn draw(&mut self, resources: &mut Resources) -> anyhow::Result<()> {
+        match self.system {
+            Some((point, body)) => self.draw_system(resources, point, *body.selection()),
+            None => self.draw_galaxy(resources),
+        }
 
         Ok(())
     }
@@ -112,7 +112,7 @@ impl Scene<Resources> for MainMenu {
 
         let title_point = PixelPoint::new(PIXEL_RECT.center().x / 2, PIXEL_RECT.center().y / 2);
         resources
-            .assets
+            .assets()
             .monospace_font
             .draw(TITLE_HEADER, title_point, None, None);
 
@@ -122,14 +122,14 @@ impl Scene<Resources> for MainMenu {
 
             let point = PixelPoint::new(
                 (PIXEL_RECT.center().x as f32 / 2.) as i32,
-                (resources.assets.monospace_font.char_size.height * i as i32)
+                (resources.assets().monospace_font.char_size.height * i as i32)
                     + PIXEL_RECT.center().y
                     + (PIXEL_RECT.center().y / 2),
             );
 
             // TODO: make it possible to pass in color for monospace_font
             resources
-                .assets
+                .assets()
                 .monospace_font
                 .draw(&entry.to_string(), point, Some(color), None);
         }
@@ -4,20 +4,15 @@ use macroquad::prelude::*;
 
 use crate::{
     game::consts::{
-        get_screen_to_pixel_transform_float, SCREEN_RECT, SECTOR_SIZE, VIEWPORT_HEIGHT,
-        VIEWPORT_WIDTH,
+        get_screen_to_pixel_transform_float, SCREEN_RECT, VIEWPORT_HEIGHT, VIEWPORT_WIDTH,
     },
     overworld::Overworld,
-    procgen::{
-        seed::{self},
-        Combo, MapTemplate, SectorProcgenLoader, SubMap, WfcGen,
-    },
+    procgen::{sector_generator, SectorProcgenLoader},
     resource::{Resources, Viewport},
-    sector::{FloorKind, Tile},
     util::{
         OverworldFloatPoint, OverworldPoint, OverworldSpace, OverworldToViewport, OverworldVector,
         PixelPoint, Scene, SceneSwitch, ScreenFloatPoint, TransformExt, ViewportFloatPoint,
-        ViewportFloatToScreen, ViewportPoint, ViewportRect, ViewportSize, WorldPoint, WorldSize,
+        ViewportFloatToScreen, ViewportPoint, ViewportRect, ViewportSize,
     },
 };
 
@@ -129,25 +124,23 @@ impl Scene<Resources> for OverworldMap {
                         OverworldMapInput::MoveE => self.move_player(OverworldVector::new(1, 0)),
                         OverworldMapInput::MoveW => self.move_player(OverworldVector::new(-1, 0)),
                         OverworldMapInput::Activate => {
-                            // TODO: this logic needs to move somewhere else (like how we had it in LoadingScreen)
-                            let mapgen = Combo::new(MapTemplate::new(
-                                SECTOR_SIZE,
-                                Tile::Floor(FloorKind::FloorScenery('~')),
-                                vec![
-                                    // First create an entire map of craters
-                                    SubMap::new(
-                                        Box::new(WfcGen::new(seed::CRATERS)),
-                                        SECTOR_SIZE,
-                                        WorldPoint::new(0, 0),
-                                    ),
-                                    // Then, create a city in the middle
-                                    SubMap::new(
-                                        Box::new(WfcGen::new(seed::CITY)),
-                                        WorldSize::new(50, 50),
-                                        WorldPoint::new(25, 25),
-                                    ),
-                                ],
-                            ));
+                            if let Some(sector) =
+                                (*self.planet).borrow().get_sector(&self.player_position)
+                            {
+                                return SceneSwitch::Push(Box::new(Sector::new(sector)));
+                            }
+
+                            // Pick the generator from the sector's tile, type and planet
+                            let mapgen = {
+                                let planet = (*self.planet).borrow();
+                                let Some(sector_info) =
+                                    planet.get_sector_info(&self.player_position)
+                                else {
+                                    return SceneSwitch::None;
+                                };
+                                let seed = planet.info().seed.sector(self.player_position);
+                                sector_generator(sector_info, seed, resources)
+                            };
 
                             // TODO: this isn't plumbed correctly
                             // TODO: Make history optional so that it's only used for the debug view
@@ -157,12 +150,9 @@ impl Scene<Resources> for OverworldMap {
                             let mut loader =
                                 SectorProcgenLoader::new(mapgen, resources, &mut history);
 
-                            let sector = {
-                                let mut planet = (*self.planet).borrow_mut();
-                                planet.get_sector(&self.player_position).unwrap_or_else(|| {
-                                    planet.create_sector(&self.player_position, &mut loader)
-                                })
-                            };
+                            let sector = (*self.planet)
+                                .borrow_mut()
+                                .create_sector(&self.player_position, &mut loader);
 
                             return SceneSwitch::Push(Box::new(Sector::new(sector)));
                         }
@@ -174,7 +164,7 @@ impl Scene<Resources> for OverworldMap {
     }
 
     fn draw(&mut self, resources: &mut Resources) -> anyhow::Result<()> {
-        resources.assets.monospace_font.draw(
+        resources.assets().monospace_font.draw(
             &format!("{}", (*self.planet).borrow()),
             PixelPoint::new(0, 0),
             None,
@@ -189,13 +179,13 @@ impl Scene<Resources> for OverworldMap {
 
         // player
         resources
-            .assets
+            .assets()
             .tileset
             // TODO: remove hardcoded sprite
             .spr(469, self.overworld_to_pixel(self.player_position.to_f32()));
 
         // Coordinate debugging
-        resources.assets.monospace_font.draw(
+        resources.assets().monospace_font.draw(
             &format!(
                 "{:?} {:?}",
                 self.player_position,
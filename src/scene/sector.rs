@@ -5,17 +5,20 @@
 //! overworld `Space` activation flow. The older ECS turn scheduler can be wired
 //! back in once the rest of the game loop has been ported off the old ggez API.
 
-use std::{cell::RefCell, rc::Rc};
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
 
 use macroquad::prelude::{is_key_pressed, Color, KeyCode, GRAY, WHITE};
 
 use crate::{
-    color::{COMMON, FIRE, PLANT, WATER},
-    component::{Player, Position},
-    game::consts::{SCREEN_HEIGHT_PIXELS, SCREEN_WIDTH_PIXELS},
+    color::{COMMON, FIRE},
+    component::{Door, Item, Key, Locked, Player, Position},
+    game::{
+        consts::{SCREEN_HEIGHT_PIXELS, SCREEN_WIDTH_PIXELS},
+        is_blocked_by_entity, open_doors, pick_up_keys,
+    },
     overworld::SectorData,
     resource::Resources,
-    sector::{FloorKind, Tile, WallKind},
+    sector::{tile_color, tile_sprite},
     util::{PixelPoint, Scene, SceneSwitch, WorldPoint, WorldVector},
 };
 
@@ -30,37 +33,6 @@ enum SectorInput {
     Exit,
 }
 
-fn tile_sprite(tile: Tile) -> u32 {
-    match tile {
-        Tile::Floor(FloorKind::FloorDefault) => 6,
-        Tile::Floor(FloorKind::FloorInterior) => 755,
-        Tile::Floor(FloorKind::FloorScenery('~')) => 253,
-        Tile::Floor(FloorKind::FloorScenery(',')) => 6,
-        Tile::Floor(FloorKind::FloorScenery('*')) => 554,
-        Tile::Floor(FloorKind::FloorScenery('░')) => 7,
-        Tile::Floor(FloorKind::FloorScenery('▒')) => 8,
-        Tile::Floor(FloorKind::FloorScenery('▓')) => 9,
-        Tile::Floor(FloorKind::FloorScenery(_)) => 6,
-        Tile::Wall(WallKind::WallPillar) => 393,
-        Tile::Wall(_) => 790,
-    }
-}
-
-fn tile_color(tile: Tile) -> Color {
-    match tile {
-        Tile::Floor(FloorKind::FloorDefault) => COMMON.two,
-        Tile::Floor(FloorKind::FloorInterior) => COMMON.four,
-        Tile::Floor(FloorKind::FloorScenery('~')) => WATER.three,
-        Tile::Floor(FloorKind::FloorScenery(',')) => COMMON.three,
-        Tile::Floor(FloorKind::FloorScenery('*')) => FIRE.two,
-        Tile::Floor(FloorKind::FloorScenery('░')) => COMMON.two,
-        Tile::Floor(FloorKind::FloorScenery('▒')) => COMMON.three,
-        Tile::Floor(FloorKind::FloorScenery('▓')) => COMMON.four,
-        Tile::Floor(FloorKind::FloorScenery(_)) => PLANT.three,
-        Tile::Wall(_) => COMMON.five,
-    }
-}
-
 pub struct Sector {
     data: Rc<RefCell<SectorData>>,
     input: Option<SectorInput>,
@@ -83,23 +55,30 @@ impl Sector {
 
     fn move_player(&mut self, delta: WorldVector) {
         let mut data = self.data.borrow_mut();
-        let Some(current) = data
+        let Some((player, current)) = data
             .world
             .query::<(&Player, &Position)>()
             .iter()
             .next()
-            .map(|(_, (_, position))| position.grid_point())
+            .map(|(entity, (_, position))| (entity, position.grid_point()))
         else {
             tracing::warn!("Sector scene has no player entity to move");
             return;
         };
 
         let next = current + delta;
+
+        // Bumping into a door opens it, if the player has its key
+        if open_doors(&mut data.world, player, next) {
+            return;
+        }
+
         let can_move = data
             .map
             .get(next)
             .map(|tile| tile.is_passable())
-            .unwrap_or(false);
+            .unwrap_or(false)
+            && !is_blocked_by_entity(&data.world, next);
 
         if !can_move {
             tracing::debug!(?current, ?next, "Blocked sector move");
@@ -109,6 +88,7 @@ impl Sector {
         for (_, (_, position)) in data.world.query_mut::<(&Player, &mut Position)>() {
             position.set_grid_point(next);
         }
+        pick_up_keys(&mut data.world, player, next);
     }
 }
 
@@ -163,13 +143,13 @@ impl Scene<Resources> for Sector {
             WorldPoint::new(0, 0)
         };
 
-        resources.assets.monospace_font.draw(
+        resources.assets().monospace_font.draw(
             "Sector view: arrows move, Esc returns to overworld",
             PixelPoint::new(0, 0),
             Some(WHITE),
             None,
         );
-        resources.assets.monospace_font.draw(
+        resources.assets().monospace_font.draw(
             &format!(
                 "map={}x{} origin={:?} player={:?}",
                 map_width, map_height, origin, player_position
@@ -179,6 +159,8 @@ impl Scene<Resources> for Sector {
             None,
         );
 
+        let glyphs = entity_glyphs(&data.world);
+
         for screen_y in 0..view_height {
             for screen_x in 0..view_width {
                 let map_point = WorldPoint::new(origin.x + screen_x, origin.y + screen_y);
@@ -188,7 +170,7 @@ impl Scene<Resources> for Sector {
                 );
 
                 if let Some(tile) = map.get(map_point) {
-                    resources.assets.tileset.draw(
+                    resources.assets().tileset.draw(
                         tile_sprite(*tile),
                         pixel_point,
                         Some(tile_color(*tile)),
@@ -197,8 +179,17 @@ impl Scene<Resources> for Sector {
                     );
                 }
 
+                if let Some((glyph, color)) = glyphs.get(&map_point) {
+                    resources.assets().monospace_font.draw(
+                        &glyph.to_string(),
+                        pixel_point,
+                        Some(*color),
+                        None,
+                    );
+                }
+
                 if Some(map_point) == player_position {
-                    resources.assets.tileset.draw(
+                    resources.assets().tileset.draw(
                         PLAYER_SPRITE,
                         pixel_point,
                         Some(WHITE),
@@ -212,3 +203,30 @@ impl Scene<Resources> for Sector {
         Ok(())
     }
 }
+
+/// Glyphs for doors and items until they get sprites. Items are drawn over
+/// doors.
+fn entity_glyphs(world: &hecs::World) -> HashMap<WorldPoint, (char, Color)> {
+    let mut glyphs = HashMap::new();
+    for (_, (position, door, locked)) in world.query::<(&Position, &Door, Option<&Locked>)>().iter()
+    {
+        let glyph = match (door.opened, locked) {
+            (true, _) => ('\'', COMMON.four),
+            (false, Some(_)) => ('+', FIRE.four),
+            (false, None) => ('+', COMMON.four),
+        };
+        glyphs.insert(position.grid_point(), glyph);
+    }
+    for (_, (position, key)) in world
+        .query::<(&Position, Option<&Key>)>()
+        .with::<&Item>()
+        .iter()
+    {
+        let glyph = match key {
+            Some(_) => ('-', FIRE.two),
+            None => ('!', WHITE),
+        };
+        glyphs.insert(position.grid_point(), glyph);
+    }
+    glyphs
+}
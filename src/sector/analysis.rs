@@ -0,0 +1,242 @@
+//! Numbers that describe a map's shape, for comparing generators and catching
+//! regressions without looking at every map
+use std::{collections::VecDeque, fmt};
+
+use bracket_random::prelude::RandomNumberGenerator;
+
+use crate::util::{PointExt, WorldPoint, WorldVector};
+
+use super::{Map, DIRECTIONS};
+
+/// Random pairs of tiles [`MapMetrics::path_length`] is averaged over
+const PATH_SAMPLES: usize = 20;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct MapMetrics {
+    /// Share of the map that's passable
+    pub floor_ratio: f32,
+    pub regions: usize,
+    /// Share of the passable tiles in the largest region
+    pub largest_region: f32,
+    /// Passable tiles with a single passable tile next to them
+    pub dead_ends: usize,
+    /// Tiles in one tile wide corridors per tile in open areas
+    pub corridor_ratio: f32,
+    /// Average steps between random pairs of tiles in the largest region
+    pub path_length: f32,
+    /// Whether a generator gave up while making the map. Maps don't know
+    /// this themselves, the caller sets it from
+    /// [`Resources::mapgen_fallbacks`](crate::resource::Resources::mapgen_fallbacks)
+    pub fallback: bool,
+}
+
+impl MapMetrics {
+    pub fn measure(map: &Map, rng: &mut RandomNumberGenerator) -> Self {
+        let passable = |point: WorldPoint| map.get(point).is_some_and(|tile| tile.is_passable());
+        let floors: Vec<WorldPoint> = map.iter_points().filter(|p| passable(*p)).collect();
+        let regions = map.regions();
+
+        let orthogonal = [(0, 1), (0, -1), (1, 0), (-1, 0)].map(|(x, y)| WorldVector::new(x, y));
+        let dead_ends = floors
+            .iter()
+            .filter(|point| {
+                orthogonal
+                    .iter()
+                    .filter(|offset| passable(**point + **offset))
+                    .count()
+                    == 1
+            })
+            .count();
+
+        // Open areas are anything that's part of a 2x2 square of floor
+        let in_square = |point: WorldPoint| {
+            [(-1, -1), (0, -1), (-1, 0), (0, 0)].iter().any(|(x, y)| {
+                let corner = point + WorldVector::new(*x, *y);
+                [(0, 0), (1, 0), (0, 1), (1, 1)]
+                    .iter()
+                    .all(|(dx, dy)| passable(corner + WorldVector::new(*dx, *dy)))
+            })
+        };
+        let open = floors.iter().filter(|point| in_square(**point)).count();
+        let corridors = floors.len() - open;
+
+        let path_length = match regions.largest() {
+            Some(largest) => {
+                let points: Vec<WorldPoint> = regions.points(largest).collect();
+                let total: usize = (0..PATH_SAMPLES)
+                    .filter_map(|_| {
+                        let start = points[rng.range(0, points.len())];
+                        let end = points[rng.range(0, points.len())];
+                        steps(map, start, end)
+                    })
+                    .sum();
+                total as f32 / PATH_SAMPLES as f32
+            }
+            None => 0.,
+        };
+
+        Self {
+            floor_ratio: floors.len() as f32 / map.get_rect().area() as f32,
+            regions: regions.len(),
+            largest_region: regions.largest().map_or(0., |largest| {
+                regions.size(largest) as f32 / floors.len() as f32
+            }),
+            dead_ends,
+            corridor_ratio: corridors as f32 / open.max(1) as f32,
+            path_length,
+            fallback: false,
+        }
+    }
+}
+
+/// Fewest steps between two passable tiles, moving the same way regions spread
+fn steps(map: &Map, start: WorldPoint, end: WorldPoint) -> Option<usize> {
+    let width = map.get_width();
+    let mut distances: Vec<Option<usize>> = vec![None; map.get_rect().area() as usize];
+    let mut queue = VecDeque::from([start]);
+    distances[start.to_index(width)] = Some(0);
+
+    while let Some(point) = queue.pop_front() {
+        let distance = distances[point.to_index(width)]?;
+        if point == end {
+            return Some(distance);
+        }
+        for cardinal in DIRECTIONS.iter() {
+            let next = point + *cardinal.to_vector();
+            let passable = map.get(next).is_some_and(|tile| tile.is_passable());
+            if passable && distances[next.to_index(width)].is_none() {
+                distances[next.to_index(width)] = Some(distance + 1);
+                queue.push_back(next);
+            }
+        }
+    }
+    None
+}
+
+/// Reads one number out of [`MapMetrics`]
+type Metric = fn(&MapMetrics) -> f32;
+
+/// [`MapMetrics`] for many maps from one generator
+#[derive(Debug, Clone)]
+pub struct MapReport {
+    pub generator: String,
+    pub maps: Vec<MapMetrics>,
+}
+
+impl MapReport {
+    pub fn new(generator: &str) -> Self {
+        Self {
+            generator: generator.to_owned(),
+            maps: Vec::new(),
+        }
+    }
+
+    pub fn add(&mut self, metrics: MapMetrics) {
+        self.maps.push(metrics);
+    }
+
+    /// Share of the maps the generator gave up on
+    pub fn fallback_rate(&self) -> f32 {
+        let fallbacks = self.maps.iter().filter(|metrics| metrics.fallback).count();
+        fallbacks as f32 / self.maps.len().max(1) as f32
+    }
+
+    /// Sorted values of one metric across every map
+    fn values(&self, metric: Metric) -> Vec<f32> {
+        let mut values: Vec<f32> = self.maps.iter().map(metric).collect();
+        values.sort_by(f32::total_cmp);
+        values
+    }
+}
+
+impl fmt::Display for MapReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} x{}", self.generator, self.maps.len())?;
+        if self.maps.is_empty() {
+            return Ok(());
+        }
+
+        writeln!(
+            f,
+            "  {:<16} {:>8} {:>8} {:>8} {:>8} {:>8}",
+            "", "min", "p10", "median", "p90", "max"
+        )?;
+        let rows: [(&str, Metric); 6] = [
+            ("floor ratio", |m| m.floor_ratio),
+            ("regions", |m| m.regions as f32),
+            ("largest region", |m| m.largest_region),
+            ("dead ends", |m| m.dead_ends as f32),
+            ("corridor ratio", |m| m.corridor_ratio),
+            ("path length", |m| m.path_length),
+        ];
+        for (name, metric) in rows {
+            let values = self.values(metric);
+            let percentile = |p: usize| values[(values.len() - 1) * p / 100];
+            writeln!(
+                f,
+                "  {:<16} {:>8.2} {:>8.2} {:>8.2} {:>8.2} {:>8.2}",
+                name,
+                percentile(0),
+                percentile(10),
+                percentile(50),
+                percentile(90),
+                percentile(100)
+            )?;
+        }
+        writeln!(
+            f,
+            "  {:<16} {:>7.2}%",
+            "fallbacks",
+            100. * self.fallback_rate()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        sector::{FloorKind, Tile, WallKind},
+        util::WorldSize,
+    };
+
+    use super::*;
+
+    #[test]
+    fn measures_rooms_and_corridors() {
+        // A 3x3 room with a corridor running east to a dead end, and a
+        // single tile cut off from both
+        let mut map = Map::init(
+            "test".into(),
+            WorldSize::new(12, 7),
+            Tile::Wall(WallKind::default()),
+        );
+        for x in 1..4 {
+            for y in 1..4 {
+                map[&WorldPoint::new(x, y)] = Tile::Floor(FloorKind::FloorDefault);
+            }
+        }
+        for x in 4..10 {
+            map[&WorldPoint::new(x, 2)] = Tile::Floor(FloorKind::FloorDefault);
+        }
+        map[&WorldPoint::new(10, 5)] = Tile::Floor(FloorKind::FloorDefault);
+
+        let metrics = MapMetrics::measure(&map, &mut RandomNumberGenerator::seeded(3));
+        assert_eq!(metrics.floor_ratio, 16. / 84.);
+        assert_eq!(metrics.regions, 2);
+        assert_eq!(metrics.largest_region, 15. / 16.);
+        assert_eq!(metrics.dead_ends, 1);
+        // The cut off tile isn't part of an open area either
+        assert_eq!(metrics.corridor_ratio, 7. / 9.);
+        assert!(metrics.path_length > 0. && metrics.path_length <= 8.);
+        assert!(!metrics.fallback);
+
+        assert_eq!(
+            steps(&map, WorldPoint::new(1, 1), WorldPoint::new(9, 2)),
+            Some(8)
+        );
+        assert_eq!(
+            steps(&map, WorldPoint::new(1, 1), WorldPoint::new(10, 5)),
+            None
+        );
+    }
+}
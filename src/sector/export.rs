@@ -0,0 +1,250 @@
+//! Saving maps to look at outside the game: plain text, Tiled `.tmx` maps and
+//! PNG images. Images are drawn on the CPU, so none of these need a window.
+use std::{fmt::Write, io::Read, path::Path};
+
+use assets_manager::AssetCache;
+use image::{Rgba, RgbaImage};
+
+use crate::{resource::read_asset_path, util::WorldPoint};
+
+use super::{tile_color, tile_sprite, Map};
+
+/// The tileset exported maps are drawn with, relative to the assets directory
+pub const TILESET_PATH: &str = "tileset/tileset_transparent.tsx";
+
+/// Pixels per tile in the tileset
+const TILE_SIZE: u32 = 16;
+
+/// One line of [`Tile::glyph`](super::Tile::glyph)s per row
+pub fn to_ascii(map: &Map) -> String {
+    let mut ascii = String::new();
+    for y in 0..map.get_height() {
+        ascii.extend((0..map.get_width()).map(|x| map[&WorldPoint::new(x, y)].glyph()));
+        ascii.push('\n');
+    }
+    ascii
+}
+
+/// A Tiled map with a single tile layer. `tileset_source` is the path to
+/// [`TILESET_PATH`], either absolute or relative to where the map is saved.
+pub fn to_tmx(map: &Map, tileset_source: &str) -> String {
+    let width = map.get_width();
+    let height = map.get_height();
+
+    let rows: Vec<String> = (0..height)
+        .map(|y| {
+            (0..width)
+                .map(|x| {
+                    // Global tile ids start at the tileset's firstgid
+                    let tile = map[&WorldPoint::new(x, y)];
+                    (tile_sprite(tile) + 1).to_string()
+                })
+                .collect::<Vec<_>>()
+                .join(",")
+        })
+        .collect();
+
+    let mut tmx = String::new();
+    let _ = writeln!(tmx, r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+    let _ = writeln!(
+        tmx,
+        r#"<map version="1.9" orientation="orthogonal" renderorder="right-down" width="{}" height="{}" tilewidth="{}" tileheight="{}" infinite="0" nextlayerid="2" nextobjectid="1">"#,
+        width, height, TILE_SIZE, TILE_SIZE
+    );
+    let _ = writeln!(
+        tmx,
+        r#" <tileset firstgid="1" source="{}"/>"#,
+        escape(tileset_source)
+    );
+    let _ = writeln!(
+        tmx,
+        r#" <layer id="1" name="{}" width="{}" height="{}">"#,
+        escape(map.get_name()),
+        width,
+        height
+    );
+    let _ = writeln!(tmx, r#"  <data encoding="csv">"#);
+    let _ = writeln!(tmx, "{}", rows.join(",\n"));
+    let _ = writeln!(tmx, "</data>");
+    let _ = writeln!(tmx, " </layer>");
+    let _ = writeln!(tmx, "</map>");
+    tmx
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// The tileset's sprite sheet, loaded for drawing on the CPU
+pub struct TilesetImage {
+    columns: u32,
+    margin: u32,
+    spacing: u32,
+    image: RgbaImage,
+}
+
+impl TilesetImage {
+    /// Load [`TILESET_PATH`] and its image through the asset cache
+    pub fn load(cache: &AssetCache) -> anyhow::Result<Self> {
+        let mut loader = tiled::Loader::with_reader(|path: &Path| read_asset_path(cache, path));
+        let tileset = loader.load_tsx_tileset(TILESET_PATH)?;
+        if tileset.tile_width != TILE_SIZE || tileset.tile_height != TILE_SIZE {
+            anyhow::bail!(
+                "Expected {}x{} tiles in {}",
+                TILE_SIZE,
+                TILE_SIZE,
+                TILESET_PATH
+            );
+        }
+        let source = &tileset
+            .image
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Expected an image for the tileset"))?
+            .source;
+
+        let mut bytes = Vec::new();
+        read_asset_path(cache, source)?.read_to_end(&mut bytes)?;
+        let image = image::load_from_memory(&bytes)?.to_rgba8();
+
+        Ok(Self {
+            columns: tileset.columns,
+            margin: tileset.margin,
+            spacing: tileset.spacing,
+            image,
+        })
+    }
+
+    /// Draw a sprite with its top left corner at (x, y), multiplying its
+    /// colors by `tint` and blending it over what's already there
+    fn draw(&self, canvas: &mut RgbaImage, sprite: u32, x: u32, y: u32, tint: [f32; 4]) {
+        let sx = (sprite % self.columns) * (TILE_SIZE + self.spacing) + self.margin;
+        let sy = (sprite / self.columns) * (TILE_SIZE + self.spacing) + self.margin;
+
+        for dy in 0..TILE_SIZE {
+            for dx in 0..TILE_SIZE {
+                let Some(src) = self.image.get_pixel_checked(sx + dx, sy + dy) else {
+                    continue;
+                };
+                let alpha = src[3] as f32 / 255. * tint[3];
+                let dest = canvas.get_pixel_mut(x + dx, y + dy);
+                for channel in 0..3 {
+                    let color = src[channel] as f32 * tint[channel];
+                    dest[channel] = (color * alpha + dest[channel] as f32 * (1. - alpha)) as u8;
+                }
+            }
+        }
+    }
+}
+
+/// Draw every tile's sprite tinted with [`tile_color`], like the sector view
+/// does, on a black background
+pub fn to_image(map: &Map, tileset: &TilesetImage) -> RgbaImage {
+    let mut canvas = RgbaImage::from_pixel(
+        map.get_width() as u32 * TILE_SIZE,
+        map.get_height() as u32 * TILE_SIZE,
+        Rgba([0, 0, 0, 255]),
+    );
+    for (point, tile) in map.iter_tiles() {
+        let color = tile_color(*tile);
+        tileset.draw(
+            &mut canvas,
+            tile_sprite(*tile),
+            point.x as u32 * TILE_SIZE,
+            point.y as u32 * TILE_SIZE,
+            [color.r, color.g, color.b, color.a],
+        );
+    }
+    canvas
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io, path};
+
+    use tiled::Loader;
+
+    use crate::{
+        procgen::{TiledMap, TiledTiles},
+        resource::test_asset_cache,
+        sector::{FloorKind, Tile, WallKind},
+        util::WorldSize,
+    };
+
+    use super::*;
+
+    fn test_map() -> Map {
+        let mut map = Map::init(
+            "export".into(),
+            WorldSize::new(6, 4),
+            Tile::Floor(FloorKind::FloorDefault),
+        );
+        map[&WorldPoint::new(1, 1)] = Tile::Wall(WallKind::WallDefault);
+        map[&WorldPoint::new(2, 1)] = Tile::Floor(FloorKind::FloorScenery('~'));
+        map[&WorldPoint::new(3, 2)] = Tile::Floor(FloorKind::FloorInterior);
+        map
+    }
+
+    #[test]
+    fn ascii_round_trip() {
+        let map = test_map();
+        let ascii = to_ascii(&map);
+        assert_eq!(ascii.lines().count(), 4);
+        assert_eq!(ascii.lines().nth(1), Some(".#~..."));
+
+        for (y, line) in ascii.lines().enumerate() {
+            for (x, c) in line.chars().enumerate() {
+                let point = WorldPoint::new(x as i32, y as i32);
+                assert_eq!(Tile::from_char(c), Some(map[&point]), "{:?}", point);
+            }
+        }
+    }
+
+    #[test]
+    fn tmx_round_trip() {
+        let cache = test_asset_cache();
+        let map = test_map();
+        let tmx = to_tmx(&map, "../tileset/tileset_transparent.tsx");
+
+        // Read it back like a map saved to assets/maps
+        let mut loader = Loader::with_reader(|path: &path::Path| {
+            if path == path::Path::new("maps/export.tmx") {
+                Ok(io::Cursor::new(tmx.clone().into_bytes()))
+            } else {
+                read_asset_path(&cache, path)
+            }
+        });
+        let loaded = loader.load_tmx_map("maps/export.tmx").unwrap();
+        let tiles = cache.load::<TiledTiles>("maps.tiles").unwrap().read();
+        let imported = TiledMap::from_tmx("export", &loaded, &tiles).unwrap();
+
+        assert_eq!(imported.map().get_rect(), map.get_rect());
+        for (point, tile) in map.iter_tiles() {
+            assert_eq!(imported.map()[&point], *tile, "{:?}", point);
+        }
+    }
+
+    #[test]
+    fn renders_png() {
+        let cache = test_asset_cache();
+        let tileset = TilesetImage::load(&cache).unwrap();
+        let map = test_map();
+        let image = to_image(&map, &tileset);
+
+        assert_eq!(image.dimensions(), (6 * TILE_SIZE, 4 * TILE_SIZE));
+
+        // Tinted sprites show up in their tile's color
+        let wall = tile_color(Tile::Wall(WallKind::WallDefault));
+        let brightest = |x: u32, y: u32| {
+            (0..TILE_SIZE)
+                .flat_map(|dy| (0..TILE_SIZE).map(move |dx| (dx, dy)))
+                .map(|(dx, dy)| image.get_pixel(x * TILE_SIZE + dx, y * TILE_SIZE + dy)[2])
+                .max()
+                .unwrap()
+        };
+        assert!(brightest(1, 1).abs_diff((wall.b * 255.) as u8) < 10);
+        assert!(brightest(1, 1) > brightest(0, 0));
+    }
+}
@@ -0,0 +1,262 @@
+//! The rooms and corridors a generator laid out, for anything that needs to
+//! reason about a sector's structure rather than its individual tiles
+use std::collections::VecDeque;
+
+use serde::{Deserialize, Serialize};
+
+use crate::util::{WorldPoint, WorldRect, WorldVector};
+
+/// A corridor between two rooms, not counting the tiles inside either room
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Corridor {
+    pub rooms: (usize, usize),
+    pub path: Vec<WorldPoint>,
+}
+
+impl Corridor {
+    pub fn len(&self) -> usize {
+        self.path.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.path.is_empty()
+    }
+
+    /// The room at the other end from `room`
+    pub fn other(&self, room: usize) -> usize {
+        if self.rooms.0 == room {
+            self.rooms.1
+        } else {
+            self.rooms.0
+        }
+    }
+}
+
+/// Where a corridor meets a room: the corridor tile right outside it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Doorway {
+    pub point: WorldPoint,
+    pub room: usize,
+    pub corridor: usize,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MapLayout {
+    pub rooms: Vec<WorldRect>,
+    pub corridors: Vec<Corridor>,
+    pub doors: Vec<Doorway>,
+    /// The room the player starts in
+    entrance: usize,
+    /// Corridors between each room and the entrance
+    distances: Vec<Option<usize>>,
+}
+
+impl MapLayout {
+    pub fn new(rooms: Vec<WorldRect>) -> Self {
+        let mut layout = Self {
+            rooms,
+            ..Default::default()
+        };
+        layout.update_distances();
+        layout
+    }
+
+    /// The room containing a point, if any
+    pub fn room_at(&self, point: WorldPoint) -> Option<usize> {
+        self.rooms.iter().position(|room| room.contains(point))
+    }
+
+    /// Record a dug path. It's split wherever it passes through a room, so a
+    /// path from A to B that cuts through C connects A to C and C to B.
+    pub fn add_path(&mut self, path: &[WorldPoint]) {
+        let mut last_room: Option<usize> = None;
+        let mut outside: Vec<WorldPoint> = Vec::new();
+
+        for point in path {
+            match self.room_at(*point) {
+                Some(room) => {
+                    if let Some(from) = last_room.filter(|from| *from != room) {
+                        self.add_corridor(from, room, std::mem::take(&mut outside));
+                    }
+                    outside.clear();
+                    last_room = Some(room);
+                }
+                None => outside.push(*point),
+            }
+        }
+    }
+
+    fn add_corridor(&mut self, from: usize, to: usize, path: Vec<WorldPoint>) {
+        let corridor = self.corridors.len();
+        if let (Some(first), Some(last)) = (path.first(), path.last()) {
+            self.doors.push(Doorway {
+                point: *first,
+                room: from,
+                corridor,
+            });
+            self.doors.push(Doorway {
+                point: *last,
+                room: to,
+                corridor,
+            });
+        }
+        self.corridors.push(Corridor {
+            rooms: (from, to),
+            path,
+        });
+        self.update_distances();
+    }
+
+    /// Rooms connected to `room` and the corridors that connect them
+    pub fn neighbours(&self, room: usize) -> impl Iterator<Item = (usize, &Corridor)> {
+        self.corridors
+            .iter()
+            .filter(move |corridor| corridor.rooms.0 == room || corridor.rooms.1 == room)
+            .map(move |corridor| (corridor.other(room), corridor))
+    }
+
+    /// Doors into a room
+    pub fn doors_of(&self, room: usize) -> impl Iterator<Item = &Doorway> {
+        self.doors.iter().filter(move |door| door.room == room)
+    }
+
+    pub fn entrance(&self) -> usize {
+        self.entrance
+    }
+
+    pub fn set_entrance(&mut self, room: usize) {
+        self.entrance = room;
+        self.update_distances();
+    }
+
+    /// How many corridors away from the entrance a room is. None if it can't
+    /// be reached.
+    pub fn distance(&self, room: usize) -> Option<usize> {
+        self.distances.get(room).copied().flatten()
+    }
+
+    /// The reachable room furthest from the entrance
+    pub fn furthest_room(&self) -> Option<usize> {
+        (0..self.rooms.len())
+            .filter_map(|room| self.distance(room).map(|distance| (distance, room)))
+            .max()
+            .map(|(_, room)| room)
+    }
+
+    /// Move everything by an offset, eg. when compositing a submap
+    pub fn translate(&mut self, offset: WorldVector) {
+        for room in self.rooms.iter_mut() {
+            room.origin += offset;
+        }
+        for corridor in self.corridors.iter_mut() {
+            for point in corridor.path.iter_mut() {
+                *point += offset;
+            }
+        }
+        for door in self.doors.iter_mut() {
+            door.point += offset;
+        }
+    }
+
+    /// Add another layout's rooms and corridors to this one
+    pub fn extend(&mut self, other: MapLayout) {
+        let rooms = self.rooms.len();
+        let corridors = self.corridors.len();
+
+        self.rooms.extend(other.rooms);
+        self.corridors
+            .extend(other.corridors.into_iter().map(|corridor| Corridor {
+                rooms: (corridor.rooms.0 + rooms, corridor.rooms.1 + rooms),
+                path: corridor.path,
+            }));
+        self.doors
+            .extend(other.doors.into_iter().map(|door| Doorway {
+                room: door.room + rooms,
+                corridor: door.corridor + corridors,
+                ..door
+            }));
+        self.update_distances();
+    }
+
+    fn update_distances(&mut self) {
+        self.distances = vec![None; self.rooms.len()];
+        if self.entrance >= self.rooms.len() {
+            return;
+        }
+
+        self.distances[self.entrance] = Some(0);
+        let mut queue = VecDeque::from([self.entrance]);
+        while let Some(room) = queue.pop_front() {
+            let distance = self.distances[room].unwrap_or_default() + 1;
+            let next: Vec<usize> = self.neighbours(room).map(|(next, _)| next).collect();
+            for next in next {
+                if self.distances[next].is_none() {
+                    self.distances[next] = Some(distance);
+                    queue.push_back(next);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{component::Cardinal, util::WorldSize};
+
+    use super::*;
+
+    /// Whether a corridor tile sits right outside a room's wall
+    fn is_next_to(room: &WorldRect, point: WorldPoint) -> bool {
+        [Cardinal::N, Cardinal::S, Cardinal::E, Cardinal::W]
+            .iter()
+            .any(|cardinal| room.contains(point + *cardinal.to_vector()))
+    }
+
+    fn path(from: WorldPoint, to: WorldPoint) -> Vec<WorldPoint> {
+        let mut points = vec![from];
+        let mut cursor = from;
+        while cursor != to {
+            cursor.x += (to.x - cursor.x).signum();
+            if cursor.x == to.x {
+                cursor.y += (to.y - cursor.y).signum();
+            }
+            points.push(cursor);
+        }
+        points
+    }
+
+    #[test]
+    fn splits_paths_through_rooms() {
+        let size = WorldSize::new(4, 4);
+        let mut layout = MapLayout::new(vec![
+            WorldRect::new(WorldPoint::new(1, 1), size),
+            WorldRect::new(WorldPoint::new(10, 1), size),
+            WorldRect::new(WorldPoint::new(20, 1), size),
+            WorldRect::new(WorldPoint::new(10, 20), size),
+        ]);
+
+        // 0 -> 2 straight through 1
+        layout.add_path(&path(WorldPoint::new(2, 2), WorldPoint::new(21, 2)));
+
+        assert_eq!(layout.corridors.len(), 2);
+        assert_eq!(layout.corridors[0].rooms, (0, 1));
+        assert_eq!(layout.corridors[1].rooms, (1, 2));
+        assert_eq!(layout.corridors[0].len(), 5);
+
+        let doors: Vec<WorldPoint> = layout.doors_of(1).map(|door| door.point).collect();
+        assert_eq!(doors, vec![WorldPoint::new(9, 2), WorldPoint::new(14, 2)]);
+        assert!(layout
+            .doors
+            .iter()
+            .all(|door| is_next_to(&layout.rooms[door.room], door.point)));
+
+        assert_eq!(layout.distance(0), Some(0));
+        assert_eq!(layout.distance(2), Some(2));
+        assert_eq!(layout.distance(3), None);
+        assert_eq!(layout.furthest_room(), Some(2));
+
+        layout.set_entrance(2);
+        assert_eq!(layout.distance(0), Some(2));
+        assert_eq!(layout.furthest_room(), Some(0));
+    }
+}
@@ -1,6 +1,18 @@
 mod tile;
 pub use tile::*;
 
+mod region;
+pub use region::*;
+
+mod layout;
+pub use layout::*;
+
+mod export;
+pub use export::*;
+
+mod analysis;
+pub use analysis::*;
+
 use euclid::{Point2D, Rect, Size2D};
 use fixedbitset::FixedBitSet;
 use hecs::Entity;
@@ -183,6 +195,10 @@ impl Map {
         self.revealed.contains(point.to_index(self.get_width()))
     }
 
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
     pub fn get_rect(&self) -> &Rect<i32, WorldSpace> {
         &self.rect
     }
@@ -233,6 +249,36 @@ impl Map {
 
         self.tiles[idx].is_opaque()
     }
+
+    /// Re-pick the shape of every wall at or next to the given points, eg.
+    /// after carving through or filling in part of a generated map
+    pub fn reshape_walls(&mut self, around: &[WorldPoint]) {
+        let mut walls: Vec<WorldPoint> = around
+            .iter()
+            .flat_map(|point| {
+                [
+                    Cardinal::N,
+                    Cardinal::S,
+                    Cardinal::E,
+                    Cardinal::W,
+                    Cardinal::NE,
+                    Cardinal::NW,
+                    Cardinal::SE,
+                    Cardinal::SW,
+                ]
+                .into_iter()
+                .map(move |cardinal| *point + *cardinal.to_vector())
+                .chain(std::iter::once(*point))
+            })
+            .filter(|point| matches!(self.get(*point), Some(Tile::Wall(_))))
+            .collect();
+        walls.sort_by_key(|point| (point.y, point.x));
+        walls.dedup();
+
+        for point in walls {
+            self[&point] = Tile::Wall(WallKind::from_map_position(self, point));
+        }
+    }
 }
 
 impl Index<&WorldPoint> for Map {
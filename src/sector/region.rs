@@ -0,0 +1,165 @@
+//! Connected areas of passable tiles in a [`Map`]
+use std::collections::VecDeque;
+
+use crate::{
+    component::Cardinal,
+    util::{PointExt, WorldPoint},
+};
+
+use super::Map;
+
+/// The directions a region spreads in. Matches how actors move (see [`Map::neighbors`]).
+pub(super) const DIRECTIONS: [Cardinal; 8] = [
+    Cardinal::N,
+    Cardinal::S,
+    Cardinal::E,
+    Cardinal::W,
+    Cardinal::NE,
+    Cardinal::NW,
+    Cardinal::SE,
+    Cardinal::SW,
+];
+
+/// Every tile's region label. Impassable tiles aren't in any region.
+/// Labels are ordered from the largest region to the smallest.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Regions {
+    width: i32,
+    labels: Vec<Option<usize>>,
+    sizes: Vec<usize>,
+}
+
+impl Regions {
+    pub fn len(&self) -> usize {
+        self.sizes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sizes.is_empty()
+    }
+
+    /// The region a point belongs to, if it's passable and in the map
+    pub fn label(&self, point: WorldPoint) -> Option<usize> {
+        if point.x < 0 || point.y < 0 || point.x >= self.width {
+            return None;
+        }
+        self.labels
+            .get(point.to_index(self.width))
+            .copied()
+            .flatten()
+    }
+
+    /// How many tiles are in a region
+    pub fn size(&self, label: usize) -> usize {
+        self.sizes.get(label).copied().unwrap_or_default()
+    }
+
+    pub fn largest(&self) -> Option<usize> {
+        (!self.is_empty()).then_some(0)
+    }
+
+    /// Every point in a region, in row-major order
+    pub fn points(&self, label: usize) -> impl Iterator<Item = WorldPoint> + '_ {
+        self.labels
+            .iter()
+            .enumerate()
+            .filter(move |(_, other)| **other == Some(label))
+            .map(|(idx, _)| WorldPoint::from_index(idx, self.width))
+    }
+}
+
+impl Map {
+    /// Label the connected areas of passable tiles, largest first
+    pub fn regions(&self) -> Regions {
+        let width = self.get_width();
+        let mut labels: Vec<Option<usize>> = vec![None; (width * self.get_height()) as usize];
+        let mut sizes = Vec::new();
+
+        for start in self.iter_points() {
+            if labels[start.to_index(width)].is_some() || !self[&start].is_passable() {
+                continue;
+            }
+
+            // Flood fill everything reachable from here
+            let label = sizes.len();
+            let mut size = 0;
+            let mut queue = VecDeque::from([start]);
+            labels[start.to_index(width)] = Some(label);
+
+            while let Some(point) = queue.pop_front() {
+                size += 1;
+                for cardinal in DIRECTIONS.iter() {
+                    let next = point + *cardinal.to_vector();
+                    let passable = self.get(next).is_some_and(|tile| tile.is_passable());
+                    if passable && labels[next.to_index(width)].is_none() {
+                        labels[next.to_index(width)] = Some(label);
+                        queue.push_back(next);
+                    }
+                }
+            }
+
+            sizes.push(size);
+        }
+
+        // Relabel so that the largest region is 0. Ties keep scan order.
+        let mut order: Vec<usize> = (0..sizes.len()).collect();
+        order.sort_by_key(|label| std::cmp::Reverse(sizes[*label]));
+        let mut relabel = vec![0; sizes.len()];
+        for (new, old) in order.iter().enumerate() {
+            relabel[*old] = new;
+        }
+
+        Regions {
+            width,
+            labels: labels
+                .into_iter()
+                .map(|label| label.map(|label| relabel[label]))
+                .collect(),
+            sizes: order.iter().map(|label| sizes[*label]).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sector::{FloorKind, Tile, WallKind};
+
+    use super::*;
+
+    #[test]
+    fn labels_regions() {
+        let w = Tile::Wall(WallKind::default());
+        let f = Tile::Floor(FloorKind::default());
+
+        #[rustfmt::skip]
+        let tiles: Vec<Tile> = vec![
+            f, w, f, f, f,
+            w, w, w, w, w,
+            f, f, w, f, w,
+            f, w, w, w, f,
+        ];
+        let map = Map::new(String::from("test"), 5, 4, tiles);
+        let regions = map.regions();
+
+        assert_eq!(regions.len(), 4);
+        assert_eq!(regions.size(0), 3);
+        assert_eq!(
+            regions.size(regions.label(WorldPoint::new(3, 0)).unwrap()),
+            3
+        );
+        assert_eq!(regions.size(3), 1);
+        // Diagonals connect, the same way actors move
+        assert_eq!(
+            regions.label(WorldPoint::new(3, 2)),
+            regions.label(WorldPoint::new(4, 3))
+        );
+        assert_eq!(regions.label(WorldPoint::new(1, 0)), None);
+        assert_eq!(regions.label(WorldPoint::new(9, 9)), None);
+        assert_eq!(
+            regions
+                .points(regions.label(WorldPoint::new(0, 2)).unwrap())
+                .count(),
+            3
+        );
+    }
+}
@@ -7,7 +7,11 @@ pub use floor::*;
 use macroquad::prelude::Color;
 use serde::{Deserialize, Serialize};
 
-use crate::{color::EMPTY, resource::Resources, util::PixelPoint};
+use crate::{
+    color::{COMMON, EMPTY, FIRE, PLANT, WATER},
+    resource::Resources,
+    util::PixelPoint,
+};
 pub enum VisibilityKind {
     Torch { brightness: u32 },
     DiscoBall { value: u32 },
@@ -135,3 +139,36 @@ impl Tile {
         }
     }
 }
+
+/// Sprite in `tileset/tileset_transparent.tsx` that a tile is drawn with
+pub fn tile_sprite(tile: Tile) -> u32 {
+    match tile {
+        Tile::Floor(FloorKind::FloorDefault) => 6,
+        Tile::Floor(FloorKind::FloorInterior) => 755,
+        Tile::Floor(FloorKind::FloorScenery('~')) => 253,
+        Tile::Floor(FloorKind::FloorScenery(',')) => 6,
+        Tile::Floor(FloorKind::FloorScenery('*')) => 554,
+        Tile::Floor(FloorKind::FloorScenery('░')) => 7,
+        Tile::Floor(FloorKind::FloorScenery('▒')) => 8,
+        Tile::Floor(FloorKind::FloorScenery('▓')) => 9,
+        Tile::Floor(FloorKind::FloorScenery(_)) => 6,
+        Tile::Wall(WallKind::WallPillar) => 393,
+        Tile::Wall(_) => 790,
+    }
+}
+
+/// Tint for a tile's sprite
+pub fn tile_color(tile: Tile) -> Color {
+    match tile {
+        Tile::Floor(FloorKind::FloorDefault) => COMMON.two,
+        Tile::Floor(FloorKind::FloorInterior) => COMMON.four,
+        Tile::Floor(FloorKind::FloorScenery('~')) => WATER.three,
+        Tile::Floor(FloorKind::FloorScenery(',')) => COMMON.three,
+        Tile::Floor(FloorKind::FloorScenery('*')) => FIRE.two,
+        Tile::Floor(FloorKind::FloorScenery('░')) => COMMON.two,
+        Tile::Floor(FloorKind::FloorScenery('▒')) => COMMON.three,
+        Tile::Floor(FloorKind::FloorScenery('▓')) => COMMON.four,
+        Tile::Floor(FloorKind::FloorScenery(_)) => PLANT.three,
+        Tile::Wall(_) => COMMON.five,
+    }
+}